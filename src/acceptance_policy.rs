@@ -0,0 +1,106 @@
+use crate::address_book::address_book_entry;
+use crate::constants::{PREFIX_ACCEPTANCE_POLICIES, PREFIX_BLOCKED_ADDRESSES};
+use cosmwasm_std::{
+    CanonicalAddr, HumanAddr, ReadonlyStorage, StdError, StdResult, Storage, Uint128,
+};
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+use schemars::JsonSchema;
+use secret_toolkit::storage::{TypedStore, TypedStoreMut};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Default, PartialEq)]
+pub struct AcceptancePolicy {
+    pub contacts_only: bool,
+    pub minimum_amount: Option<Uint128>,
+    pub allowed_tokens: Option<Vec<HumanAddr>>,
+    // Receive requests wait for the user to acknowledge them before they can be paid
+    pub acknowledge_receive_requests: bool,
+}
+
+// Storage functions:
+pub fn acceptance_policy<S: ReadonlyStorage>(
+    storage: &S,
+    owner: &CanonicalAddr,
+) -> StdResult<AcceptancePolicy> {
+    let store = ReadonlyPrefixedStorage::new(PREFIX_ACCEPTANCE_POLICIES, storage);
+    let policy: Option<AcceptancePolicy> = TypedStore::attach(&store).may_load(owner.as_slice())?;
+
+    Ok(policy.unwrap_or_default())
+}
+
+pub fn set_acceptance_policy<S: Storage>(
+    storage: &mut S,
+    owner: &CanonicalAddr,
+    policy: &AcceptancePolicy,
+) -> StdResult<()> {
+    let mut store = PrefixedStorage::new(PREFIX_ACCEPTANCE_POLICIES, storage);
+    TypedStoreMut::attach(&mut store).store(owner.as_slice(), policy)
+}
+
+pub fn set_blocked<S: Storage>(
+    storage: &mut S,
+    owner: &CanonicalAddr,
+    address: &CanonicalAddr,
+    blocked: bool,
+) -> StdResult<()> {
+    let mut store =
+        PrefixedStorage::multilevel(&[PREFIX_BLOCKED_ADDRESSES, owner.as_slice()], storage);
+    if blocked {
+        TypedStoreMut::attach(&mut store).store(address.as_slice(), &blocked)
+    } else {
+        TypedStoreMut::<bool, _>::attach(&mut store).remove(address.as_slice());
+        Ok(())
+    }
+}
+
+pub fn is_blocked<S: ReadonlyStorage>(
+    storage: &S,
+    owner: &CanonicalAddr,
+    address: &CanonicalAddr,
+) -> StdResult<bool> {
+    let store =
+        ReadonlyPrefixedStorage::multilevel(&[PREFIX_BLOCKED_ADDRESSES, owner.as_slice()], storage);
+    let blocked: Option<bool> = TypedStore::attach(&store).may_load(address.as_slice())?;
+
+    Ok(blocked.unwrap_or(false))
+}
+
+// Verify that the counterparty of a new request accepts it from the creator
+pub fn verify_acceptance<S: ReadonlyStorage>(
+    storage: &S,
+    counterparty: &CanonicalAddr,
+    creator: &CanonicalAddr,
+    amount: Uint128,
+    token_address: &HumanAddr,
+    receive_request: bool,
+) -> StdResult<()> {
+    if is_blocked(storage, counterparty, creator)? {
+        return Err(StdError::generic_err(
+            "Request not accepted by counterparty.",
+        ));
+    }
+
+    let policy = acceptance_policy(storage, counterparty)?;
+    if let Some(minimum_amount) = policy.minimum_amount {
+        if amount < minimum_amount {
+            return Err(StdError::generic_err(
+                "Amount is below counterparty's minimum.",
+            ));
+        }
+    }
+    if let Some(allowed_tokens) = policy.allowed_tokens {
+        if !allowed_tokens.contains(token_address) {
+            return Err(StdError::generic_err("Token not accepted by counterparty."));
+        }
+    }
+    if receive_request
+        && policy.contacts_only
+        && address_book_entry(storage, counterparty, creator)?.is_none()
+    {
+        return Err(StdError::generic_err(
+            "Counterparty only accepts receive requests from contacts.",
+        ));
+    }
+
+    Ok(())
+}
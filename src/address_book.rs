@@ -0,0 +1,115 @@
+use crate::constants::{PREFIX_ADDRESS_BOOK, PREFIX_ADDRESS_BOOK_INDEX};
+use cosmwasm_std::{CanonicalAddr, HumanAddr, ReadonlyStorage, StdError, StdResult, Storage};
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+use schemars::JsonSchema;
+use secret_toolkit::storage::{AppendStore, AppendStoreMut, TypedStore, TypedStoreMut};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct AddressBookEntry {
+    pub label: Option<String>,
+    pub address: HumanAddr,
+    pub verified_at: u64,
+}
+
+// Storage functions:
+pub fn get_address_book_entries<S: ReadonlyStorage>(
+    storage: &S,
+    owner: &CanonicalAddr,
+    page: u32,
+    page_size: u32,
+) -> StdResult<(Vec<AddressBookEntry>, u64)> {
+    let store =
+        ReadonlyPrefixedStorage::multilevel(&[PREFIX_ADDRESS_BOOK, owner.as_slice()], storage);
+
+    // Try to access the address book for the account.
+    // If it doesn't exist yet, return an empty list of entries.
+    let store = AppendStore::<AddressBookEntry, _, _>::attach(&store);
+    let store = if let Some(result) = store {
+        result?
+    } else {
+        return Ok((vec![], 0));
+    };
+
+    let entries: StdResult<Vec<AddressBookEntry>> = store
+        .iter()
+        .rev()
+        .skip((page * page_size) as _)
+        .take(page_size as _)
+        .collect();
+    entries.map(|entries| (entries, store.len() as u64))
+}
+
+pub fn address_book_entry<S: ReadonlyStorage>(
+    storage: &S,
+    owner: &CanonicalAddr,
+    address: &CanonicalAddr,
+) -> StdResult<Option<AddressBookEntry>> {
+    let index_store = ReadonlyPrefixedStorage::multilevel(
+        &[PREFIX_ADDRESS_BOOK_INDEX, owner.as_slice()],
+        storage,
+    );
+    let position: Option<u32> = TypedStore::attach(&index_store).may_load(address.as_slice())?;
+    if position.is_none() {
+        return Ok(None);
+    }
+
+    let store =
+        ReadonlyPrefixedStorage::multilevel(&[PREFIX_ADDRESS_BOOK, owner.as_slice()], storage);
+    let store = AppendStore::<AddressBookEntry, _, _>::attach(&store).unwrap()?;
+    Ok(Some(store.get_at(position.unwrap())?))
+}
+
+// Adds the address to the owner's address book, or refreshes verified_at if it's already there.
+pub fn store_verified_address<S: Storage>(
+    storage: &mut S,
+    owner: &CanonicalAddr,
+    address: &CanonicalAddr,
+    human_address: HumanAddr,
+    verified_at: u64,
+) -> StdResult<()> {
+    let mut index_store =
+        PrefixedStorage::multilevel(&[PREFIX_ADDRESS_BOOK_INDEX, owner.as_slice()], storage);
+    let position: Option<u32> =
+        TypedStoreMut::attach(&mut index_store).may_load(address.as_slice())?;
+
+    let mut store = PrefixedStorage::multilevel(&[PREFIX_ADDRESS_BOOK, owner.as_slice()], storage);
+    let mut store = AppendStoreMut::<AddressBookEntry, _, _>::attach_or_create(&mut store)?;
+    if let Some(position) = position {
+        let mut entry = store.get_at(position)?;
+        entry.verified_at = verified_at;
+        return store.set_at(position, &entry);
+    }
+
+    let position = store.len();
+    store.push(&AddressBookEntry {
+        label: None,
+        address: human_address,
+        verified_at,
+    })?;
+    let mut index_store =
+        PrefixedStorage::multilevel(&[PREFIX_ADDRESS_BOOK_INDEX, owner.as_slice()], storage);
+    TypedStoreMut::attach(&mut index_store).store(address.as_slice(), &position)
+}
+
+pub fn update_address_book_label<S: Storage>(
+    storage: &mut S,
+    owner: &CanonicalAddr,
+    address: &CanonicalAddr,
+    label: Option<String>,
+) -> StdResult<()> {
+    let index_store = ReadonlyPrefixedStorage::multilevel(
+        &[PREFIX_ADDRESS_BOOK_INDEX, owner.as_slice()],
+        storage,
+    );
+    let position: Option<u32> = TypedStore::attach(&index_store).may_load(address.as_slice())?;
+    if position.is_none() {
+        return Err(StdError::generic_err("Address is not in address book."));
+    }
+
+    let mut store = PrefixedStorage::multilevel(&[PREFIX_ADDRESS_BOOK, owner.as_slice()], storage);
+    let mut store = AppendStoreMut::<AddressBookEntry, _, _>::attach_or_create(&mut store)?;
+    let mut entry = store.get_at(position.unwrap())?;
+    entry.label = label;
+    store.set_at(position.unwrap(), &entry)
+}
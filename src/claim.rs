@@ -0,0 +1,41 @@
+use crate::constants::PREFIX_CLAIMS;
+use crate::state::{hash_code, SecretContract};
+use cosmwasm_std::{HumanAddr, ReadonlyStorage, StdResult, Storage, Uint128};
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+use schemars::JsonSchema;
+use secret_toolkit::storage::{TypedStore, TypedStoreMut};
+use serde::{Deserialize, Serialize};
+
+// Funds escrowed by the sender that can be claimed by whoever knows the code until it expires
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct Claim {
+    pub sender: HumanAddr,
+    pub send_amount: Uint128,
+    pub token: SecretContract,
+    pub description: Option<String>,
+    pub fee: Uint128,
+    pub fee_token: SecretContract,
+    pub funded: bool,
+    pub expires_at: u64,
+}
+
+// Storage functions:
+// Claims are stored under the SHA-256 hash of their code
+pub fn claim<S: ReadonlyStorage>(storage: &S, code_hash: &[u8]) -> StdResult<Option<Claim>> {
+    let store = ReadonlyPrefixedStorage::new(PREFIX_CLAIMS, storage);
+    TypedStore::attach(&store).may_load(code_hash)
+}
+
+pub fn claim_by_code<S: ReadonlyStorage>(storage: &S, code: &str) -> StdResult<Option<Claim>> {
+    claim(storage, &hash_code(code))
+}
+
+pub fn remove_claim<S: Storage>(storage: &mut S, code_hash: &[u8]) {
+    let mut store = PrefixedStorage::new(PREFIX_CLAIMS, storage);
+    TypedStoreMut::<Claim, _>::attach(&mut store).remove(code_hash);
+}
+
+pub fn store_claim<S: Storage>(storage: &mut S, code_hash: &[u8], claim: &Claim) -> StdResult<()> {
+    let mut store = PrefixedStorage::new(PREFIX_CLAIMS, storage);
+    TypedStoreMut::attach(&mut store).store(code_hash, claim)
+}
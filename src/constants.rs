@@ -1,4 +1,39 @@
 pub const BLOCK_SIZE: usize = 256;
+pub const DESCRIPTION_BLOCK_SIZE: usize = 64;
+pub const DESCRIPTION_LENGTH_WIDTH: usize = 4;
+pub const FIXED_SIZE_TX_LENGTH: usize = 2048;
+pub const MAX_DESCRIPTION_LENGTH: usize = 256;
+pub const MAX_TX_PAGE_SIZE: u32 = 50;
+pub const MAX_MILESTONES: usize = 5;
+pub const MAX_PREIMAGE_LENGTH: usize = 64;
+pub const MAX_TOKEN_FIELD_LENGTH: usize = 64;
 pub const CONFIG_KEY: &[u8] = b"config";
+pub const ORGANIZATION_COUNT_KEY: &[u8] = b"organization_count";
+pub const SHAPE_PADDING_KEY: &[u8] = b"shape_padding";
+pub const STATS_KEY: &[u8] = b"stats";
+pub const PREFIX_ACCEPTANCE_POLICIES: &[u8] = b"acceptance_policies";
+pub const PREFIX_ADDRESS_BOOK: &[u8] = b"address_book";
+pub const PREFIX_ADDRESS_BOOK_INDEX: &[u8] = b"address_book_index";
+pub const PREFIX_BLOCKED_ADDRESSES: &[u8] = b"blocked_addresses";
+pub const PREFIX_CLAIMS: &[u8] = b"claims";
+pub const PREFIX_COUNTERPARTY_SPENDING: &[u8] = b"counterparty_spending";
+pub const PREFIX_DAILY_FEE_STATS: &[u8] = b"daily_fee_stats";
+pub const PREFIX_DAILY_STATS: &[u8] = b"daily_stats";
+pub const PREFIX_DAILY_VOLUMES: &[u8] = b"daily_volumes";
+pub const PREFIX_ESCROWED_PAYMENTS: &[u8] = b"escrowed_payments";
+pub const PREFIX_FEE_DISCOUNT_KEYS: &[u8] = b"fee_discount_keys";
+pub const PREFIX_FEE_EXEMPTIONS: &[u8] = b"fee_exemptions";
+pub const PREFIX_FEE_STATS: &[u8] = b"fee_stats";
+pub const PREFIX_ORGANIZATION_AUDIT_TRAILS: &[u8] = b"organization_audit_trails";
+pub const PREFIX_ORGANIZATIONS: &[u8] = b"organizations";
+pub const PREFIX_PARTICIPANTS: &[u8] = b"participants";
+pub const PREFIX_PAID_COUNTERPARTIES: &[u8] = b"paid_counterparties";
+pub const PREFIX_PAYMENT_APPROVALS: &[u8] = b"payment_approvals";
+pub const PREFIX_PAYMENT_LINKS: &[u8] = b"payment_links";
+pub const PREFIX_REFERRAL_EARNINGS: &[u8] = b"referral_earnings";
+pub const PREFIX_SPENDING: &[u8] = b"spending";
+pub const PREFIX_SPENDING_LIMITS: &[u8] = b"spending_limits";
 pub const PREFIX_TXS: &[u8] = b"txs";
+pub const PREFIX_VIEWER_GRANTS: &[u8] = b"viewer_grants";
+pub const PREFIX_VOLUMES: &[u8] = b"volumes";
 pub const PREFIX_REGISTERED_TOKENS: &[u8] = b"registered_tokens";
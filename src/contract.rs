@@ -1,19 +1,59 @@
+use crate::acceptance_policy::{
+    acceptance_policy, set_acceptance_policy, set_blocked, verify_acceptance, AcceptancePolicy,
+};
+use crate::address_book::{
+    address_book_entry, get_address_book_entries, store_verified_address, update_address_book_label,
+};
 use crate::authorize::authorize;
-use crate::constants::{BLOCK_SIZE, CONFIG_KEY};
+use crate::claim::{claim, claim_by_code, remove_claim, store_claim, Claim};
+use crate::constants::{
+    BLOCK_SIZE, CONFIG_KEY, FIXED_SIZE_TX_LENGTH, MAX_MILESTONES, MAX_PREIMAGE_LENGTH,
+    MAX_TX_PAGE_SIZE,
+};
+use crate::fees::{
+    add_referral_earnings, fee_discount_key, fee_stats, get_daily_fee_stats, is_fee_exempt,
+    record_fees, referral_earnings, set_fee_discount_key, set_fee_exempt,
+};
+use crate::organizations::{
+    append_audit_entry, escrowed_payment, get_audit_trail, next_organization_address, organization,
+    payment_approvals, set_escrowed_payment, set_payment_approvals, store_organization,
+    ApprovalPolicy, EscrowedPayment, Organization, OrganizationAction, OrganizationAuditEntry,
+    OrganizationMember, OrganizationRole, PaymentApproval,
+};
+use crate::payment_link::{
+    payment_link, payment_link_by_code, remove_payment_link, store_payment_link, PaymentLink,
+};
+use crate::shape::{BorrowedQuerier, CountingStorage, SHAPE_MESSAGES};
+use crate::spending_limits::{
+    record_spending, release_spending, set_spending_limit_settings, spending_limit_settings,
+    PendingSpendingLimits, SpendingLimits, SPENDING_LIMITS_DELAY,
+};
+use crate::stats::{get_daily_stats, record_participants, record_stats, record_volume, stats};
 use crate::transaction_history::{
-    get_txs, store_txs, update_tx, verify_txs, verify_txs_for_cancel,
-    verify_txs_for_confirm_address,
+    get_filtered_txs, get_txs, prune_txs, store_milestone_release_txs, store_refund_txs, store_txs,
+    tx_at_position, update_tx, verify_text_length, verify_token, verify_txs,
+    verify_txs_for_acknowledgement, verify_txs_for_cancel, verify_txs_for_confirm_address,
+    verify_txs_for_refund, CancellationPolicy, HumanizedTx, Milestone, MilestoneStatus, NewTx, Tx,
+    TxTerms,
 };
+use crate::viewers::{set_viewer_grant, viewer_grant, ViewerGrant, ViewerScope};
 use crate::{
-    msg::{HandleMsg, InitMsg, QueryAnswer, QueryMsg, ReceiveMsg},
-    state::{Config, RegisteredTokensStorage, SecretContract},
+    msg::{
+        ClaimRequest, HandleAnswer, HandleMsg, InitMsg, PaymentLinkRequest, QueryAnswer, QueryMsg,
+        ReceiveMsg, ReceiveRequest, SendRequest,
+    },
+    state::{
+        hash_code, Config, FeeDiscount, FeeToken, RegisteredTokensReadonlyStorage,
+        RegisteredTokensStorage, SecretContract, TreasuryRecipient,
+    },
 };
 use cosmwasm_std::{
-    from_binary, to_binary, Api, Binary, CosmosMsg, Env, Extern, HandleResponse, HumanAddr,
-    InitResponse, Querier, StdError, StdResult, Storage, Uint128,
+    from_binary, to_binary, Api, Binary, CanonicalAddr, CosmosMsg, Env, Extern, HandleResponse,
+    HumanAddr, InitResponse, Querier, StdError, StdResult, Storage, Uint128,
 };
 use secret_toolkit::snip20;
 use secret_toolkit::storage::{TypedStore, TypedStoreMut};
+use sha2::{Digest, Sha256};
 
 pub fn init<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
@@ -23,11 +63,16 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
     let mut config_store = TypedStoreMut::attach(&mut deps.storage);
     let config: Config = Config {
         admin: env.message.sender,
+        constant_shape: false,
         fee: msg.fee,
+        fee_discounts: vec![],
+        fee_tokens: vec![],
         new_admin_nomination: None,
+        referral_share_bps: 0,
         shade: msg.shade.clone(),
         sscrt: msg.sscrt.clone(),
         treasury_address: msg.treasury_address,
+        treasury_recipients: vec![],
     };
     config_store.store(CONFIG_KEY, &config)?;
 
@@ -47,14 +92,180 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
 ) -> StdResult<HandleResponse> {
     match msg {
         HandleMsg::AcceptNewAdminNomination {} => accept_new_admin_nomination(deps, &env),
+        HandleMsg::AcknowledgeRequest { position } => acknowledge_request(deps, &env, position),
+        HandleMsg::ApprovePayment {
+            organization,
+            position,
+        } => approve_payment(deps, &env, organization, position),
+        HandleMsg::BlockAddress { address } => block_address(deps, &env, address, true),
+        HandleMsg::Cancel { position, reason } => {
+            allowance_payment(deps, env, ReceiveMsg::Cancel { position, reason }, None)
+        }
+        HandleMsg::CancelMilestone { position, index } => {
+            cancel_milestone(deps, &env, position, index)
+        }
+        HandleMsg::Claim { code, recipient } => redeem_claim(deps, &env, code, recipient),
+        HandleMsg::ClaimHtlc { position, preimage } => claim_htlc(deps, &env, position, preimage),
+        HandleMsg::ClosePaymentLink { code_hash } => close_payment_link(deps, &env, code_hash),
+        HandleMsg::ConfirmAddress { position } => {
+            allowance_payment(deps, env, ReceiveMsg::ConfirmAddress { position }, None)
+        }
+        HandleMsg::CreateOrganization { name } => create_organization(deps, &env, name),
+        HandleMsg::CreateReceiveRequest {
+            address,
+            send_amount,
+            description,
+            token,
+            referrer,
+            cancellation_policy,
+            cancellation_penalty,
+            attachment_hash,
+            fee_token,
+        } => allowance_payment(
+            deps,
+            env,
+            ReceiveMsg::CreateReceiveRequest {
+                address,
+                send_amount,
+                description,
+                token,
+                referrer,
+                cancellation_policy,
+                cancellation_penalty,
+                attachment_hash,
+            },
+            fee_token,
+        ),
+        HandleMsg::CreateSendRequest {
+            address,
+            send_amount,
+            description,
+            token,
+            skip_confirmation_if_verified,
+            referrer,
+            cancellation_policy,
+            cancellation_penalty,
+            hash_timelock,
+            milestones,
+            attachment_hash,
+            fee_token,
+        } => allowance_payment(
+            deps,
+            env,
+            ReceiveMsg::CreateSendRequest {
+                address,
+                send_amount,
+                description,
+                token,
+                skip_confirmation_if_verified,
+                referrer,
+                cancellation_policy,
+                cancellation_penalty,
+                hash_timelock,
+                milestones,
+                attachment_hash,
+            },
+            fee_token,
+        ),
+        HandleMsg::Decline { position, reason } => decline(deps, &env, position, reason),
+        HandleMsg::GrantViewer {
+            address,
+            scope,
+            expires_at,
+        } => grant_viewer(deps, &env, address, scope, expires_at),
         HandleMsg::NominateNewAdmin { address } => nominate_new_admin(deps, &env, address),
+        HandleMsg::OnBehalfOf { organization, msg } => on_behalf_of(deps, env, organization, *msg),
+        HandleMsg::PruneHistory {
+            before_position,
+            before_time,
+        } => prune_history(deps, &env, before_position, before_time),
         HandleMsg::Receive {
             from, amount, msg, ..
-        } => receive(deps, env, from, amount, msg),
+        } => {
+            let received_token = env.message.sender.clone();
+            receive(deps, env, from, amount, &received_token, from_binary(&msg)?)
+        }
+        HandleMsg::Reclaim { code_hash } => reclaim(deps, &env, code_hash),
+        HandleMsg::RefundHtlc { position } => refund_htlc(deps, &env, position),
+        HandleMsg::RejectPayment {
+            organization,
+            position,
+        } => reject_payment(deps, &env, organization, position),
+        HandleMsg::ReleaseMilestone { position, index } => {
+            release_milestone(deps, &env, position, index)
+        }
+        HandleMsg::RevokeViewer { address } => revoke_viewer(deps, &env, address),
+        HandleMsg::SendPayment { position } => {
+            allowance_payment(deps, env, ReceiveMsg::SendPayment { position }, None)
+        }
+        HandleMsg::SetAcceptancePolicy {
+            contacts_only,
+            minimum_amount,
+            allowed_tokens,
+            acknowledge_receive_requests,
+        } => update_acceptance_policy(
+            deps,
+            &env,
+            AcceptancePolicy {
+                contacts_only,
+                minimum_amount,
+                allowed_tokens,
+                acknowledge_receive_requests: acknowledge_receive_requests.unwrap_or(false),
+            },
+        ),
+        HandleMsg::SetAddressBookLabel { address, label } => {
+            set_address_book_label(deps, &env, address, label)
+        }
+        HandleMsg::SetApprovalPolicies {
+            organization,
+            approval_policies,
+        } => set_approval_policies(deps, &env, organization, approval_policies),
+        HandleMsg::SetFeeDiscountKey { token, key } => {
+            update_fee_discount_key(deps, &env, token, key)
+        }
+        HandleMsg::SetFeeExemption { address, exempt } => {
+            update_fee_exemption(deps, &env, address, exempt)
+        }
+        HandleMsg::SetMemo { position, memo } => set_memo(deps, &env, position, memo),
+        HandleMsg::SetSpendingLimits {
+            token_limits,
+            cooling_off_period,
+        } => update_spending_limits(
+            deps,
+            &env,
+            SpendingLimits {
+                token_limits,
+                cooling_off_period,
+            },
+        ),
+        HandleMsg::SetOrganizationMember {
+            organization,
+            address,
+            role,
+        } => set_organization_member(deps, &env, organization, address, role),
+        HandleMsg::UnblockAddress { address } => block_address(deps, &env, address, false),
+        HandleMsg::UpdateConstantShape { constant_shape } => {
+            update_constant_shape(deps, &env, constant_shape)
+        }
         HandleMsg::UpdateFee { fee } => update_fee(deps, &env, fee),
+        HandleMsg::UpdateFeeDiscounts { fee_discounts } => {
+            update_fee_discounts(deps, &env, fee_discounts)
+        }
+        HandleMsg::UpdateFeeTokens { fee_tokens } => update_fee_tokens(deps, &env, fee_tokens),
+        HandleMsg::UpdateReferralShare { referral_share_bps } => {
+            update_referral_share(deps, &env, referral_share_bps)
+        }
         HandleMsg::UpdateTreasuryAddress { address } => {
             update_treasury_address(deps, &env, address)
         }
+        HandleMsg::UpdateTreasuryRecipients {
+            treasury_recipients,
+        } => update_treasury_recipients(deps, &env, treasury_recipients),
+        HandleMsg::WithdrawFees {
+            token,
+            amount,
+            recipient,
+        } => withdraw_fees(deps, &env, token, amount, recipient),
     }
 }
 
@@ -62,18 +273,266 @@ pub fn query<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
     msg: QueryMsg,
 ) -> StdResult<Binary> {
-    match msg {
+    let response = match msg {
+        QueryMsg::AcceptancePolicy { address, key } => query_acceptance_policy(deps, address, key),
+        QueryMsg::AddressBook {
+            address,
+            key,
+            page,
+            page_size,
+        } => address_book(deps, address, key, page, page_size),
+        QueryMsg::Claim { code } => query_claim(deps, code),
         QueryMsg::Config {} => {
             let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
             Ok(to_binary(&config)?)
         }
+        QueryMsg::DailyStats {
+            key,
+            page,
+            page_size,
+        } => query_daily_stats(deps, key, page, page_size),
+        QueryMsg::EffectiveFee {
+            address,
+            key,
+            token,
+        } => query_effective_fee(deps, address, key, token),
+        QueryMsg::FeeStats {
+            key,
+            token,
+            page,
+            page_size,
+        } => query_fee_stats(deps, key, token, page, page_size),
+        QueryMsg::Organization {
+            organization,
+            address,
+            key,
+        } => query_organization(deps, organization, address, key),
+        QueryMsg::OrganizationAuditTrail {
+            organization,
+            address,
+            key,
+            page,
+            page_size,
+        } => query_organization_audit_trail(deps, organization, address, key, page, page_size),
+        QueryMsg::PaymentApprovals {
+            organization,
+            address,
+            key,
+            position,
+        } => query_payment_approvals(deps, organization, address, key, position),
+        QueryMsg::PaymentLink { code } => query_payment_link(deps, code),
+        QueryMsg::ReferralEarnings {
+            address,
+            key,
+            token,
+        } => query_referral_earnings(deps, address, key, token),
+        QueryMsg::SpendingLimits { address, key, time } => {
+            query_spending_limits(deps, address, key, time)
+        }
+        QueryMsg::Stats {} => {
+            let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
+            let stats = stats(&deps.storage, &config.volume_tokens())?.into_coarse();
+            to_binary(&QueryAnswer::Stats { stats })
+        }
         QueryMsg::Txs {
             address,
             key,
             page,
             page_size,
-        } => txs(deps, address, key, page, page_size),
-    }
+            fixed_size,
+            viewer,
+            time,
+        } => txs_scope(deps, &address, key, viewer, time).and_then(|scope| {
+            tx_page(
+                deps,
+                &deps.api.canonical_address(&address)?,
+                page,
+                page_size,
+                fixed_size.unwrap_or(false),
+                scope,
+            )
+        }),
+    };
+    pad_query_result(response)
+}
+
+fn query_acceptance_policy<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: HumanAddr,
+    key: String,
+) -> StdResult<Binary> {
+    verify_viewing_key(deps, address.clone(), key)?;
+
+    let policy = acceptance_policy(&deps.storage, &deps.api.canonical_address(&address)?)?;
+    to_binary(&QueryAnswer::AcceptancePolicy { policy })
+}
+
+fn address_book<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: HumanAddr,
+    key: String,
+    page: u32,
+    page_size: u32,
+) -> StdResult<Binary> {
+    verify_viewing_key(deps, address.clone(), key)?;
+
+    let address = deps.api.canonical_address(&address)?;
+    let (entries, total) = get_address_book_entries(&deps.storage, &address, page, page_size)?;
+
+    let result = QueryAnswer::AddressBook {
+        entries,
+        total: Some(total),
+    };
+    to_binary(&result)
+}
+
+fn query_claim<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    code: String,
+) -> StdResult<Binary> {
+    let claim = claim_by_code(&deps.storage, &code)?
+        .ok_or_else(|| StdError::generic_err("Claim not found."))?;
+    to_binary(&QueryAnswer::Claim { claim })
+}
+
+fn query_daily_stats<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    key: String,
+    page: u32,
+    page_size: u32,
+) -> StdResult<Binary> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
+    verify_viewing_key(deps, config.admin.clone(), key)?;
+
+    let (daily_stats, total) =
+        get_daily_stats(&deps.storage, &config.volume_tokens(), page, page_size)?;
+    let result = QueryAnswer::DailyStats {
+        daily_stats,
+        total: Some(total),
+    };
+    to_binary(&result)
+}
+
+fn query_effective_fee<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: HumanAddr,
+    key: String,
+    token: Option<HumanAddr>,
+) -> StdResult<Binary> {
+    verify_viewing_key(deps, address.clone(), key)?;
+
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
+    let fee_token = accepted_fee_token(&config, token)?;
+    let fee = effective_fee(deps, &config, &address, fee_token.fee)?;
+    to_binary(&QueryAnswer::EffectiveFee { fee })
+}
+
+fn query_fee_stats<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    key: String,
+    token: HumanAddr,
+    page: u32,
+    page_size: u32,
+) -> StdResult<Binary> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
+    verify_viewing_key(deps, config.admin, key)?;
+
+    let stats = fee_stats(&deps.storage, &token)?;
+    let (daily_stats, total) = get_daily_fee_stats(&deps.storage, &token, page, page_size)?;
+    let result = QueryAnswer::FeeStats {
+        stats,
+        daily_stats,
+        total: Some(total),
+    };
+    to_binary(&result)
+}
+
+fn query_organization<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    organization: HumanAddr,
+    address: HumanAddr,
+    key: String,
+) -> StdResult<Binary> {
+    verify_viewing_key(deps, address.clone(), key)?;
+
+    let (_, organization) =
+        member_organization(deps, &organization, &address, OrganizationRole::Viewer)?;
+    to_binary(&QueryAnswer::Organization { organization })
+}
+
+fn query_organization_audit_trail<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    organization: HumanAddr,
+    address: HumanAddr,
+    key: String,
+    page: u32,
+    page_size: u32,
+) -> StdResult<Binary> {
+    verify_viewing_key(deps, address.clone(), key)?;
+
+    let (organization, _) =
+        member_organization(deps, &organization, &address, OrganizationRole::Viewer)?;
+    let (entries, total) = get_audit_trail(&deps.storage, &organization, page, page_size)?;
+    let result = QueryAnswer::OrganizationAuditTrail {
+        entries,
+        total: Some(total),
+    };
+    to_binary(&result)
+}
+
+fn query_payment_approvals<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    organization: HumanAddr,
+    address: HumanAddr,
+    key: String,
+    position: u32,
+) -> StdResult<Binary> {
+    verify_viewing_key(deps, address.clone(), key)?;
+
+    let (organization, _) =
+        member_organization(deps, &organization, &address, OrganizationRole::Viewer)?;
+    let approvals = payment_approvals(&deps.storage, &organization, position)?;
+    to_binary(&QueryAnswer::PaymentApprovals { approvals })
+}
+
+fn query_payment_link<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    code: String,
+) -> StdResult<Binary> {
+    let payment_link = payment_link_by_code(&deps.storage, &code)?
+        .ok_or_else(|| StdError::generic_err("Payment link not found."))?;
+    to_binary(&QueryAnswer::PaymentLink { payment_link })
+}
+
+fn query_referral_earnings<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: HumanAddr,
+    key: String,
+    token: Option<HumanAddr>,
+) -> StdResult<Binary> {
+    verify_viewing_key(deps, address.clone(), key)?;
+
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
+    let fee_token = accepted_fee_token(&config, token)?;
+    let amount = referral_earnings(
+        &deps.storage,
+        &deps.api.canonical_address(&address)?,
+        &fee_token.token.address,
+    )?;
+    to_binary(&QueryAnswer::ReferralEarnings { amount })
+}
+
+fn query_spending_limits<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: HumanAddr,
+    key: String,
+    time: u64,
+) -> StdResult<Binary> {
+    verify_viewing_key(deps, address.clone(), key)?;
+
+    let settings =
+        spending_limit_settings(&deps.storage, &deps.api.canonical_address(&address)?)?.at(time);
+    to_binary(&QueryAnswer::SpendingLimits { settings })
 }
 
 fn receive<S: Storage, A: Api, Q: Querier>(
@@ -81,78 +540,283 @@ fn receive<S: Storage, A: Api, Q: Querier>(
     env: Env,
     from: HumanAddr,
     amount: Uint128,
-    msg: Binary,
+    received_token: &HumanAddr,
+    msg: ReceiveMsg,
 ) -> StdResult<HandleResponse> {
-    let msg: ReceiveMsg = from_binary(&msg)?;
-    let response = match msg {
-        ReceiveMsg::Cancel { position } => cancel(deps, &env, from, amount, position),
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
+    let response = if config.constant_shape && has_constant_shape(&msg) {
+        constant_shape(deps, &env, &config, from, amount, received_token, msg)
+    } else {
+        receive_msg(deps, &env, from, amount, received_token, msg)
+    };
+    pad_response(response)
+}
+
+// Pull what the sender owes for the action with transfer_from and handle it as if it was received
+// from the token. The transfer_from leads the messages of the action, unless nothing is owed.
+fn allowance_payment<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    msg: ReceiveMsg,
+    fee_token: Option<HumanAddr>,
+) -> StdResult<HandleResponse> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
+    let from = env.message.sender.clone();
+    let from_canonical = deps.api.canonical_address(&from)?;
+    let (token, amount) = match &msg {
+        ReceiveMsg::Cancel { position, .. } => {
+            cancellation_penalties(deps, &config, &from_canonical, &[*position])?
+        }
+        ReceiveMsg::ConfirmAddress { .. } => (config.sscrt.clone(), Uint128(0)),
+        ReceiveMsg::CreateReceiveRequest { .. } | ReceiveMsg::CreateSendRequest { .. } => {
+            let fee_token = accepted_fee_token(&config, fee_token)?;
+            let fee = effective_fee(deps, &config, &from, fee_token.fee)?;
+            (fee_token.token, fee)
+        }
+        ReceiveMsg::SendPayment { position } => {
+            let tx = tx_at_position(&mut deps.storage, &from_canonical, *position)?;
+            (tx.token, tx.amount)
+        }
+        _ => {
+            return Err(StdError::generic_err(
+                "Action is not available with an allowance.",
+            ))
+        }
+    };
+    let contract_address = env.contract.address.clone();
+    let mut response = receive(deps, env, from.clone(), amount, &token.address, msg)?;
+    if !amount.is_zero() {
+        response.messages.insert(
+            0,
+            snip20::transfer_from_msg(
+                from,
+                contract_address,
+                amount,
+                None,
+                BLOCK_SIZE,
+                token.contract_hash,
+                token.address,
+            )?,
+        );
+    }
+
+    Ok(response)
+}
+
+// Status transitions are padded in constant shape mode, also when made for an organization
+fn has_constant_shape(msg: &ReceiveMsg) -> bool {
+    match msg {
+        ReceiveMsg::Cancel { .. }
+        | ReceiveMsg::ConfirmAddress { .. }
+        | ReceiveMsg::SendPayment { .. } => true,
+        ReceiveMsg::OnBehalfOf { msg, .. } => has_constant_shape(msg),
+        _ => false,
+    }
+}
+
+// Run a status transition padded to the same storage accesses and message count as the others,
+// so that the gas used doesn't show which one it was or whether it failed
+fn constant_shape<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    config: &Config,
+    from: HumanAddr,
+    amount: Uint128,
+    received_token: &HumanAddr,
+    msg: ReceiveMsg,
+) -> StdResult<HandleResponse> {
+    let mut counted_deps = Extern {
+        storage: CountingStorage::new(&mut deps.storage),
+        api: deps.api,
+        querier: BorrowedQuerier(&deps.querier),
+    };
+    let response = receive_msg(&mut counted_deps, env, from, amount, received_token, msg);
+    counted_deps.storage.pad()?;
+    let mut response = response?;
+    if response.messages.len() > SHAPE_MESSAGES {
+        return Err(StdError::generic_err("Handle exceeds the constant shape."));
+    }
+    while response.messages.len() < SHAPE_MESSAGES {
+        response.messages.push(snip20::transfer_msg(
+            env.contract.address.clone(),
+            Uint128(0),
+            None,
+            BLOCK_SIZE,
+            config.sscrt.contract_hash.clone(),
+            config.sscrt.address.clone(),
+        )?);
+    }
+
+    Ok(response)
+}
+
+fn receive_msg<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    from: HumanAddr,
+    amount: Uint128,
+    received_token: &HumanAddr,
+    msg: ReceiveMsg,
+) -> StdResult<HandleResponse> {
+    match msg {
+        ReceiveMsg::BatchCancel { positions, reason } => {
+            batch_cancel(deps, env, from, amount, received_token, positions, reason)
+        }
+        ReceiveMsg::BatchConfirmAddress { positions } => {
+            batch_confirm_address(deps, env, from, amount, received_token, positions)
+        }
+        ReceiveMsg::BatchSendPayment { positions } => {
+            batch_send_payment(deps, env, from, amount, received_token, positions)
+        }
+        ReceiveMsg::Cancel { position, reason } => {
+            cancel(deps, env, from, amount, received_token, position, reason)
+        }
         ReceiveMsg::ConfirmAddress { position } => {
-            confirm_address(deps, &env, from, amount, position)
+            confirm_address(deps, env, from, amount, received_token, position)
         }
-        ReceiveMsg::CreateReceiveRequest {
-            address,
+        ReceiveMsg::CreateClaim {
+            code_hash,
             send_amount,
             description,
             token,
-        } => create_receive_request(
+            expires_at,
+        } => create_claim(
             deps,
-            &env,
+            env,
             from,
             amount,
-            address,
+            received_token,
+            ClaimRequest {
+                code_hash,
+                send_amount,
+                description,
+                token,
+                expires_at,
+            },
+        ),
+        ReceiveMsg::CreatePaymentLink {
+            code_hash,
             send_amount,
             description,
             token,
+            max_uses,
+            referrer,
+        } => create_payment_link(
+            deps,
+            env,
+            from,
+            amount,
+            received_token,
+            PaymentLinkRequest {
+                code_hash,
+                send_amount,
+                description,
+                token,
+                max_uses,
+                referrer,
+            },
         ),
-        ReceiveMsg::CreateSendRequest {
+        ReceiveMsg::CreateReceiveRequest {
             address,
             send_amount,
             description,
             token,
-        } => create_send_request(
+            referrer,
+            cancellation_policy,
+            cancellation_penalty,
+            attachment_hash,
+        } => create_receive_request(
             deps,
-            &env,
+            env,
             from,
             amount,
+            received_token,
+            ReceiveRequest {
+                address,
+                send_amount,
+                description,
+                token,
+                referrer,
+                cancellation_policy,
+                cancellation_penalty,
+                attachment_hash,
+            },
+        ),
+        ReceiveMsg::CreateSendRequest {
             address,
             send_amount,
             description,
             token,
+            skip_confirmation_if_verified,
+            referrer,
+            cancellation_policy,
+            cancellation_penalty,
+            hash_timelock,
+            milestones,
+            attachment_hash,
+        } => create_send_request(
+            deps,
+            env,
+            from,
+            amount,
+            received_token,
+            SendRequest {
+                address,
+                send_amount,
+                description,
+                token,
+                skip_confirmation_if_verified,
+                referrer,
+                cancellation_policy,
+                cancellation_penalty,
+                hash_timelock,
+                milestones,
+                attachment_hash,
+            },
         ),
-        ReceiveMsg::SendPayment { position } => send_payment(deps, &env, from, amount, position),
-    };
-    pad_response(response)
+        ReceiveMsg::CreateSendRequests { requests } => {
+            create_send_requests(deps, env, from, amount, received_token, requests)
+        }
+        ReceiveMsg::FundClaim { code_hash } => {
+            fund_claim(deps, env, from, amount, received_token, code_hash)
+        }
+        ReceiveMsg::OnBehalfOf { organization, msg } => {
+            receive_on_behalf_of(deps, env, from, amount, received_token, organization, *msg)
+        }
+        ReceiveMsg::PayLink { code } => pay_link(deps, env, from, amount, received_token, code),
+        ReceiveMsg::Refund { position } => {
+            refund(deps, env, from, amount, received_token, position)
+        }
+        ReceiveMsg::SendPayment { position } => {
+            send_payment(deps, env, from, amount, received_token, position)
+        }
+    }
 }
 
-fn confirm_address<S: Storage, A: Api, Q: Querier>(
+fn batch_confirm_address<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: &Env,
     from: HumanAddr,
     amount: Uint128,
-    position: u32,
+    received_token: &HumanAddr,
+    positions: Vec<u32>,
 ) -> StdResult<HandleResponse> {
+    if positions.is_empty() {
+        return Err(StdError::generic_err("Positions must not be empty."));
+    }
     let config: Config = TypedStoreMut::attach(&mut deps.storage)
         .load(CONFIG_KEY)
         .unwrap();
+    // Nothing is owed, but it may be sent as any fee token
     correct_amount_of_token(
         amount,
         Uint128(0),
-        env.message.sender.clone(),
-        config.sscrt.address,
+        received_token.clone(),
+        config.fee_token(received_token).token.address,
     )?;
-
-    let (mut from_tx, mut to_tx) = verify_txs_for_confirm_address(
-        &deps.api,
-        &mut deps.storage,
-        &deps.api.canonical_address(&from)?,
-        position,
-    )?;
-
-    // Update Txs
-    from_tx.status = 1;
-    to_tx.status = 1;
-    update_tx(&mut deps.storage, &from_tx.from.clone(), from_tx)?;
-    update_tx(&mut deps.storage, &to_tx.to.clone(), to_tx)?;
+    for position in positions {
+        confirm_address_for_tx(deps, env, from.clone(), position)?;
+    }
 
     Ok(HandleResponse {
         messages: vec![],
@@ -161,91 +825,93 @@ fn confirm_address<S: Storage, A: Api, Q: Querier>(
     })
 }
 
-fn cancel<S: Storage, A: Api, Q: Querier>(
+fn confirm_address<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: &Env,
     from: HumanAddr,
     amount: Uint128,
+    received_token: &HumanAddr,
     position: u32,
 ) -> StdResult<HandleResponse> {
-    let config: Config = TypedStore::attach(&mut deps.storage)
+    let config: Config = TypedStoreMut::attach(&mut deps.storage)
         .load(CONFIG_KEY)
         .unwrap();
+    // Nothing is owed, but it may be sent as any fee token
     correct_amount_of_token(
         amount,
         Uint128(0),
-        env.message.sender.clone(),
-        config.sscrt.address.clone(),
-    )?;
-    let (mut from_tx, mut to_tx) = verify_txs_for_cancel(
-        &mut deps.storage,
-        &deps.api.canonical_address(&from)?,
-        position,
+        received_token.clone(),
+        config.fee_token(received_token).token.address,
     )?;
-    // Send refund to the creator
-    let mut messages: Vec<CosmosMsg> = vec![];
-    messages.push(snip20::transfer_msg(
-        from_tx.creator.clone(),
-        from_tx.fee,
-        None,
-        BLOCK_SIZE,
-        config.sscrt.contract_hash,
-        config.sscrt.address,
-    )?);
-
-    // Update Txs
-    from_tx.status = 2;
-    to_tx.status = 2;
-    update_tx(&mut deps.storage, &from_tx.from.clone(), from_tx)?;
-    update_tx(&mut deps.storage, &to_tx.to.clone(), to_tx)?;
+    confirm_address_for_tx(deps, env, from, position)?;
 
     Ok(HandleResponse {
-        messages,
+        messages: vec![],
         log: vec![],
         data: None,
     })
 }
 
-fn send_payment<S: Storage, A: Api, Q: Querier>(
+fn confirm_address_for_tx<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: &Env,
     from: HumanAddr,
-    amount: Uint128,
     position: u32,
-) -> StdResult<HandleResponse> {
-    let (mut from_tx, mut to_tx) = verify_txs(
+) -> StdResult<()> {
+    let (mut from_tx, mut to_tx) = verify_txs_for_confirm_address(
         &deps.api,
         &mut deps.storage,
         &deps.api.canonical_address(&from)?,
-        amount,
         position,
-        1,
-        env.message.sender.clone(),
     )?;
-    from_tx.status = 3;
-    to_tx.status = 3;
-    update_tx(&mut deps.storage, &from_tx.from.clone(), from_tx.clone())?;
+
+    // Update Txs
+    from_tx.status = 1;
+    to_tx.status = 1;
+    // Remember that the receiver has proven control of this address
+    store_verified_address(
+        &mut deps.storage,
+        &from_tx.from,
+        &to_tx.to,
+        from,
+        env.block.time,
+    )?;
+    update_tx(&mut deps.storage, &from_tx.from.clone(), from_tx)?;
     update_tx(&mut deps.storage, &to_tx.to.clone(), to_tx)?;
+    record_stats(&mut deps.storage, env.block.time, |stats| {
+        stats.confirmed += 1
+    })
+}
+
+fn batch_cancel<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    from: HumanAddr,
+    amount: Uint128,
+    received_token: &HumanAddr,
+    positions: Vec<u32>,
+    reason: Option<String>,
+) -> StdResult<HandleResponse> {
+    if positions.is_empty() {
+        return Err(StdError::generic_err("Positions must not be empty."));
+    }
+    verify_text_length("Reason", &reason)?;
     let config: Config = TypedStore::attach(&mut deps.storage)
         .load(CONFIG_KEY)
         .unwrap();
-    let mut messages: Vec<CosmosMsg> = vec![];
-    messages.push(snip20::transfer_msg(
-        config.treasury_address,
-        from_tx.fee,
-        None,
-        BLOCK_SIZE,
-        config.sscrt.contract_hash,
-        config.sscrt.address,
-    )?);
-    messages.push(snip20::transfer_msg(
-        deps.api.human_address(&from_tx.to)?,
-        from_tx.amount,
-        None,
-        BLOCK_SIZE,
-        from_tx.token.contract_hash,
-        env.message.sender.clone(),
-    )?);
+    let from = deps.api.canonical_address(&from)?;
+    let (penalty_token, penalties) = cancellation_penalties(deps, &config, &from, &positions)?;
+    correct_amount_of_token(
+        amount,
+        penalties,
+        received_token.clone(),
+        penalty_token.address,
+    )?;
+    let mut txs: Vec<Tx> = vec![];
+    for position in positions {
+        txs.push(cancel_tx(deps, env, &from, position, reason.clone())?);
+    }
+    let messages = settle_cancellations(deps, env, &config, &from, txs)?;
 
     Ok(HandleResponse {
         messages,
@@ -254,107 +920,187 @@ fn send_payment<S: Storage, A: Api, Q: Querier>(
     })
 }
 
-fn accept_new_admin_nomination<S: Storage, A: Api, Q: Querier>(
+fn cancel<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: &Env,
+    from: HumanAddr,
+    amount: Uint128,
+    received_token: &HumanAddr,
+    position: u32,
+    reason: Option<String>,
 ) -> StdResult<HandleResponse> {
-    let mut config: Config = TypedStoreMut::attach(&mut deps.storage)
+    verify_text_length("Reason", &reason)?;
+    let config: Config = TypedStore::attach(&mut deps.storage)
         .load(CONFIG_KEY)
         .unwrap();
-    if config.new_admin_nomination.is_none() {
-        return Err(StdError::Unauthorized { backtrace: None });
-    }
-    authorize(
-        env.message.sender.clone(),
-        config.new_admin_nomination.clone().unwrap(),
+    let from = deps.api.canonical_address(&from)?;
+    let (penalty_token, penalties) = cancellation_penalties(deps, &config, &from, &[position])?;
+    correct_amount_of_token(
+        amount,
+        penalties,
+        received_token.clone(),
+        penalty_token.address,
     )?;
-
-    config.admin = config.new_admin_nomination.unwrap();
-    config.new_admin_nomination = None;
-    TypedStoreMut::attach(&mut deps.storage).store(CONFIG_KEY, &config)?;
+    let from_tx = cancel_tx(deps, env, &from, position, reason)?;
+    let messages = settle_cancellations(deps, env, &config, &from, vec![from_tx])?;
 
     Ok(HandleResponse {
-        messages: vec![],
+        messages,
         log: vec![],
         data: None,
     })
 }
 
-pub fn correct_amount_of_token(
-    amount_received: Uint128,
-    amount_wanted: Uint128,
-    token_received: HumanAddr,
-    token_wanted: HumanAddr,
-) -> StdResult<()> {
-    if amount_received != amount_wanted {
-        return Err(StdError::generic_err("Wrong amount received."));
-    }
-    if token_received != token_wanted {
-        return Err(StdError::generic_err("Wrong token received."));
+// The fee token and amount of the penalties the canceller has to send for the Txs they did not
+// create. Without any penalties, nothing is owed in SSCRT.
+fn cancellation_penalties<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    config: &Config,
+    from: &CanonicalAddr,
+    positions: &[u32],
+) -> StdResult<(SecretContract, Uint128)> {
+    let from_human = deps.api.human_address(from)?;
+    let mut penalty_token: Option<SecretContract> = None;
+    let mut penalties = Uint128(0);
+    for position in positions {
+        let (_from_tx, to_tx) =
+            verify_txs_for_cancel(&deps.api, &mut deps.storage, from, *position)?;
+        if to_tx.creator != from_human && !to_tx.cancellation_penalty.is_zero() {
+            if let Some(penalty_token) = &penalty_token {
+                if penalty_token != &to_tx.fee_token {
+                    return Err(StdError::generic_err(
+                        "Cancellation penalties must be in the same fee token.",
+                    ));
+                }
+            }
+            penalty_token = Some(to_tx.fee_token);
+            penalties = penalties + to_tx.cancellation_penalty;
+        }
     }
 
-    Ok(())
+    Ok((
+        penalty_token.unwrap_or_else(|| config.sscrt.clone()),
+        penalties,
+    ))
 }
 
-fn register_token<S: Storage>(
-    storage: &mut S,
-    contract_code_hash: String,
-    token: SecretContract,
-) -> StdResult<Option<CosmosMsg>> {
-    let mut cosmos_msg: Option<CosmosMsg> = None;
-    let mut registered_tokens_storage = RegisteredTokensStorage::from_storage(storage);
-    let contract_hash = registered_tokens_storage.get_contract_hash(token.address.clone());
-    if contract_hash.is_none() {
-        registered_tokens_storage.set_contract_hash(token.address.clone(), &token.contract_hash);
-        cosmos_msg = Some(snip20::register_receive_msg(
-            contract_code_hash,
+// Cancel the Txs and return the sender's copy
+fn cancel_tx<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    from: &CanonicalAddr,
+    position: u32,
+    reason: Option<String>,
+) -> StdResult<Tx> {
+    let (mut from_tx, mut to_tx) =
+        verify_txs_for_cancel(&deps.api, &mut deps.storage, from, position)?;
+
+    // Update Txs
+    from_tx.status = 2;
+    to_tx.status = 2;
+    from_tx.cancellation_reason = reason.clone();
+    to_tx.cancellation_reason = reason;
+    update_tx(&mut deps.storage, &from_tx.from.clone(), from_tx.clone())?;
+    update_tx(&mut deps.storage, &to_tx.to.clone(), to_tx)?;
+    record_stats(&mut deps.storage, env.block.time, |stats| {
+        stats.cancelled += 1
+    })?;
+
+    Ok(from_tx)
+}
+
+// The creator gets the fee back when they cancel. When the counterparty cancels, the fee is kept
+// like it is for a finalized Tx and the penalty they sent in is passed on to the creator.
+fn settle_cancellations<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    config: &Config,
+    from: &CanonicalAddr,
+    txs: Vec<Tx>,
+) -> StdResult<Vec<CosmosMsg>> {
+    let from = deps.api.human_address(from)?;
+    let mut fees: Vec<(SecretContract, Uint128, Option<HumanAddr>)> = vec![];
+    let mut transfer_messages: Vec<CosmosMsg> = vec![];
+    for tx in txs {
+        let transfer_amount = if tx.creator == from {
+            record_fees(
+                &mut deps.storage,
+                &tx.fee_token.address,
+                env.block.time,
+                |stats| {
+                    stats.refunded = stats.refunded + tx.fee;
+                    Ok(())
+                },
+            )?;
+            tx.fee
+        } else {
+            fees.push((tx.fee_token.clone(), tx.fee, tx.referrer));
+            if tx.cancellation_penalty.is_zero() {
+                continue;
+            }
+            tx.cancellation_penalty
+        };
+        transfer_messages.push(snip20::transfer_msg(
+            payout_address(deps, tx.creator)?,
+            transfer_amount,
             None,
             BLOCK_SIZE,
-            token.contract_hash,
-            token.address,
+            tx.fee_token.contract_hash,
+            tx.fee_token.address,
         )?);
     }
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if !fees.is_empty() {
+        messages = distribute_fees(deps, env, config, fees)?;
+    }
+    messages.append(&mut transfer_messages);
 
-    Ok(cosmos_msg)
+    Ok(messages)
 }
 
-fn create_receive_request<S: Storage, A: Api, Q: Querier>(
+fn batch_send_payment<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: &Env,
     from: HumanAddr,
     amount: Uint128,
-    address: HumanAddr,
-    send_amount: Uint128,
-    description: Option<String>,
-    token: SecretContract,
+    received_token: &HumanAddr,
+    positions: Vec<u32>,
 ) -> StdResult<HandleResponse> {
+    if positions.is_empty() {
+        return Err(StdError::generic_err("Positions must not be empty."));
+    }
+    // Any failure reverts the whole batch
+    let from = deps.api.canonical_address(&from)?;
+    let mut total_amount = Uint128(0);
+    let mut fees: Vec<(SecretContract, Uint128, Option<HumanAddr>)> = vec![];
+    let mut payment_messages: Vec<CosmosMsg> = vec![];
+    for position in positions {
+        let tx_amount = tx_at_position(&mut deps.storage, &from, position)?.amount;
+        let from_tx = pay_tx(deps, env, &from, tx_amount, received_token, position)?;
+        total_amount = total_amount + from_tx.amount;
+        if from_tx.status == 6 {
+            continue;
+        }
+        fees.push((from_tx.fee_token.clone(), from_tx.fee, from_tx.referrer));
+        payment_messages.push(snip20::transfer_msg(
+            deps.api.human_address(&from_tx.to)?,
+            from_tx.amount,
+            None,
+            BLOCK_SIZE,
+            from_tx.token.contract_hash,
+            received_token.clone(),
+        )?);
+    }
+    // All payments must be covered by the single amount received
+    if total_amount != amount {
+        return Err(StdError::generic_err("Wrong amount received."));
+    }
+
     let config: Config = TypedStore::attach(&mut deps.storage)
         .load(CONFIG_KEY)
         .unwrap();
-    correct_amount_of_token(
-        amount,
-        config.fee,
-        env.message.sender.clone(),
-        config.sscrt.address,
-    )?;
-    store_txs(
-        &mut deps.storage,
-        config.fee,
-        &deps.api.canonical_address(&address)?,
-        &deps.api.canonical_address(&from)?,
-        from,
-        send_amount,
-        token.clone(),
-        description,
-        1,
-        &env.block,
-    )?;
-    let mut messages: Vec<CosmosMsg> = vec![];
-    let register_token_msg: Option<CosmosMsg> =
-        register_token(&mut deps.storage, env.contract_code_hash.clone(), token)?;
-    if register_token_msg.is_some() {
-        messages.push(register_token_msg.unwrap())
-    }
+    let mut messages: Vec<CosmosMsg> = distribute_fees(deps, env, &config, fees)?;
+    messages.append(&mut payment_messages);
 
     Ok(HandleResponse {
         messages,
@@ -363,221 +1109,3086 @@ fn create_receive_request<S: Storage, A: Api, Q: Querier>(
     })
 }
 
-fn create_send_request<S: Storage, A: Api, Q: Querier>(
+// Pay referrers their share of the fees of paid Txs and accrue the rest for the treasury
+fn distribute_fees<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: &Env,
-    from: HumanAddr,
+    config: &Config,
+    fees: Vec<(SecretContract, Uint128, Option<HumanAddr>)>,
+) -> StdResult<Vec<CosmosMsg>> {
+    // Referrals paid and accrued fees for each fee token
+    let mut totals: Vec<(SecretContract, Uint128, Uint128)> = vec![];
+    let mut messages: Vec<CosmosMsg> = vec![];
+    for (fee_token, fee, referrer) in fees {
+        let mut referral_amount = Uint128(0);
+        if let Some(referrer) = referrer {
+            referral_amount = fee.multiply_ratio(config.referral_share_bps as u128, 10_000u128);
+            if !referral_amount.is_zero() {
+                add_referral_earnings(
+                    &mut deps.storage,
+                    &deps.api.canonical_address(&referrer)?,
+                    &fee_token.address,
+                    referral_amount,
+                )?;
+                messages.push(snip20::transfer_msg(
+                    referrer,
+                    referral_amount,
+                    None,
+                    BLOCK_SIZE,
+                    fee_token.contract_hash.clone(),
+                    fee_token.address.clone(),
+                )?);
+            }
+        }
+        let accrued = (fee - referral_amount)?;
+        match totals.iter_mut().find(|(token, _, _)| token == &fee_token) {
+            Some((_, referrals_paid, token_accrued)) => {
+                *referrals_paid = *referrals_paid + referral_amount;
+                *token_accrued = *token_accrued + accrued;
+            }
+            None => totals.push((fee_token, referral_amount, accrued)),
+        }
+    }
+    for (fee_token, referrals_paid, accrued) in totals {
+        record_fees(
+            &mut deps.storage,
+            &fee_token.address,
+            env.block.time,
+            |stats| {
+                stats.referrals_paid = stats.referrals_paid + referrals_paid;
+                stats.accrued = stats.accrued + accrued;
+                Ok(())
+            },
+        )?;
+    }
+
+    Ok(messages)
+}
+
+// Mark the Txs as paid and return the sender's copy
+fn pay_tx<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    from: &CanonicalAddr,
     amount: Uint128,
-    address: HumanAddr,
-    send_amount: Uint128,
-    description: Option<String>,
-    token: SecretContract,
-) -> StdResult<HandleResponse> {
-    let config: Config = TypedStore::attach(&mut deps.storage)
-        .load(CONFIG_KEY)
-        .unwrap();
-    correct_amount_of_token(
+    received_token: &HumanAddr,
+    position: u32,
+) -> StdResult<Tx> {
+    let (mut from_tx, mut to_tx) = verify_txs(
+        &deps.api,
+        &mut deps.storage,
+        from,
         amount,
-        config.fee,
-        env.message.sender.clone(),
-        config.sscrt.address,
+        position,
+        1,
+        received_token.clone(),
     )?;
-    store_txs(
+    record_spending(
         &mut deps.storage,
-        config.fee,
-        &deps.api.canonical_address(&from)?,
-        &deps.api.canonical_address(&address)?,
         from,
-        send_amount,
-        token.clone(),
-        description,
-        0,
-        &env.block,
+        Some(&from_tx.to),
+        &from_tx.token.address,
+        from_tx.amount,
+        from_tx.block_time,
+        env.block.time,
     )?;
-    let mut messages: Vec<CosmosMsg> = vec![];
-    let register_token_msg: Option<CosmosMsg> =
-        register_token(&mut deps.storage, env.contract_code_hash.clone(), token)?;
-    if register_token_msg.is_some() {
-        messages.push(register_token_msg.unwrap())
+    let needs_approval = match organization(&deps.storage, from)? {
+        Some(organization) => organization
+            .required_approvals(&from_tx.token.address, from_tx.amount)
+            .is_some(),
+        None => false,
+    };
+    // Organization payments above an approval threshold are escrowed until they are approved
+    if needs_approval {
+        if from_tx.hash_timelock.is_some() || from_tx.milestones.is_some() {
+            return Err(StdError::generic_err(
+                "Payments that need approval can not be hash timelocked or have milestones.",
+            ));
+        }
+        from_tx.status = 6;
+        to_tx.status = 6;
+    } else if let Some(hash_timelock) = &from_tx.hash_timelock {
+        if env.block.time >= hash_timelock.timelock {
+            return Err(StdError::generic_err("Timelock has passed."));
+        }
+        from_tx.status = 6;
+        to_tx.status = 6;
+    } else if from_tx.milestones.is_some() {
+        // Payments for milestone Txs are escrowed until each milestone is released or cancelled
+        from_tx.status = 6;
+        to_tx.status = 6;
+    } else {
+        from_tx.status = 3;
+        to_tx.status = 3;
+        record_stats(&mut deps.storage, env.block.time, |stats| {
+            stats.finalized += 1;
+        })?;
+        record_volume(
+            &mut deps.storage,
+            &from_tx.token.address,
+            env.block.time,
+            from_tx.amount,
+        )?;
     }
+    update_tx(&mut deps.storage, &from_tx.from.clone(), from_tx.clone())?;
+    update_tx(&mut deps.storage, &to_tx.to.clone(), to_tx)?;
 
-    Ok(HandleResponse {
-        messages,
-        log: vec![],
-        data: None,
-    })
+    Ok(from_tx)
 }
 
-fn nominate_new_admin<S: Storage, A: Api, Q: Querier>(
+fn send_payment<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: &Env,
-    address: HumanAddr,
+    from: HumanAddr,
+    amount: Uint128,
+    received_token: &HumanAddr,
+    position: u32,
 ) -> StdResult<HandleResponse> {
-    let mut config: Config = TypedStoreMut::attach(&mut deps.storage)
+    let from_tx = pay_tx(
+        deps,
+        env,
+        &deps.api.canonical_address(&from)?,
+        amount,
+        received_token,
+        position,
+    )?;
+    if from_tx.status == 6 {
+        return Ok(HandleResponse {
+            messages: vec![],
+            log: vec![],
+            data: None,
+        });
+    }
+    let config: Config = TypedStore::attach(&mut deps.storage)
         .load(CONFIG_KEY)
         .unwrap();
-    authorize(env.message.sender.clone(), config.admin.clone())?;
-
-    config.new_admin_nomination = Some(address);
-    TypedStoreMut::attach(&mut deps.storage).store(CONFIG_KEY, &config)?;
+    let mut messages: Vec<CosmosMsg> = distribute_fees(
+        deps,
+        env,
+        &config,
+        vec![(
+            from_tx.fee_token.clone(),
+            from_tx.fee,
+            from_tx.referrer.clone(),
+        )],
+    )?;
+    messages.push(snip20::transfer_msg(
+        deps.api.human_address(&from_tx.to)?,
+        from_tx.amount,
+        None,
+        BLOCK_SIZE,
+        from_tx.token.contract_hash,
+        received_token.clone(),
+    )?);
 
     Ok(HandleResponse {
-        messages: vec![],
+        messages,
         log: vec![],
         data: None,
     })
 }
 
-fn pad_response(response: StdResult<HandleResponse>) -> StdResult<HandleResponse> {
-    response.map(|mut response| {
-        response.data = response.data.map(|mut data| {
-            space_pad(BLOCK_SIZE, &mut data.0);
-            data
-        });
-        response
-    })
-}
+// Pay a payment link, which binds the sender as the payer of a new finalized Tx
+fn pay_link<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    from: HumanAddr,
+    amount: Uint128,
+    received_token: &HumanAddr,
+    code: String,
+) -> StdResult<HandleResponse> {
+    let code_hash = hash_code(&code);
+    let mut link = payment_link(&deps.storage, &code_hash)?
+        .ok_or_else(|| StdError::generic_err("Payment link not found."))?;
+    correct_amount_of_token(
+        amount,
+        link.send_amount,
+        received_token.clone(),
+        link.token.address.clone(),
+    )?;
+    let config: Config = TypedStore::attach(&mut deps.storage)
+        .load(CONFIG_KEY)
+        .unwrap();
+    let from_canonical = deps.api.canonical_address(&from)?;
+    let receiver_canonical = deps.api.canonical_address(&link.receiver)?;
+    // The Tx for the link is only created now, so a new receiver is still in its cooling-off period
+    record_spending(
+        &mut deps.storage,
+        &from_canonical,
+        Some(&receiver_canonical),
+        &link.token.address,
+        link.send_amount,
+        env.block.time,
+        env.block.time,
+    )?;
+    store_txs(
+        &mut deps.storage,
+        &from_canonical,
+        &receiver_canonical,
+        NewTx {
+            creator: link.receiver.clone(),
+            amount: link.send_amount,
+            token: link.token.clone(),
+            description: link.description.clone(),
+            fee: link.fee,
+            fee_token: link.fee_token.clone(),
+        },
+        TxTerms {
+            referrer: link.referrer.clone(),
+            ..TxTerms::default()
+        },
+        3,
+        &env.block,
+    )?;
+    let new_participants =
+        record_participants(&mut deps.storage, &[&from_canonical, &receiver_canonical])?;
+    record_stats(&mut deps.storage, env.block.time, |stats| {
+        stats.receive_requests_created += 1;
+        stats.finalized += 1;
+        stats.unique_participants += new_participants;
+    })?;
+    record_volume(
+        &mut deps.storage,
+        &link.token.address,
+        env.block.time,
+        link.send_amount,
+    )?;
 
-// Take a Vec<u8> and pad it up to a multiple of `block_size`, using spaces at the end.
-fn space_pad(block_size: usize, message: &mut Vec<u8>) -> &mut Vec<u8> {
-    let len = message.len();
-    let surplus = len % block_size;
-    if surplus == 0 {
-        return message;
+    // The link can't be paid again once it has been used up
+    link.uses += 1;
+    if link.uses == link.max_uses {
+        remove_payment_link(&mut deps.storage, &code_hash);
+    } else {
+        store_payment_link(&mut deps.storage, &code_hash, &link)?;
     }
 
-    let missing = block_size - surplus;
-    message.reserve(missing);
-    message.extend(std::iter::repeat(b' ').take(missing));
-    message
+    let mut messages: Vec<CosmosMsg> = distribute_fees(
+        deps,
+        env,
+        &config,
+        vec![(link.fee_token.clone(), link.fee, link.referrer)],
+    )?;
+    messages.push(snip20::transfer_msg(
+        link.receiver,
+        link.send_amount,
+        None,
+        BLOCK_SIZE,
+        link.token.contract_hash,
+        link.token.address,
+    )?);
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: None,
+    })
 }
 
-fn txs<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    address: HumanAddr,
-    key: String,
-    page: u32,
-    page_size: u32,
-) -> StdResult<Binary> {
-    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+// Release an escrowed payment to the receiver, who reveals the preimage of the hashlock
+fn claim_htlc<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    position: u32,
+    preimage: Binary,
+) -> StdResult<HandleResponse> {
+    let to = deps.api.canonical_address(&env.message.sender)?;
+    let mut to_tx = tx_at_position(&mut deps.storage, &to, position)?;
+    authorize(
+        deps.api.human_address(&to_tx.to)?,
+        env.message.sender.clone(),
+    )?;
+    let mut from_tx = tx_at_position(&mut deps.storage, &to_tx.from, to_tx.other_storage_position)?;
+    if to_tx.status != 6 {
+        return Err(StdError::generic_err("Tx is not escrowed."));
+    }
+    let hash_timelock = to_tx
+        .hash_timelock
+        .clone()
+        .ok_or_else(|| StdError::generic_err("Tx is not hash timelocked."))?;
+    if env.block.time >= hash_timelock.timelock {
+        return Err(StdError::generic_err("Timelock has passed."));
+    }
+    // The preimage is stored on both Txs
+    if preimage.len() > MAX_PREIMAGE_LENGTH {
+        return Err(StdError::generic_err(format!(
+            "Preimage must be at most {} bytes.",
+            MAX_PREIMAGE_LENGTH
+        )));
+    }
+    if Sha256::digest(preimage.as_slice()).to_vec() != hash_timelock.hashlock.0 {
+        return Err(StdError::generic_err("Preimage does not match hashlock."));
+    }
 
-    // This is here so that the user can use their viewing key for shade for this
-    snip20::balance_query(
-        &deps.querier,
-        address.clone(),
-        key.to_string(),
-        BLOCK_SIZE,
-        config.shade.contract_hash,
-        config.shade.address,
+    // Update Txs
+    from_tx.status = 3;
+    to_tx.status = 3;
+    from_tx.preimage = Some(preimage.clone());
+    to_tx.preimage = Some(preimage);
+    update_tx(&mut deps.storage, &from_tx.from.clone(), from_tx.clone())?;
+    update_tx(&mut deps.storage, &to_tx.to.clone(), to_tx)?;
+    record_stats(&mut deps.storage, env.block.time, |stats| {
+        stats.finalized += 1;
+    })?;
+    record_volume(
+        &mut deps.storage,
+        &from_tx.token.address,
+        env.block.time,
+        from_tx.amount,
     )?;
 
-    let address = deps.api.canonical_address(&address)?;
-    let (txs, total) = get_txs(&deps.api, &deps.storage, &address, page, page_size)?;
+    let config: Config = TypedStore::attach(&mut deps.storage)
+        .load(CONFIG_KEY)
+        .unwrap();
+    let mut messages: Vec<CosmosMsg> = distribute_fees(
+        deps,
+        env,
+        &config,
+        vec![(from_tx.fee_token.clone(), from_tx.fee, from_tx.referrer)],
+    )?;
+    messages.push(snip20::transfer_msg(
+        env.message.sender.clone(),
+        from_tx.amount,
+        None,
+        BLOCK_SIZE,
+        from_tx.token.contract_hash,
+        from_tx.token.address,
+    )?);
 
-    let result = QueryAnswer::Txs {
-        txs,
-        total: Some(total),
-    };
-    to_binary(&result)
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: None,
+    })
 }
 
-fn update_fee<S: Storage, A: Api, Q: Querier>(
+// Return an escrowed payment and the fee to the sender once the timelock has passed
+fn refund_htlc<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: &Env,
-    fee: Uint128,
+    position: u32,
 ) -> StdResult<HandleResponse> {
-    let mut config: Config = TypedStoreMut::attach(&mut deps.storage)
-        .load(CONFIG_KEY)
-        .unwrap();
-    authorize(env.message.sender.clone(), config.admin.clone())?;
+    let from = deps.api.canonical_address(&env.message.sender)?;
+    let mut from_tx = tx_at_position(&mut deps.storage, &from, position)?;
+    authorize(
+        deps.api.human_address(&from_tx.from)?,
+        env.message.sender.clone(),
+    )?;
+    let mut to_tx = tx_at_position(
+        &mut deps.storage,
+        &from_tx.to,
+        from_tx.other_storage_position,
+    )?;
+    if to_tx.status != 6 {
+        return Err(StdError::generic_err("Tx is not escrowed."));
+    }
+    let hash_timelock = to_tx
+        .hash_timelock
+        .clone()
+        .ok_or_else(|| StdError::generic_err("Tx is not hash timelocked."))?;
+    if env.block.time < hash_timelock.timelock {
+        return Err(StdError::generic_err("Timelock has not passed yet."));
+    }
 
-    config.fee = fee;
-    TypedStoreMut::attach(&mut deps.storage).store(CONFIG_KEY, &config)?;
+    // Update Txs
+    from_tx.status = 2;
+    to_tx.status = 2;
+    update_tx(&mut deps.storage, &from_tx.from.clone(), from_tx.clone())?;
+    update_tx(&mut deps.storage, &to_tx.to.clone(), to_tx)?;
+    record_stats(&mut deps.storage, env.block.time, |stats| {
+        stats.cancelled += 1
+    })?;
+
+    record_fees(
+        &mut deps.storage,
+        &from_tx.fee_token.address,
+        env.block.time,
+        |stats| {
+            stats.refunded = stats.refunded + from_tx.fee;
+            Ok(())
+        },
+    )?;
+    let mut messages: Vec<CosmosMsg> = vec![];
+    messages.push(snip20::transfer_msg(
+        payout_address(deps, from_tx.creator)?,
+        from_tx.fee,
+        None,
+        BLOCK_SIZE,
+        from_tx.fee_token.contract_hash.clone(),
+        from_tx.fee_token.address.clone(),
+    )?);
+    messages.push(snip20::transfer_msg(
+        payout_address(deps, env.message.sender.clone())?,
+        from_tx.amount,
+        None,
+        BLOCK_SIZE,
+        from_tx.token.contract_hash,
+        from_tx.token.address,
+    )?);
 
     Ok(HandleResponse {
-        messages: vec![],
+        messages,
         log: vec![],
         data: None,
     })
 }
 
-fn update_treasury_address<S: Storage, A: Api, Q: Querier>(
+// Release a milestone of an escrowed milestone Tx to the receiver
+fn release_milestone<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: &Env,
-    address: HumanAddr,
+    position: u32,
+    index: u32,
 ) -> StdResult<HandleResponse> {
-    let mut config: Config = TypedStoreMut::attach(&mut deps.storage)
-        .load(CONFIG_KEY)
-        .unwrap();
-    authorize(env.message.sender.clone(), config.admin.clone())?;
+    let from = deps.api.canonical_address(&env.message.sender)?;
+    let from_tx = tx_at_position(&mut deps.storage, &from, position)?;
+    authorize(
+        deps.api.human_address(&from_tx.from)?,
+        env.message.sender.clone(),
+    )?;
+    let to_tx = tx_at_position(
+        &mut deps.storage,
+        &from_tx.to,
+        from_tx.other_storage_position,
+    )?;
+    let milestone = pending_milestone(&to_tx, index)?;
 
-    config.treasury_address = address;
-    TypedStoreMut::attach(&mut deps.storage).store(CONFIG_KEY, &config)?;
+    store_milestone_release_txs(
+        &mut deps.storage,
+        &from_tx,
+        &to_tx,
+        milestone.label.clone(),
+        milestone.amount,
+        &env.block,
+    )?;
+    record_volume(
+        &mut deps.storage,
+        &from_tx.token.address,
+        env.block.time,
+        milestone.amount,
+    )?;
+    let mut messages: Vec<CosmosMsg> = settle_milestone(
+        deps,
+        env,
+        from_tx.clone(),
+        to_tx,
+        index,
+        MilestoneStatus::Released,
+    )?;
+    messages.push(snip20::transfer_msg(
+        deps.api.human_address(&from_tx.to)?,
+        milestone.amount,
+        None,
+        BLOCK_SIZE,
+        from_tx.token.contract_hash,
+        from_tx.token.address,
+    )?);
 
     Ok(HandleResponse {
-        messages: vec![],
+        messages,
         log: vec![],
         data: None,
     })
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::state::RegisteredTokensReadonlyStorage;
-    use crate::transaction_history::{tx_at_position, Tx};
-    use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage};
-
-    // === HELPERS ===
-    fn init_helper() -> (
-        StdResult<InitResponse>,
-        Extern<MockStorage, MockApi, MockQuerier>,
-    ) {
-        let env = mock_env(mock_contract_initiator_address(), &[]);
-        let mut deps = mock_dependencies(20, &[]);
-        let msg = InitMsg {
-            fee: mock_fee(),
-            shade: mock_shade(),
-            sscrt: mock_sscrt(),
-            treasury_address: mock_treasury_address(),
-        };
-        (init(&mut deps, env, msg), deps)
+// Cancel a milestone of an escrowed milestone Tx and return its amount to the sender.
+// Who can cancel follows the Tx's cancellation policy, but the sender can always cancel a
+// milestone after its deadline.
+fn cancel_milestone<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    position: u32,
+    index: u32,
+) -> StdResult<HandleResponse> {
+    let address = deps.api.canonical_address(&env.message.sender)?;
+    let tx = tx_at_position(&mut deps.storage, &address, position)?;
+    let (from_tx, to_tx) = if tx.from == address {
+        let to_tx = tx_at_position(&mut deps.storage, &tx.to, tx.other_storage_position)?;
+        (tx, to_tx)
+    } else {
+        let from_tx = tx_at_position(&mut deps.storage, &tx.from, tx.other_storage_position)?;
+        (from_tx, tx)
+    };
+    let milestone = pending_milestone(&to_tx, index)?;
+    let past_deadline = match milestone.deadline {
+        Some(deadline) => env.block.time >= deadline,
+        None => false,
+    };
+    if !(past_deadline && from_tx.from == address) {
+        match to_tx.cancellation_policy {
+            CancellationPolicy::EitherParty => {}
+            CancellationPolicy::CreatorOnly => {
+                if env.message.sender != to_tx.creator {
+                    return Err(StdError::generic_err(
+                        "Only the creator can cancel this Tx.",
+                    ));
+                }
+            }
+            CancellationPolicy::BeforeConfirmation => {
+                return Err(StdError::generic_err(
+                    "Tx can not be cancelled after address confirmation.",
+                ));
+            }
+        }
     }
 
-    fn mock_fee() -> Uint128 {
-        Uint128(1_000_000)
-    }
+    let mut messages: Vec<CosmosMsg> = settle_milestone(
+        deps,
+        env,
+        from_tx.clone(),
+        to_tx,
+        index,
+        MilestoneStatus::Cancelled,
+    )?;
+    messages.push(snip20::transfer_msg(
+        payout_address(deps, deps.api.human_address(&from_tx.from)?)?,
+        milestone.amount,
+        None,
+        BLOCK_SIZE,
+        from_tx.token.contract_hash,
+        from_tx.token.address,
+    )?);
 
-    fn mock_silk() -> SecretContract {
-        SecretContract {
-            address: HumanAddr::from("mock-silk-address"),
-            contract_hash: "mock-silk-contract-hash".to_string(),
-        }
-    }
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: None,
+    })
+}
 
-    fn mock_treasury_address() -> HumanAddr {
-        HumanAddr::from("mock-treasury-address")
+fn pending_milestone(tx: &Tx, index: u32) -> StdResult<Milestone> {
+    if tx.status != 6 {
+        return Err(StdError::generic_err("Tx is not escrowed."));
     }
-
-    fn mock_contract() -> SecretContract {
-        let env = mock_env(mock_user_address(), &[]);
-        SecretContract {
-            address: env.contract.address,
-            contract_hash: env.contract_code_hash,
-        }
+    let milestone = tx
+        .milestones
+        .as_ref()
+        .ok_or_else(|| StdError::generic_err("Tx has no milestones."))?
+        .get(index as usize)
+        .ok_or_else(|| StdError::generic_err("Milestone not found."))?;
+    if milestone.status != MilestoneStatus::Pending {
+        return Err(StdError::generic_err("Milestone already settled."));
     }
 
-    fn mock_contract_initiator_address() -> HumanAddr {
-        HumanAddr::from("admin")
-    }
+    Ok(milestone.clone())
+}
 
-    fn mock_sscrt() -> SecretContract {
-        SecretContract {
-            address: HumanAddr::from("mock-sscrt-address"),
-            contract_hash: "mock-sscrt-contract-hash".to_string(),
+// Update the status of the milestone on both Txs. Once no milestones are pending, the Tx is
+// finalized and its fee kept if any milestone was released, otherwise it is cancelled and the
+// fee is sent back to the creator.
+fn settle_milestone<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    mut from_tx: Tx,
+    mut to_tx: Tx,
+    index: u32,
+    status: MilestoneStatus,
+) -> StdResult<Vec<CosmosMsg>> {
+    let mut milestones = to_tx.milestones.clone().unwrap();
+    milestones[index as usize].status = status;
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if milestones
+        .iter()
+        .all(|milestone| milestone.status != MilestoneStatus::Pending)
+    {
+        let config: Config = TypedStore::attach(&mut deps.storage)
+            .load(CONFIG_KEY)
+            .unwrap();
+        if milestones
+            .iter()
+            .any(|milestone| milestone.status == MilestoneStatus::Released)
+        {
+            from_tx.status = 3;
+            record_stats(&mut deps.storage, env.block.time, |stats| {
+                stats.finalized += 1
+            })?;
+            messages = distribute_fees(
+                deps,
+                env,
+                &config,
+                vec![(
+                    from_tx.fee_token.clone(),
+                    from_tx.fee,
+                    from_tx.referrer.clone(),
+                )],
+            )?;
+        } else {
+            from_tx.status = 2;
+            record_stats(&mut deps.storage, env.block.time, |stats| {
+                stats.cancelled += 1
+            })?;
+            record_fees(
+                &mut deps.storage,
+                &from_tx.fee_token.address,
+                env.block.time,
+                |stats| {
+                    stats.refunded = stats.refunded + from_tx.fee;
+                    Ok(())
+                },
+            )?;
+            messages.push(snip20::transfer_msg(
+                payout_address(deps, from_tx.creator.clone())?,
+                from_tx.fee,
+                None,
+                BLOCK_SIZE,
+                from_tx.fee_token.contract_hash.clone(),
+                from_tx.fee_token.address.clone(),
+            )?);
         }
+        to_tx.status = from_tx.status;
     }
-
+    from_tx.milestones = Some(milestones.clone());
+    to_tx.milestones = Some(milestones);
+    update_tx(&mut deps.storage, &from_tx.from.clone(), from_tx)?;
+    update_tx(&mut deps.storage, &to_tx.to.clone(), to_tx)?;
+
+    Ok(messages)
+}
+
+fn accept_new_admin_nomination<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+) -> StdResult<HandleResponse> {
+    let mut config: Config = TypedStoreMut::attach(&mut deps.storage)
+        .load(CONFIG_KEY)
+        .unwrap();
+    if config.new_admin_nomination.is_none() {
+        return Err(StdError::Unauthorized { backtrace: None });
+    }
+    authorize(
+        env.message.sender.clone(),
+        config.new_admin_nomination.clone().unwrap(),
+    )?;
+
+    config.admin = config.new_admin_nomination.unwrap();
+    config.new_admin_nomination = None;
+    TypedStoreMut::attach(&mut deps.storage).store(CONFIG_KEY, &config)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+// The sender of a receive request acknowledges it so that it can be paid
+fn acknowledge_request<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    position: u32,
+) -> StdResult<HandleResponse> {
+    let (mut from_tx, mut to_tx) = verify_txs_for_acknowledgement(
+        &deps.api,
+        &mut deps.storage,
+        &deps.api.canonical_address(&env.message.sender)?,
+        position,
+        &[7],
+    )?;
+    from_tx.status = 1;
+    to_tx.status = 1;
+    update_tx(&mut deps.storage, &from_tx.from.clone(), from_tx)?;
+    update_tx(&mut deps.storage, &to_tx.to.clone(), to_tx)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+// The sender of a receive request declines it instead of paying it.
+// The fee is kept like it is for a finalized Tx.
+fn decline<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    position: u32,
+    reason: Option<String>,
+) -> StdResult<HandleResponse> {
+    verify_text_length("Reason", &reason)?;
+    let (mut from_tx, mut to_tx) = verify_txs_for_acknowledgement(
+        &deps.api,
+        &mut deps.storage,
+        &deps.api.canonical_address(&env.message.sender)?,
+        position,
+        &[7],
+    )?;
+    from_tx.status = 8;
+    to_tx.status = 8;
+    from_tx.cancellation_reason = reason.clone();
+    to_tx.cancellation_reason = reason;
+    update_tx(&mut deps.storage, &from_tx.from.clone(), from_tx.clone())?;
+    update_tx(&mut deps.storage, &to_tx.to.clone(), to_tx)?;
+    record_stats(&mut deps.storage, env.block.time, |stats| {
+        stats.cancelled += 1
+    })?;
+
+    let config: Config = TypedStore::attach(&mut deps.storage)
+        .load(CONFIG_KEY)
+        .unwrap();
+    let messages: Vec<CosmosMsg> = distribute_fees(
+        deps,
+        env,
+        &config,
+        vec![(from_tx.fee_token.clone(), from_tx.fee, from_tx.referrer)],
+    )?;
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: None,
+    })
+}
+
+fn block_address<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    address: HumanAddr,
+    blocked: bool,
+) -> StdResult<HandleResponse> {
+    set_blocked(
+        &mut deps.storage,
+        &deps.api.canonical_address(&env.message.sender)?,
+        &deps.api.canonical_address(&address)?,
+        blocked,
+    )?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+pub fn correct_amount_of_token(
+    amount_received: Uint128,
+    amount_wanted: Uint128,
+    token_received: HumanAddr,
+    token_wanted: HumanAddr,
+) -> StdResult<()> {
+    if amount_received != amount_wanted {
+        return Err(StdError::generic_err("Wrong amount received."));
+    }
+    if token_received != token_wanted {
+        return Err(StdError::generic_err("Wrong token received."));
+    }
+
+    Ok(())
+}
+
+fn refund<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    from: HumanAddr,
+    amount: Uint128,
+    received_token: &HumanAddr,
+    position: u32,
+) -> StdResult<HandleResponse> {
+    let (mut from_tx, mut to_tx) = verify_txs_for_refund(
+        &deps.api,
+        &mut deps.storage,
+        &deps.api.canonical_address(&from)?,
+        amount,
+        position,
+        received_token.clone(),
+    )?;
+
+    // Update Txs, they are refunded once the whole amount has been sent back.
+    // The sender's copy is left alone if they have pruned it.
+    to_tx.refunded = to_tx.refunded + amount;
+    to_tx.status = if to_tx.refunded == to_tx.amount { 4 } else { 5 };
+    update_tx(&mut deps.storage, &to_tx.to.clone(), to_tx.clone())?;
+    if from_tx.status != 9 {
+        from_tx.refunded = to_tx.refunded;
+        from_tx.status = to_tx.status;
+        update_tx(&mut deps.storage, &from_tx.from.clone(), from_tx.clone())?;
+    }
+    store_refund_txs(
+        &mut deps.storage,
+        &from_tx,
+        &to_tx,
+        from,
+        amount,
+        &env.block,
+    )?;
+
+    // Send refund to the sender
+    let mut messages: Vec<CosmosMsg> = vec![];
+    messages.push(snip20::transfer_msg(
+        payout_address(deps, deps.api.human_address(&to_tx.from)?)?,
+        amount,
+        None,
+        BLOCK_SIZE,
+        to_tx.token.contract_hash,
+        to_tx.token.address,
+    )?);
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: None,
+    })
+}
+
+// Prune the user's own history. The counterparty's copies of the Txs are not touched.
+fn prune_history<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    before_position: Option<u32>,
+    before_time: Option<u64>,
+) -> StdResult<HandleResponse> {
+    if before_position.is_none() && before_time.is_none() {
+        return Err(StdError::generic_err(
+            "Either before_position or before_time must be set.",
+        ));
+    }
+    let pruned = prune_txs(
+        &mut deps.storage,
+        &deps.api.canonical_address(&env.message.sender)?,
+        before_position,
+        before_time,
+    )?;
+
+    pad_response(Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::PruneHistory { pruned })?),
+    }))
+}
+
+fn register_token<S: Storage>(
+    storage: &mut S,
+    contract_code_hash: String,
+    token: SecretContract,
+) -> StdResult<Option<CosmosMsg>> {
+    let mut cosmos_msg: Option<CosmosMsg> = None;
+    let mut registered_tokens_storage = RegisteredTokensStorage::from_storage(storage);
+    let contract_hash = registered_tokens_storage.get_contract_hash(token.address.clone());
+    if contract_hash.is_none() {
+        registered_tokens_storage.set_contract_hash(token.address.clone(), &token.contract_hash);
+        cosmos_msg = Some(snip20::register_receive_msg(
+            contract_code_hash,
+            None,
+            BLOCK_SIZE,
+            token.contract_hash,
+            token.address,
+        )?);
+    }
+
+    Ok(cosmos_msg)
+}
+
+fn create_claim<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    from: HumanAddr,
+    amount: Uint128,
+    received_token: &HumanAddr,
+    request: ClaimRequest,
+) -> StdResult<HandleResponse> {
+    let ClaimRequest {
+        code_hash,
+        send_amount,
+        description,
+        token,
+        expires_at,
+    } = request;
+    if code_hash.len() != 32 {
+        return Err(StdError::generic_err("Code hash must be a SHA-256 hash."));
+    }
+    if send_amount.is_zero() {
+        return Err(StdError::generic_err(
+            "Send amount must be greater than zero.",
+        ));
+    }
+    verify_text_length("Description", &description)?;
+    verify_token(&token)?;
+    if expires_at <= env.block.time {
+        return Err(StdError::generic_err("Expiry must be in the future."));
+    }
+    if claim(&deps.storage, code_hash.as_slice())?.is_some() {
+        return Err(StdError::generic_err("Claim code is already in use."));
+    }
+    let config: Config = TypedStore::attach(&mut deps.storage)
+        .load(CONFIG_KEY)
+        .unwrap();
+    let fee_token = config.fee_token(received_token);
+    let fee = effective_fee(deps, &config, &from, fee_token.fee)?;
+    correct_amount_of_token(
+        amount,
+        fee,
+        received_token.clone(),
+        fee_token.token.address.clone(),
+    )?;
+    store_claim(
+        &mut deps.storage,
+        code_hash.as_slice(),
+        &Claim {
+            sender: from,
+            send_amount,
+            token: token.clone(),
+            description,
+            fee,
+            fee_token: fee_token.token.clone(),
+            funded: false,
+            expires_at,
+        },
+    )?;
+    record_fees(
+        &mut deps.storage,
+        &fee_token.token.address,
+        env.block.time,
+        |stats| {
+            stats.collected = stats.collected + fee;
+            Ok(())
+        },
+    )?;
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let register_token_msg: Option<CosmosMsg> =
+        register_token(&mut deps.storage, env.contract_code_hash.clone(), token)?;
+    if register_token_msg.is_some() {
+        messages.push(register_token_msg.unwrap())
+    }
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: None,
+    })
+}
+
+// Escrow the funds of a claim, after which it can be claimed
+fn fund_claim<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    from: HumanAddr,
+    amount: Uint128,
+    received_token: &HumanAddr,
+    code_hash: Binary,
+) -> StdResult<HandleResponse> {
+    let mut claim = claim(&deps.storage, code_hash.as_slice())?
+        .ok_or_else(|| StdError::generic_err("Claim not found."))?;
+    authorize(claim.sender.clone(), from.clone())?;
+    if claim.funded {
+        return Err(StdError::generic_err("Claim already funded."));
+    }
+    correct_amount_of_token(
+        amount,
+        claim.send_amount,
+        received_token.clone(),
+        claim.token.address.clone(),
+    )?;
+    // Whoever redeems the claim is not known yet
+    record_spending(
+        &mut deps.storage,
+        &deps.api.canonical_address(&from)?,
+        None,
+        &claim.token.address,
+        claim.send_amount,
+        env.block.time,
+        env.block.time,
+    )?;
+    claim.funded = true;
+    store_claim(&mut deps.storage, code_hash.as_slice(), &claim)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+// Send the funds of a claim to the recipient, who is stored as the receiver of a finalized Tx
+fn redeem_claim<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    code: String,
+    recipient: HumanAddr,
+) -> StdResult<HandleResponse> {
+    let code_hash = hash_code(&code);
+    let claim = claim(&deps.storage, &code_hash)?
+        .ok_or_else(|| StdError::generic_err("Claim not found."))?;
+    if !claim.funded {
+        return Err(StdError::generic_err("Claim not funded."));
+    }
+    if env.block.time >= claim.expires_at {
+        return Err(StdError::generic_err("Claim expired."));
+    }
+    remove_claim(&mut deps.storage, &code_hash);
+    let config: Config = TypedStore::attach(&mut deps.storage)
+        .load(CONFIG_KEY)
+        .unwrap();
+    let sender_canonical = deps.api.canonical_address(&claim.sender)?;
+    let recipient_canonical = deps.api.canonical_address(&recipient)?;
+    store_txs(
+        &mut deps.storage,
+        &sender_canonical,
+        &recipient_canonical,
+        NewTx {
+            creator: claim.sender.clone(),
+            amount: claim.send_amount,
+            token: claim.token.clone(),
+            description: claim.description.clone(),
+            fee: claim.fee,
+            fee_token: claim.fee_token.clone(),
+        },
+        TxTerms::default(),
+        3,
+        &env.block,
+    )?;
+    let new_participants = record_participants(
+        &mut deps.storage,
+        &[&sender_canonical, &recipient_canonical],
+    )?;
+    record_stats(&mut deps.storage, env.block.time, |stats| {
+        stats.send_requests_created += 1;
+        stats.finalized += 1;
+        stats.unique_participants += new_participants;
+    })?;
+    record_volume(
+        &mut deps.storage,
+        &claim.token.address,
+        env.block.time,
+        claim.send_amount,
+    )?;
+
+    let mut messages: Vec<CosmosMsg> = distribute_fees(
+        deps,
+        env,
+        &config,
+        vec![(claim.fee_token.clone(), claim.fee, None)],
+    )?;
+    messages.push(snip20::transfer_msg(
+        recipient,
+        claim.send_amount,
+        None,
+        BLOCK_SIZE,
+        claim.token.contract_hash,
+        claim.token.address,
+    )?);
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: None,
+    })
+}
+
+// Return the funds and fee of a claim to the sender. Funded claims can only be reclaimed once expired.
+fn reclaim<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    code_hash: Binary,
+) -> StdResult<HandleResponse> {
+    let claim = claim(&deps.storage, code_hash.as_slice())?
+        .ok_or_else(|| StdError::generic_err("Claim not found."))?;
+    authorize(claim.sender.clone(), env.message.sender.clone())?;
+    if claim.funded && env.block.time < claim.expires_at {
+        return Err(StdError::generic_err("Claim has not expired yet."));
+    }
+    remove_claim(&mut deps.storage, code_hash.as_slice());
+
+    record_fees(
+        &mut deps.storage,
+        &claim.fee_token.address,
+        env.block.time,
+        |stats| {
+            stats.refunded = stats.refunded + claim.fee;
+            Ok(())
+        },
+    )?;
+    let mut messages: Vec<CosmosMsg> = vec![];
+    messages.push(snip20::transfer_msg(
+        claim.sender.clone(),
+        claim.fee,
+        None,
+        BLOCK_SIZE,
+        claim.fee_token.contract_hash,
+        claim.fee_token.address,
+    )?);
+    if claim.funded {
+        messages.push(snip20::transfer_msg(
+            claim.sender,
+            claim.send_amount,
+            None,
+            BLOCK_SIZE,
+            claim.token.contract_hash,
+            claim.token.address,
+        )?);
+    }
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: None,
+    })
+}
+
+// Close a payment link and refund the fees for the uses that are left
+fn close_payment_link<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    code_hash: Binary,
+) -> StdResult<HandleResponse> {
+    let link = payment_link(&deps.storage, code_hash.as_slice())?
+        .ok_or_else(|| StdError::generic_err("Payment link not found."))?;
+    authorize(link.receiver.clone(), env.message.sender.clone())?;
+    remove_payment_link(&mut deps.storage, code_hash.as_slice());
+
+    let refund = fee_times(link.fee, (link.max_uses - link.uses) as u128)?;
+    record_fees(
+        &mut deps.storage,
+        &link.fee_token.address,
+        env.block.time,
+        |stats| {
+            stats.refunded = stats.refunded + refund;
+            Ok(())
+        },
+    )?;
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if !refund.is_zero() {
+        messages.push(snip20::transfer_msg(
+            link.receiver,
+            refund,
+            None,
+            BLOCK_SIZE,
+            link.fee_token.contract_hash,
+            link.fee_token.address,
+        )?);
+    }
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: None,
+    })
+}
+
+fn create_payment_link<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    from: HumanAddr,
+    amount: Uint128,
+    received_token: &HumanAddr,
+    request: PaymentLinkRequest,
+) -> StdResult<HandleResponse> {
+    let PaymentLinkRequest {
+        code_hash,
+        send_amount,
+        description,
+        token,
+        max_uses,
+        referrer,
+    } = request;
+    let max_uses = max_uses.unwrap_or(1);
+    if code_hash.len() != 32 {
+        return Err(StdError::generic_err("Code hash must be a SHA-256 hash."));
+    }
+    if max_uses == 0 {
+        return Err(StdError::generic_err("Max uses must be greater than zero."));
+    }
+    if send_amount.is_zero() {
+        return Err(StdError::generic_err(
+            "Send amount must be greater than zero.",
+        ));
+    }
+    verify_text_length("Description", &description)?;
+    verify_token(&token)?;
+    if payment_link(&deps.storage, code_hash.as_slice())?.is_some() {
+        return Err(StdError::generic_err(
+            "Payment link code is already in use.",
+        ));
+    }
+    let config: Config = TypedStore::attach(&mut deps.storage)
+        .load(CONFIG_KEY)
+        .unwrap();
+    // The fee for every use is paid up front
+    let fee_token = config.fee_token(received_token);
+    let fee = effective_fee(deps, &config, &from, fee_token.fee)?;
+    let total_fee = fee_times(fee, max_uses as u128)?;
+    correct_amount_of_token(
+        amount,
+        total_fee,
+        received_token.clone(),
+        fee_token.token.address.clone(),
+    )?;
+    verify_referrer(deps, &deps.api.canonical_address(&from)?, &referrer)?;
+    store_payment_link(
+        &mut deps.storage,
+        code_hash.as_slice(),
+        &PaymentLink {
+            receiver: from,
+            send_amount,
+            token: token.clone(),
+            description,
+            referrer,
+            fee,
+            fee_token: fee_token.token.clone(),
+            uses: 0,
+            max_uses,
+        },
+    )?;
+    record_fees(
+        &mut deps.storage,
+        &fee_token.token.address,
+        env.block.time,
+        |stats| {
+            stats.collected = stats.collected + total_fee;
+            Ok(())
+        },
+    )?;
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let register_token_msg: Option<CosmosMsg> =
+        register_token(&mut deps.storage, env.contract_code_hash.clone(), token)?;
+    if register_token_msg.is_some() {
+        messages.push(register_token_msg.unwrap())
+    }
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: None,
+    })
+}
+
+fn create_receive_request<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    from: HumanAddr,
+    amount: Uint128,
+    received_token: &HumanAddr,
+    request: ReceiveRequest,
+) -> StdResult<HandleResponse> {
+    let config: Config = TypedStore::attach(&mut deps.storage)
+        .load(CONFIG_KEY)
+        .unwrap();
+    let fee_token = config.fee_token(received_token);
+    let fee = effective_fee(deps, &config, &from, fee_token.fee)?;
+    correct_amount_of_token(
+        amount,
+        fee,
+        received_token.clone(),
+        fee_token.token.address.clone(),
+    )?;
+    let from_canonical = deps.api.canonical_address(&from)?;
+    let address_canonical = deps.api.canonical_address(&request.address)?;
+    verify_acceptance(
+        &deps.storage,
+        &address_canonical,
+        &from_canonical,
+        request.send_amount,
+        &request.token.address,
+        true,
+    )?;
+    verify_referrer(deps, &from_canonical, &request.referrer)?;
+    // The sender may want to acknowledge the request before it can be paid
+    let mut status: u8 = 1;
+    if acceptance_policy(&deps.storage, &address_canonical)?.acknowledge_receive_requests {
+        status = 7;
+    }
+    store_txs(
+        &mut deps.storage,
+        &address_canonical,
+        &from_canonical,
+        NewTx {
+            creator: from,
+            amount: request.send_amount,
+            token: request.token.clone(),
+            description: request.description,
+            fee,
+            fee_token: fee_token.token.clone(),
+        },
+        TxTerms {
+            attachment_hash: request.attachment_hash,
+            referrer: request.referrer,
+            cancellation_policy: request.cancellation_policy.unwrap_or_default(),
+            cancellation_penalty: request.cancellation_penalty.unwrap_or_default(),
+            ..TxTerms::default()
+        },
+        status,
+        &env.block,
+    )?;
+    let new_participants =
+        record_participants(&mut deps.storage, &[&from_canonical, &address_canonical])?;
+    record_stats(&mut deps.storage, env.block.time, |stats| {
+        stats.receive_requests_created += 1;
+        stats.unique_participants += new_participants;
+    })?;
+    record_fees(
+        &mut deps.storage,
+        &fee_token.token.address,
+        env.block.time,
+        |stats| {
+            stats.collected = stats.collected + fee;
+            Ok(())
+        },
+    )?;
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let register_token_msg: Option<CosmosMsg> = register_token(
+        &mut deps.storage,
+        env.contract_code_hash.clone(),
+        request.token,
+    )?;
+    if register_token_msg.is_some() {
+        messages.push(register_token_msg.unwrap())
+    }
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: None,
+    })
+}
+
+fn create_send_request<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    from: HumanAddr,
+    amount: Uint128,
+    received_token: &HumanAddr,
+    request: SendRequest,
+) -> StdResult<HandleResponse> {
+    let config: Config = TypedStore::attach(&mut deps.storage)
+        .load(CONFIG_KEY)
+        .unwrap();
+    let fee_token = config.fee_token(received_token);
+    let fee = effective_fee(deps, &config, &from, fee_token.fee)?;
+    correct_amount_of_token(
+        amount,
+        fee,
+        received_token.clone(),
+        fee_token.token.address.clone(),
+    )?;
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let (_position, register_token_msg) =
+        store_send_request(deps, env, from, fee, &fee_token.token, request)?;
+    record_fees(
+        &mut deps.storage,
+        &fee_token.token.address,
+        env.block.time,
+        |stats| {
+            stats.collected = stats.collected + fee;
+            Ok(())
+        },
+    )?;
+    if register_token_msg.is_some() {
+        messages.push(register_token_msg.unwrap())
+    }
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: None,
+    })
+}
+
+fn create_send_requests<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    from: HumanAddr,
+    amount: Uint128,
+    received_token: &HumanAddr,
+    requests: Vec<SendRequest>,
+) -> StdResult<HandleResponse> {
+    if requests.is_empty() {
+        return Err(StdError::generic_err("Requests must not be empty."));
+    }
+    let config: Config = TypedStore::attach(&mut deps.storage)
+        .load(CONFIG_KEY)
+        .unwrap();
+    let fee_token = config.fee_token(received_token);
+    let fee = effective_fee(deps, &config, &from, fee_token.fee)?;
+    let total_fee = fee_times(fee, requests.len() as u128)?;
+    correct_amount_of_token(
+        amount,
+        total_fee,
+        received_token.clone(),
+        fee_token.token.address.clone(),
+    )?;
+    // Any invalid request reverts the whole upload
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let mut positions: Vec<u32> = vec![];
+    for request in requests {
+        let (position, register_token_msg) =
+            store_send_request(deps, env, from.clone(), fee, &fee_token.token, request)?;
+        positions.push(position);
+        if register_token_msg.is_some() {
+            messages.push(register_token_msg.unwrap())
+        }
+    }
+    record_fees(
+        &mut deps.storage,
+        &fee_token.token.address,
+        env.block.time,
+        |stats| {
+            stats.collected = stats.collected + total_fee;
+            Ok(())
+        },
+    )?;
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::CreateSendRequests { positions })?),
+    })
+}
+
+// Store the Txs for a send request and register its token if needed.
+// Returns the position of the Tx in the sender's Txs.
+fn store_send_request<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    from: HumanAddr,
+    fee: Uint128,
+    fee_token: &SecretContract,
+    mut request: SendRequest,
+) -> StdResult<(u32, Option<CosmosMsg>)> {
+    if request.send_amount.is_zero() {
+        return Err(StdError::generic_err(
+            "Send amount must be greater than zero.",
+        ));
+    }
+    if let Some(hash_timelock) = &request.hash_timelock {
+        if hash_timelock.hashlock.len() != 32 {
+            return Err(StdError::generic_err("Hashlock must be a SHA-256 hash."));
+        }
+        if hash_timelock.timelock <= env.block.time {
+            return Err(StdError::generic_err("Timelock must be in the future."));
+        }
+    }
+    if let Some(milestones) = &mut request.milestones {
+        if request.hash_timelock.is_some() {
+            return Err(StdError::generic_err(
+                "Txs can not have both a hash timelock and milestones.",
+            ));
+        }
+        if milestones.is_empty() {
+            return Err(StdError::generic_err("Milestones must not be empty."));
+        }
+        if milestones.len() > MAX_MILESTONES {
+            return Err(StdError::generic_err(format!(
+                "Txs can have at most {} milestones.",
+                MAX_MILESTONES
+            )));
+        }
+        let mut total = Uint128(0);
+        for milestone in milestones.iter_mut() {
+            if milestone.amount.is_zero() {
+                return Err(StdError::generic_err(
+                    "Milestone amount must be greater than zero.",
+                ));
+            }
+            // Each label becomes the description of the Tx that releases the milestone
+            verify_text_length("Milestone label", &Some(milestone.label.clone()))?;
+            total = total + milestone.amount;
+            milestone.status = MilestoneStatus::Pending;
+        }
+        if total != request.send_amount {
+            return Err(StdError::generic_err(
+                "Milestone amounts must add up to the send amount.",
+            ));
+        }
+    }
+    let from_canonical = deps.api.canonical_address(&from)?;
+    let to_canonical = deps.api.canonical_address(&request.address)?;
+    if organization(&deps.storage, &to_canonical)?.is_some() {
+        return Err(StdError::generic_err(
+            "Organizations can not receive payments.",
+        ));
+    }
+    verify_acceptance(
+        &deps.storage,
+        &to_canonical,
+        &from_canonical,
+        request.send_amount,
+        &request.token.address,
+        false,
+    )?;
+    verify_referrer(deps, &from_canonical, &request.referrer)?;
+    // Skip address confirmation if the receiver has already confirmed this address before
+    let mut status: u8 = 0;
+    if request.skip_confirmation_if_verified.unwrap_or(false)
+        && address_book_entry(&deps.storage, &from_canonical, &to_canonical)?.is_some()
+    {
+        status = 1;
+    }
+    let position = store_txs(
+        &mut deps.storage,
+        &from_canonical,
+        &to_canonical,
+        NewTx {
+            creator: from,
+            amount: request.send_amount,
+            token: request.token.clone(),
+            description: request.description,
+            fee,
+            fee_token: fee_token.clone(),
+        },
+        TxTerms {
+            attachment_hash: request.attachment_hash,
+            referrer: request.referrer,
+            cancellation_policy: request.cancellation_policy.unwrap_or_default(),
+            cancellation_penalty: request.cancellation_penalty.unwrap_or_default(),
+            hash_timelock: request.hash_timelock,
+            milestones: request.milestones,
+        },
+        status,
+        &env.block,
+    )?;
+    let new_participants =
+        record_participants(&mut deps.storage, &[&from_canonical, &to_canonical])?;
+    record_stats(&mut deps.storage, env.block.time, |stats| {
+        stats.send_requests_created += 1;
+        stats.unique_participants += new_participants;
+    })?;
+    let register_token_msg: Option<CosmosMsg> = register_token(
+        &mut deps.storage,
+        env.contract_code_hash.clone(),
+        request.token,
+    )?;
+
+    Ok((position, register_token_msg))
+}
+
+// The fee an address pays in a fee token to create a request, after any exemption or holder discount
+fn effective_fee<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    config: &Config,
+    address: &HumanAddr,
+    fee: Uint128,
+) -> StdResult<Uint128> {
+    let address_canonical = deps.api.canonical_address(address)?;
+    if is_fee_exempt(&deps.storage, &address_canonical)? {
+        return Ok(Uint128(0));
+    }
+
+    // Only the largest discount the address qualifies for is applied
+    let mut discount_bps: u16 = 0;
+    for fee_discount in &config.fee_discounts {
+        if fee_discount.discount_bps <= discount_bps {
+            continue;
+        }
+        let key = fee_discount_key(
+            &deps.storage,
+            &address_canonical,
+            &fee_discount.token.address,
+        )?;
+        if key.is_none() {
+            continue;
+        }
+        // A viewing key that no longer works means no discount rather than a failed request
+        let balance = snip20::balance_query(
+            &deps.querier,
+            address.clone(),
+            key.unwrap(),
+            BLOCK_SIZE,
+            fee_discount.token.contract_hash.clone(),
+            fee_discount.token.address.clone(),
+        );
+        if let Ok(balance) = balance {
+            if balance.amount >= fee_discount.minimum_balance {
+                discount_bps = fee_discount.discount_bps;
+            }
+        }
+    }
+
+    Ok(fee.multiply_ratio(10_000 - discount_bps as u128, 10_000u128))
+}
+
+// The fee for that many requests or uses, which can overflow with user input
+fn fee_times(fee: Uint128, count: u128) -> StdResult<Uint128> {
+    fee.u128()
+        .checked_mul(count)
+        .map(Uint128)
+        .ok_or_else(|| StdError::generic_err("Total fee overflows."))
+}
+
+// The fee token of the given address, SSCRT by default
+fn accepted_fee_token(config: &Config, token: Option<HumanAddr>) -> StdResult<FeeToken> {
+    let token = token.unwrap_or_else(|| config.sscrt.address.clone());
+    let fee_token = config.fee_token(&token);
+    if fee_token.token.address != token {
+        return Err(StdError::generic_err("Token is not accepted for fees."));
+    }
+
+    Ok(fee_token)
+}
+
+fn grant_viewer<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    address: HumanAddr,
+    scope: ViewerScope,
+    expires_at: Option<u64>,
+) -> StdResult<HandleResponse> {
+    if address == env.message.sender {
+        return Err(StdError::generic_err("Can not grant a viewer to yourself."));
+    }
+
+    set_viewer_grant(
+        &mut deps.storage,
+        &deps.api.canonical_address(&env.message.sender)?,
+        &deps.api.canonical_address(&address)?,
+        Some(&ViewerGrant { scope, expires_at }),
+    )?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+fn revoke_viewer<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    address: HumanAddr,
+) -> StdResult<HandleResponse> {
+    set_viewer_grant(
+        &mut deps.storage,
+        &deps.api.canonical_address(&env.message.sender)?,
+        &deps.api.canonical_address(&address)?,
+        None,
+    )?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+fn create_organization<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    name: String,
+) -> StdResult<HandleResponse> {
+    verify_text_length("Name", &Some(name.clone()))?;
+    let address_canonical = next_organization_address(
+        &mut deps.storage,
+        &deps.api.canonical_address(&env.contract.address)?,
+    )?;
+    let address = deps.api.human_address(&address_canonical)?;
+    let organization = Organization {
+        address: address.clone(),
+        name: name.clone(),
+        admin: env.message.sender.clone(),
+        members: vec![],
+        approval_policies: vec![],
+    };
+    store_organization(&mut deps.storage, &address_canonical, &organization)?;
+    append_audit_entry(
+        &mut deps.storage,
+        &address_canonical,
+        &OrganizationAuditEntry {
+            member: env.message.sender.clone(),
+            action: OrganizationAction::Handle {
+                msg: HandleMsg::CreateOrganization { name },
+            },
+            block_time: env.block.time,
+        },
+    )?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::CreateOrganization { address })?),
+    })
+}
+
+fn set_organization_member<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    organization: HumanAddr,
+    address: HumanAddr,
+    role: Option<OrganizationRole>,
+) -> StdResult<HandleResponse> {
+    let organization_canonical = deps.api.canonical_address(&organization)?;
+    let mut organization = organization_at(&deps.storage, &organization_canonical)?;
+    authorize(env.message.sender.clone(), organization.admin.clone())?;
+    if address == organization.admin {
+        return Err(StdError::generic_err(
+            "The admin's role can not be changed.",
+        ));
+    }
+
+    organization
+        .members
+        .retain(|member| member.address != address);
+    if let Some(role) = role {
+        organization.members.push(OrganizationMember {
+            address: address.clone(),
+            role,
+        });
+    }
+    store_organization(&mut deps.storage, &organization_canonical, &organization)?;
+    append_audit_entry(
+        &mut deps.storage,
+        &organization_canonical,
+        &OrganizationAuditEntry {
+            member: env.message.sender.clone(),
+            action: OrganizationAction::Handle {
+                msg: HandleMsg::SetOrganizationMember {
+                    organization: organization.address,
+                    address,
+                    role,
+                },
+            },
+            block_time: env.block.time,
+        },
+    )?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+fn set_approval_policies<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    organization: HumanAddr,
+    approval_policies: Vec<ApprovalPolicy>,
+) -> StdResult<HandleResponse> {
+    if approval_policies
+        .iter()
+        .any(|policy| policy.required_approvals == 0)
+    {
+        return Err(StdError::generic_err(
+            "Required approvals must be greater than zero.",
+        ));
+    }
+    let organization_canonical = deps.api.canonical_address(&organization)?;
+    let mut organization = organization_at(&deps.storage, &organization_canonical)?;
+    authorize(env.message.sender.clone(), organization.admin.clone())?;
+
+    organization.approval_policies = approval_policies.clone();
+    store_organization(&mut deps.storage, &organization_canonical, &organization)?;
+    append_audit_entry(
+        &mut deps.storage,
+        &organization_canonical,
+        &OrganizationAuditEntry {
+            member: env.message.sender.clone(),
+            action: OrganizationAction::Handle {
+                msg: HandleMsg::SetApprovalPolicies {
+                    organization: organization.address,
+                    approval_policies,
+                },
+            },
+            block_time: env.block.time,
+        },
+    )?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+// Record an approver's approval of an escrowed payment and release it once it has enough
+fn approve_payment<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    organization: HumanAddr,
+    position: u32,
+) -> StdResult<HandleResponse> {
+    let (organization_canonical, organization) = member_organization(
+        deps,
+        &organization,
+        &env.message.sender,
+        OrganizationRole::Approver,
+    )?;
+    let (mut from_tx, mut to_tx) =
+        payment_awaiting_approval(deps, &organization_canonical, position)?;
+    let mut approvals = payment_approvals(&deps.storage, &organization_canonical, position)?;
+    if approvals
+        .iter()
+        .any(|approval| approval.approver == env.message.sender)
+    {
+        return Err(StdError::generic_err("Payment already approved."));
+    }
+    approvals.push(PaymentApproval {
+        approver: env.message.sender.clone(),
+        block_time: env.block.time,
+    });
+    set_payment_approvals(
+        &mut deps.storage,
+        &organization_canonical,
+        position,
+        &approvals,
+    )?;
+    append_audit_entry(
+        &mut deps.storage,
+        &organization_canonical,
+        &OrganizationAuditEntry {
+            member: env.message.sender.clone(),
+            action: OrganizationAction::Handle {
+                msg: HandleMsg::ApprovePayment {
+                    organization: organization.address.clone(),
+                    position,
+                },
+            },
+            block_time: env.block.time,
+        },
+    )?;
+    // A policy that was removed or lowered since the payment no longer holds it back
+    let required_approvals = organization
+        .required_approvals(&from_tx.token.address, from_tx.amount)
+        .unwrap_or(0);
+    if (approvals.len() as u32) < required_approvals {
+        return Ok(HandleResponse {
+            messages: vec![],
+            log: vec![],
+            data: None,
+        });
+    }
+
+    // Release the payment
+    set_escrowed_payment(&mut deps.storage, &organization_canonical, position, None)?;
+    from_tx.status = 3;
+    to_tx.status = 3;
+    update_tx(&mut deps.storage, &from_tx.from.clone(), from_tx.clone())?;
+    update_tx(&mut deps.storage, &to_tx.to.clone(), to_tx)?;
+    record_stats(&mut deps.storage, env.block.time, |stats| {
+        stats.finalized += 1;
+    })?;
+    record_volume(
+        &mut deps.storage,
+        &from_tx.token.address,
+        env.block.time,
+        from_tx.amount,
+    )?;
+    let config: Config = TypedStore::attach(&mut deps.storage)
+        .load(CONFIG_KEY)
+        .unwrap();
+    let mut messages: Vec<CosmosMsg> = distribute_fees(
+        deps,
+        env,
+        &config,
+        vec![(
+            from_tx.fee_token.clone(),
+            from_tx.fee,
+            from_tx.referrer.clone(),
+        )],
+    )?;
+    messages.push(snip20::transfer_msg(
+        deps.api.human_address(&from_tx.to)?,
+        from_tx.amount,
+        None,
+        BLOCK_SIZE,
+        from_tx.token.contract_hash,
+        from_tx.token.address,
+    )?);
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: None,
+    })
+}
+
+// Return an escrowed payment to the member who paid it, so that the Tx can be paid again
+fn reject_payment<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    organization: HumanAddr,
+    position: u32,
+) -> StdResult<HandleResponse> {
+    let (organization_canonical, organization) = member_organization(
+        deps,
+        &organization,
+        &env.message.sender,
+        OrganizationRole::Approver,
+    )?;
+    let (mut from_tx, mut to_tx) =
+        payment_awaiting_approval(deps, &organization_canonical, position)?;
+    // Payments escrowed before their payer was recorded go back to the admin
+    let payer = match escrowed_payment(&deps.storage, &organization_canonical, position)? {
+        Some(escrowed_payment) => {
+            release_spending(
+                &mut deps.storage,
+                &organization_canonical,
+                &from_tx.to,
+                &from_tx.token.address,
+                from_tx.amount,
+                escrowed_payment.block_time,
+            )?;
+            set_escrowed_payment(&mut deps.storage, &organization_canonical, position, None)?;
+            escrowed_payment.payer
+        }
+        None => organization.admin,
+    };
+
+    from_tx.status = 1;
+    to_tx.status = 1;
+    update_tx(&mut deps.storage, &from_tx.from.clone(), from_tx.clone())?;
+    update_tx(&mut deps.storage, &to_tx.to.clone(), to_tx)?;
+    set_payment_approvals(&mut deps.storage, &organization_canonical, position, &[])?;
+    append_audit_entry(
+        &mut deps.storage,
+        &organization_canonical,
+        &OrganizationAuditEntry {
+            member: env.message.sender.clone(),
+            action: OrganizationAction::Handle {
+                msg: HandleMsg::RejectPayment {
+                    organization: organization.address,
+                    position,
+                },
+            },
+            block_time: env.block.time,
+        },
+    )?;
+
+    Ok(HandleResponse {
+        messages: vec![snip20::transfer_msg(
+            payer,
+            from_tx.amount,
+            None,
+            BLOCK_SIZE,
+            from_tx.token.contract_hash,
+            from_tx.token.address,
+        )?],
+        log: vec![],
+        data: None,
+    })
+}
+
+fn payment_awaiting_approval<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    organization: &CanonicalAddr,
+    position: u32,
+) -> StdResult<(Tx, Tx)> {
+    let from_tx = tx_at_position(&mut deps.storage, organization, position)?;
+    if &from_tx.from != organization
+        || from_tx.status != 6
+        || from_tx.hash_timelock.is_some()
+        || from_tx.milestones.is_some()
+    {
+        return Err(StdError::generic_err("Payment is not awaiting approval."));
+    }
+    let to_tx = tx_at_position(
+        &mut deps.storage,
+        &from_tx.to,
+        from_tx.other_storage_position,
+    )?;
+
+    Ok((from_tx, to_tx))
+}
+
+// Run a handle as the organization, for a member with a role that allows it
+fn on_behalf_of<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    organization: HumanAddr,
+    msg: HandleMsg,
+) -> StdResult<HandleResponse> {
+    let role = match msg {
+        HandleMsg::SetMemo { .. } => OrganizationRole::Requester,
+        HandleMsg::AcknowledgeRequest { .. }
+        | HandleMsg::CancelMilestone { .. }
+        | HandleMsg::Decline { .. }
+        | HandleMsg::RefundHtlc { .. }
+        | HandleMsg::ReleaseMilestone { .. } => OrganizationRole::Payer,
+        _ => {
+            return Err(StdError::generic_err(
+                "Action is not available to organizations.",
+            ))
+        }
+    };
+    let (organization_canonical, _) =
+        member_organization(deps, &organization, &env.message.sender, role)?;
+
+    let member = env.message.sender.clone();
+    let block_time = env.block.time;
+    let mut organization_env = env;
+    organization_env.message.sender = organization;
+    let response = handle(deps, organization_env, msg.clone())?;
+    append_audit_entry(
+        &mut deps.storage,
+        &organization_canonical,
+        &OrganizationAuditEntry {
+            member,
+            action: OrganizationAction::Handle { msg },
+            block_time,
+        },
+    )?;
+
+    Ok(response)
+}
+
+// Run a receive msg as the organization, paid for by the member who sent the tokens
+fn receive_on_behalf_of<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    from: HumanAddr,
+    amount: Uint128,
+    received_token: &HumanAddr,
+    organization: HumanAddr,
+    msg: ReceiveMsg,
+) -> StdResult<HandleResponse> {
+    let role = match msg {
+        ReceiveMsg::BatchCancel { .. }
+        | ReceiveMsg::Cancel { .. }
+        | ReceiveMsg::CreateSendRequest { .. }
+        | ReceiveMsg::CreateSendRequests { .. } => OrganizationRole::Requester,
+        ReceiveMsg::BatchSendPayment { .. } | ReceiveMsg::SendPayment { .. } => {
+            OrganizationRole::Payer
+        }
+        _ => {
+            return Err(StdError::generic_err(
+                "Action is not available to organizations.",
+            ))
+        }
+    };
+    let (organization_canonical, _) = member_organization(deps, &organization, &from, role)?;
+
+    let response = receive_msg(deps, env, organization, amount, received_token, msg.clone())?;
+    // Payments that await approval go back to the member who paid them if they are rejected
+    let positions = match &msg {
+        ReceiveMsg::BatchSendPayment { positions } => positions.clone(),
+        ReceiveMsg::SendPayment { position } => vec![*position],
+        _ => vec![],
+    };
+    for position in positions {
+        if payment_awaiting_approval(deps, &organization_canonical, position).is_ok() {
+            set_escrowed_payment(
+                &mut deps.storage,
+                &organization_canonical,
+                position,
+                Some(&EscrowedPayment {
+                    payer: from.clone(),
+                    block_time: env.block.time,
+                }),
+            )?;
+        }
+    }
+    append_audit_entry(
+        &mut deps.storage,
+        &organization_canonical,
+        &OrganizationAuditEntry {
+            member: from,
+            action: OrganizationAction::Receive { amount, msg },
+            block_time: env.block.time,
+        },
+    )?;
+
+    Ok(response)
+}
+
+fn organization_at<S: Storage>(storage: &S, address: &CanonicalAddr) -> StdResult<Organization> {
+    organization(storage, address)?.ok_or_else(|| StdError::generic_err("Organization not found."))
+}
+
+// Returns the organization if the member has at least the role in it
+fn member_organization<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    organization: &HumanAddr,
+    member: &HumanAddr,
+    role: OrganizationRole,
+) -> StdResult<(CanonicalAddr, Organization)> {
+    let organization_canonical = deps.api.canonical_address(organization)?;
+    let organization = organization_at(&deps.storage, &organization_canonical)?;
+    organization.verify_role(member, role)?;
+
+    Ok((organization_canonical, organization))
+}
+
+// Nobody holds the key to an organization's address, so refunds owed to it go to its admin
+fn payout_address<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: HumanAddr,
+) -> StdResult<HumanAddr> {
+    match organization(&deps.storage, &deps.api.canonical_address(&address)?)? {
+        Some(organization) => Ok(organization.admin),
+        None => Ok(address),
+    }
+}
+
+fn nominate_new_admin<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    address: HumanAddr,
+) -> StdResult<HandleResponse> {
+    let mut config: Config = TypedStoreMut::attach(&mut deps.storage)
+        .load(CONFIG_KEY)
+        .unwrap();
+    authorize(env.message.sender.clone(), config.admin.clone())?;
+
+    config.new_admin_nomination = Some(address);
+    TypedStoreMut::attach(&mut deps.storage).store(CONFIG_KEY, &config)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+fn pad_response(response: StdResult<HandleResponse>) -> StdResult<HandleResponse> {
+    response.map(|mut response| {
+        response.data = response.data.map(|mut data| {
+            space_pad(BLOCK_SIZE, &mut data.0);
+            data
+        });
+        response
+    })
+}
+
+fn pad_query_result(result: StdResult<Binary>) -> StdResult<Binary> {
+    result.map(|mut data| {
+        space_pad(BLOCK_SIZE, &mut data.0);
+        data
+    })
+}
+
+fn set_address_book_label<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    address: HumanAddr,
+    label: Option<String>,
+) -> StdResult<HandleResponse> {
+    update_address_book_label(
+        &mut deps.storage,
+        &deps.api.canonical_address(&env.message.sender)?,
+        &deps.api.canonical_address(&address)?,
+        label,
+    )?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+// Set the memo on the user's own copy of a Tx, which the counterparty can't see
+fn set_memo<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    position: u32,
+    memo: Option<String>,
+) -> StdResult<HandleResponse> {
+    verify_text_length("Memo", &memo)?;
+    let address = deps.api.canonical_address(&env.message.sender)?;
+    let mut tx = tx_at_position(&mut deps.storage, &address, position)?;
+    tx.memo = memo;
+    update_tx(&mut deps.storage, &address, tx)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+// Take a Vec<u8> and pad it up to a multiple of `block_size`, using spaces at the end.
+fn space_pad(block_size: usize, message: &mut Vec<u8>) -> &mut Vec<u8> {
+    let len = message.len();
+    let surplus = len % block_size;
+    if surplus == 0 {
+        return message;
+    }
+
+    let missing = block_size - surplus;
+    message.reserve(missing);
+    message.extend(std::iter::repeat(b' ').take(missing));
+    message
+}
+
+// Authenticates the owner or a viewer of the Txs and returns the scope the viewer can see
+fn txs_scope<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    key: String,
+    viewer: Option<HumanAddr>,
+    time: Option<u64>,
+) -> StdResult<Option<ViewerScope>> {
+    match viewer {
+        // A viewer authenticates with their own viewing key
+        Some(viewer) => {
+            verify_viewing_key(deps, viewer.clone(), key)?;
+            let owner = deps.api.canonical_address(address)?;
+            // Members see an organization's whole history
+            match organization(&deps.storage, &owner)? {
+                Some(organization) => {
+                    organization.verify_role(&viewer, OrganizationRole::Viewer)?;
+                    Ok(None)
+                }
+                None => Ok(Some(verify_viewer(
+                    deps,
+                    &owner,
+                    &deps.api.canonical_address(&viewer)?,
+                    time,
+                )?)),
+            }
+        }
+        None => {
+            verify_viewing_key(deps, address.clone(), key)?;
+            Ok(None)
+        }
+    }
+}
+
+// A fixed size page is filled up with placeholder Txs and padded to FIXED_SIZE_TX_LENGTH bytes
+// per Tx, so that every page has the same length unless its Txs are unusually large
+fn tx_page<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &CanonicalAddr,
+    page: u32,
+    page_size: u32,
+    fixed_size: bool,
+    scope: Option<ViewerScope>,
+) -> StdResult<Binary> {
+    // Fixed size pages are allocated up front
+    if page_size > MAX_TX_PAGE_SIZE {
+        return Err(StdError::generic_err(format!(
+            "Page size must be at most {}.",
+            MAX_TX_PAGE_SIZE
+        )));
+    }
+    let (mut txs, total) = match scope {
+        Some(scope) => {
+            let filter = tx_filter(deps, address, scope)?;
+            let (mut txs, total) =
+                get_filtered_txs(&deps.api, &deps.storage, address, page, page_size, filter)?;
+            // Memos are private to the owner
+            for tx in txs.iter_mut() {
+                tx.memo = None;
+            }
+            (txs, total)
+        }
+        None => get_txs(&deps.api, &deps.storage, address, page, page_size)?,
+    };
+    if fixed_size {
+        txs.resize(page_size as usize, HumanizedTx::placeholder());
+    }
+
+    let mut result = to_binary(&QueryAnswer::Txs {
+        txs,
+        total: Some(total),
+    })?;
+    if fixed_size {
+        let length = page_size as usize * FIXED_SIZE_TX_LENGTH;
+        if result.len() < length {
+            let missing = length - result.len();
+            result.0.extend(std::iter::repeat_n(b' ', missing));
+        }
+    }
+
+    Ok(result)
+}
+
+// Matches the owner's Txs that fall within a viewer's scope
+fn tx_filter<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    owner: &CanonicalAddr,
+    scope: ViewerScope,
+) -> StdResult<impl Fn(&Tx) -> bool> {
+    let counterparties: Option<Vec<CanonicalAddr>> = match scope.counterparties {
+        Some(counterparties) => Some(
+            counterparties
+                .iter()
+                .map(|address| deps.api.canonical_address(address))
+                .collect::<StdResult<_>>()?,
+        ),
+        None => None,
+    };
+    let owner = owner.clone();
+    let tokens = scope.tokens;
+    let from_time = scope.from_time;
+    let to_time = scope.to_time;
+
+    Ok(move |tx: &Tx| {
+        if let Some(tokens) = &tokens {
+            if !tokens.contains(&tx.token.address) {
+                return false;
+            }
+        }
+        if from_time.is_some_and(|from_time| tx.block_time < from_time) {
+            return false;
+        }
+        if to_time.is_some_and(|to_time| tx.block_time >= to_time) {
+            return false;
+        }
+        if let Some(counterparties) = &counterparties {
+            let counterparty = if tx.from == owner { &tx.to } else { &tx.from };
+            if !counterparties.contains(counterparty) {
+                return false;
+            }
+        }
+        true
+    })
+}
+
+fn verify_referrer<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    creator: &CanonicalAddr,
+    referrer: &Option<HumanAddr>,
+) -> StdResult<()> {
+    if let Some(referrer) = referrer {
+        if &deps.api.canonical_address(referrer)? == creator {
+            return Err(StdError::generic_err("Referrer can not be the creator."));
+        }
+    }
+
+    Ok(())
+}
+
+fn verify_viewing_key<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: HumanAddr,
+    key: String,
+) -> StdResult<()> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+
+    // This is here so that the user can use their viewing key for shade for this
+    snip20::balance_query(
+        &deps.querier,
+        address,
+        key,
+        BLOCK_SIZE,
+        config.shade.contract_hash,
+        config.shade.address,
+    )?;
+
+    Ok(())
+}
+
+// Returns the scope of the viewer's grant if it exists and hasn't expired at the given time.
+// A grant with an expiry can't be used without a time.
+fn verify_viewer<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    owner: &CanonicalAddr,
+    viewer: &CanonicalAddr,
+    time: Option<u64>,
+) -> StdResult<ViewerScope> {
+    let grant = viewer_grant(&deps.storage, owner, viewer)?.ok_or_else(StdError::unauthorized)?;
+    if let Some(expires_at) = grant.expires_at {
+        if time.unwrap_or(u64::MAX) >= expires_at {
+            return Err(StdError::unauthorized());
+        }
+    }
+
+    Ok(grant.scope)
+}
+
+// Split an amount of fees between the treasury recipients by weight,
+// or send it all to the treasury address if there are none
+fn treasury_messages(
+    config: &Config,
+    token: SecretContract,
+    amount: Uint128,
+) -> StdResult<Vec<CosmosMsg>> {
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if config.treasury_recipients.is_empty() {
+        messages.push(snip20::transfer_msg(
+            config.treasury_address.clone(),
+            amount,
+            None,
+            BLOCK_SIZE,
+            token.contract_hash,
+            token.address,
+        )?);
+        return Ok(messages);
+    }
+
+    let total_weight: u128 = config
+        .treasury_recipients
+        .iter()
+        .map(|recipient| recipient.weight as u128)
+        .sum();
+    // The last recipient also gets whatever is left over from rounding down
+    let mut remaining_amount = amount;
+    for (index, recipient) in config.treasury_recipients.iter().enumerate() {
+        let mut share = amount.multiply_ratio(recipient.weight as u128, total_weight);
+        if index == config.treasury_recipients.len() - 1 {
+            share = remaining_amount;
+        }
+        remaining_amount = (remaining_amount - share)?;
+        messages.push(snip20::transfer_msg(
+            recipient.address.clone(),
+            share,
+            None,
+            BLOCK_SIZE,
+            token.contract_hash.clone(),
+            token.address.clone(),
+        )?);
+    }
+
+    Ok(messages)
+}
+
+fn update_acceptance_policy<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    policy: AcceptancePolicy,
+) -> StdResult<HandleResponse> {
+    set_acceptance_policy(
+        &mut deps.storage,
+        &deps.api.canonical_address(&env.message.sender)?,
+        &policy,
+    )?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+// New limits replace any pending ones and take effect after the delay
+fn update_spending_limits<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    limits: SpendingLimits,
+) -> StdResult<HandleResponse> {
+    let address = deps.api.canonical_address(&env.message.sender)?;
+    let mut settings = spending_limit_settings(&deps.storage, &address)?.at(env.block.time);
+    settings.pending = Some(PendingSpendingLimits {
+        limits,
+        effective_at: env.block.time + SPENDING_LIMITS_DELAY,
+    });
+    set_spending_limit_settings(&mut deps.storage, &address, &settings)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+fn update_constant_shape<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    constant_shape: bool,
+) -> StdResult<HandleResponse> {
+    let mut config: Config = TypedStoreMut::attach(&mut deps.storage)
+        .load(CONFIG_KEY)
+        .unwrap();
+    authorize(env.message.sender.clone(), config.admin.clone())?;
+
+    config.constant_shape = constant_shape;
+    TypedStoreMut::attach(&mut deps.storage).store(CONFIG_KEY, &config)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+fn update_fee<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    fee: Uint128,
+) -> StdResult<HandleResponse> {
+    let mut config: Config = TypedStoreMut::attach(&mut deps.storage)
+        .load(CONFIG_KEY)
+        .unwrap();
+    authorize(env.message.sender.clone(), config.admin.clone())?;
+
+    config.fee = fee;
+    TypedStoreMut::attach(&mut deps.storage).store(CONFIG_KEY, &config)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+fn update_fee_discount_key<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    token: HumanAddr,
+    key: String,
+) -> StdResult<HandleResponse> {
+    set_fee_discount_key(
+        &mut deps.storage,
+        &deps.api.canonical_address(&env.message.sender)?,
+        &token,
+        &key,
+    )?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+fn update_fee_discounts<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    fee_discounts: Vec<FeeDiscount>,
+) -> StdResult<HandleResponse> {
+    let mut config: Config = TypedStoreMut::attach(&mut deps.storage)
+        .load(CONFIG_KEY)
+        .unwrap();
+    authorize(env.message.sender.clone(), config.admin.clone())?;
+    if fee_discounts
+        .iter()
+        .any(|fee_discount| fee_discount.discount_bps > 10_000)
+    {
+        return Err(StdError::generic_err(
+            "Discount can not be more than 10000 basis points.",
+        ));
+    }
+
+    config.fee_discounts = fee_discounts;
+    TypedStoreMut::attach(&mut deps.storage).store(CONFIG_KEY, &config)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+fn update_fee_tokens<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    fee_tokens: Vec<FeeToken>,
+) -> StdResult<HandleResponse> {
+    let mut config: Config = TypedStoreMut::attach(&mut deps.storage)
+        .load(CONFIG_KEY)
+        .unwrap();
+    authorize(env.message.sender.clone(), config.admin.clone())?;
+    for (index, fee_token) in fee_tokens.iter().enumerate() {
+        if fee_token.token.address == config.sscrt.address
+            || fee_tokens[..index]
+                .iter()
+                .any(|other| other.token.address == fee_token.token.address)
+        {
+            return Err(StdError::generic_err(
+                "Fee tokens must be different from SSCRT and each other.",
+            ));
+        }
+    }
+
+    // Fees can only be received in tokens that the contract has registered with
+    let mut messages: Vec<CosmosMsg> = vec![];
+    for fee_token in &fee_tokens {
+        let register_token_msg: Option<CosmosMsg> = register_token(
+            &mut deps.storage,
+            env.contract_code_hash.clone(),
+            fee_token.token.clone(),
+        )?;
+        if register_token_msg.is_some() {
+            messages.push(register_token_msg.unwrap())
+        }
+    }
+    config.fee_tokens = fee_tokens;
+    TypedStoreMut::attach(&mut deps.storage).store(CONFIG_KEY, &config)?;
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: None,
+    })
+}
+
+fn update_fee_exemption<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    address: HumanAddr,
+    exempt: bool,
+) -> StdResult<HandleResponse> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    authorize(env.message.sender.clone(), config.admin)?;
+
+    set_fee_exempt(
+        &mut deps.storage,
+        &deps.api.canonical_address(&address)?,
+        exempt,
+    )?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+fn update_referral_share<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    referral_share_bps: u16,
+) -> StdResult<HandleResponse> {
+    let mut config: Config = TypedStoreMut::attach(&mut deps.storage)
+        .load(CONFIG_KEY)
+        .unwrap();
+    authorize(env.message.sender.clone(), config.admin.clone())?;
+    if referral_share_bps > 10_000 {
+        return Err(StdError::generic_err(
+            "Referral share can not be more than 10000 basis points.",
+        ));
+    }
+
+    config.referral_share_bps = referral_share_bps;
+    TypedStoreMut::attach(&mut deps.storage).store(CONFIG_KEY, &config)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+fn update_treasury_address<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    address: HumanAddr,
+) -> StdResult<HandleResponse> {
+    let mut config: Config = TypedStoreMut::attach(&mut deps.storage)
+        .load(CONFIG_KEY)
+        .unwrap();
+    authorize(env.message.sender.clone(), config.admin.clone())?;
+
+    config.treasury_address = address;
+    TypedStoreMut::attach(&mut deps.storage).store(CONFIG_KEY, &config)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+fn update_treasury_recipients<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    treasury_recipients: Vec<TreasuryRecipient>,
+) -> StdResult<HandleResponse> {
+    let mut config: Config = TypedStoreMut::attach(&mut deps.storage)
+        .load(CONFIG_KEY)
+        .unwrap();
+    authorize(env.message.sender.clone(), config.admin.clone())?;
+    if treasury_recipients
+        .iter()
+        .any(|treasury_recipient| treasury_recipient.weight == 0)
+    {
+        return Err(StdError::generic_err(
+            "Treasury recipient weight must be greater than zero.",
+        ));
+    }
+
+    config.treasury_recipients = treasury_recipients;
+    TypedStoreMut::attach(&mut deps.storage).store(CONFIG_KEY, &config)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+fn withdraw_fees<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    token: HumanAddr,
+    amount: Uint128,
+    recipient: Option<HumanAddr>,
+) -> StdResult<HandleResponse> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    authorize(env.message.sender.clone(), config.admin.clone())?;
+
+    let contract_hash = RegisteredTokensReadonlyStorage::from_storage(&deps.storage)
+        .get_contract_hash(token.clone())
+        .ok_or_else(|| StdError::generic_err("Token is not registered."))?;
+    let stats = fee_stats(&deps.storage, &token)?;
+    if amount > (stats.accrued - stats.withdrawn)? {
+        return Err(StdError::generic_err(
+            "Amount is more than the withdrawable fees.",
+        ));
+    }
+    record_fees(&mut deps.storage, &token, env.block.time, |stats| {
+        stats.withdrawn = stats.withdrawn + amount;
+        Ok(())
+    })?;
+
+    let token = SecretContract {
+        address: token,
+        contract_hash,
+    };
+    let messages: Vec<CosmosMsg> = match recipient {
+        Some(recipient) => vec![snip20::transfer_msg(
+            recipient,
+            amount,
+            None,
+            BLOCK_SIZE,
+            token.contract_hash,
+            token.address,
+        )?],
+        None => treasury_messages(&config, token, amount)?,
+    };
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::address_book::AddressBookEntry;
+    use crate::fees::SECONDS_PER_DAY;
+    use crate::spending_limits::{SpendingLimitSettings, TokenSpendingLimit};
+    use crate::state::RegisteredTokensReadonlyStorage;
+    use crate::stats::{Stats, TokenVolume};
+    use crate::transaction_history::HashTimelock;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage};
+
+    // === HELPERS ===
+    // The admin asks to acknowledge receive requests and the user requests a payment from them
+    fn create_acknowledged_request_helper(deps: &mut Extern<MockStorage, MockApi, MockQuerier>) {
+        let handle_msg = HandleMsg::SetAcceptancePolicy {
+            contacts_only: false,
+            minimum_amount: None,
+            allowed_tokens: None,
+            acknowledge_receive_requests: Some(true),
+        };
+        handle(
+            deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            handle_msg,
+        )
+        .unwrap();
+        let receive_msg = ReceiveMsg::CreateReceiveRequest {
+            address: mock_contract_initiator_address(),
+            send_amount: Uint128(1_000),
+            description: Some("Invoice".to_string()),
+            token: mock_silk(),
+            referrer: None,
+            cancellation_policy: None,
+            cancellation_penalty: None,
+            attachment_hash: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: mock_fee(),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        handle(deps, mock_env(mock_sscrt().address, &[]), handle_msg).unwrap();
+    }
+
+    // The organization's payer pays the admin's receive request of 1_000, which needs two approvals
+    fn create_approval_helper(deps: &mut Extern<MockStorage, MockApi, MockQuerier>) -> HumanAddr {
+        let organization = create_organization_helper(deps);
+        let handle_msg = HandleMsg::SetApprovalPolicies {
+            organization: organization.clone(),
+            approval_policies: vec![ApprovalPolicy {
+                token: mock_silk().address,
+                threshold: Uint128(500),
+                required_approvals: 2,
+            }],
+        };
+        handle(deps, mock_env(mock_user_address(), &[]), handle_msg).unwrap();
+        let handle_msg = HandleMsg::SetOrganizationMember {
+            organization: organization.clone(),
+            address: HumanAddr::from("bob"),
+            role: Some(OrganizationRole::Approver),
+        };
+        handle(deps, mock_env(mock_user_address(), &[]), handle_msg).unwrap();
+        let receive_msg = ReceiveMsg::CreateReceiveRequest {
+            address: organization.clone(),
+            send_amount: Uint128(1_000),
+            description: Some("Invoice".to_string()),
+            token: mock_silk(),
+            referrer: None,
+            cancellation_policy: None,
+            cancellation_penalty: None,
+            attachment_hash: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: mock_fee(),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        handle(deps, mock_env(mock_sscrt().address, &[]), handle_msg).unwrap();
+        let payment_msg = HandleMsg::Receive {
+            sender: HumanAddr::from("alice"),
+            from: HumanAddr::from("alice"),
+            amount: Uint128(1_000),
+            msg: to_binary(&ReceiveMsg::OnBehalfOf {
+                organization: organization.clone(),
+                msg: Box::new(ReceiveMsg::SendPayment { position: 0 }),
+            })
+            .unwrap(),
+        };
+        handle(deps, mock_env(mock_silk().address, &[]), payment_msg).unwrap();
+        organization
+    }
+
+    // Handle a message and count its storage reads, writes and bytes written
+    fn counted_handle_helper(
+        deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+        env: Env,
+        msg: HandleMsg,
+    ) -> (StdResult<HandleResponse>, u32, u32, usize) {
+        let mut counted_deps = Extern {
+            storage: CountingStorage::new(&mut deps.storage),
+            api: deps.api,
+            querier: BorrowedQuerier(&deps.querier),
+        };
+        let handle_result = handle(&mut counted_deps, env, msg);
+        (
+            handle_result,
+            counted_deps.storage.reads(),
+            counted_deps.storage.writes(),
+            counted_deps.storage.written_bytes(),
+        )
+    }
+
+    fn create_claim_helper(deps: &mut Extern<MockStorage, MockApi, MockQuerier>, funded: bool) {
+        let code_hash = Binary(hash_code("open-sesame"));
+        let receive_msg = ReceiveMsg::CreateClaim {
+            code_hash: code_hash.clone(),
+            send_amount: Uint128(1_000),
+            description: Some("Welcome".to_string()),
+            token: mock_silk(),
+            expires_at: mock_expires_at(),
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: mock_fee(),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        handle(deps, mock_env(mock_sscrt().address, &[]), handle_msg).unwrap();
+        if funded {
+            let handle_msg = HandleMsg::Receive {
+                sender: mock_user_address(),
+                from: mock_user_address(),
+                amount: Uint128(1_000),
+                msg: to_binary(&ReceiveMsg::FundClaim { code_hash }).unwrap(),
+            };
+            handle(deps, mock_env(mock_silk().address, &[]), handle_msg).unwrap();
+        }
+    }
+
+    // Create, confirm and pay a hash timelocked send request from the user to the admin
+    fn create_htlc_helper(deps: &mut Extern<MockStorage, MockApi, MockQuerier>) -> HandleResponse {
+        let receive_msg = ReceiveMsg::CreateSendRequest {
+            address: mock_contract_initiator_address(),
+            send_amount: Uint128(1_000),
+            description: Some("Swap".to_string()),
+            token: mock_silk(),
+            skip_confirmation_if_verified: None,
+            referrer: None,
+            cancellation_policy: None,
+            cancellation_penalty: None,
+            hash_timelock: Some(HashTimelock {
+                hashlock: Binary(Sha256::digest(b"swap-secret").to_vec()),
+                timelock: mock_expires_at(),
+            }),
+            milestones: None,
+            attachment_hash: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: mock_fee(),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        handle(deps, mock_env(mock_sscrt().address, &[]), handle_msg).unwrap();
+        let confirm_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(0),
+            msg: to_binary(&ReceiveMsg::ConfirmAddress { position: 0 }).unwrap(),
+        };
+        handle(deps, mock_env(mock_sscrt().address, &[]), confirm_msg).unwrap();
+        let payment_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(1_000),
+            msg: to_binary(&ReceiveMsg::SendPayment { position: 0 }).unwrap(),
+        };
+        handle(deps, mock_env(mock_silk().address, &[]), payment_msg).unwrap()
+    }
+
+    // Create, confirm and pay a send request from the user to the admin with two milestones
+    fn create_milestones_helper(
+        deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+        cancellation_policy: CancellationPolicy,
+    ) {
+        let receive_msg = ReceiveMsg::CreateSendRequest {
+            address: mock_contract_initiator_address(),
+            send_amount: Uint128(1_000),
+            description: Some("Website".to_string()),
+            token: mock_silk(),
+            skip_confirmation_if_verified: None,
+            referrer: None,
+            cancellation_policy: Some(cancellation_policy),
+            cancellation_penalty: None,
+            hash_timelock: None,
+            milestones: Some(vec![
+                Milestone {
+                    label: "Design".to_string(),
+                    amount: Uint128(400),
+                    deadline: None,
+                    status: MilestoneStatus::Pending,
+                },
+                Milestone {
+                    label: "Build".to_string(),
+                    amount: Uint128(600),
+                    deadline: Some(mock_expires_at()),
+                    status: MilestoneStatus::Pending,
+                },
+            ]),
+            attachment_hash: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: mock_fee(),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        handle(deps, mock_env(mock_sscrt().address, &[]), handle_msg).unwrap();
+        let confirm_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(0),
+            msg: to_binary(&ReceiveMsg::ConfirmAddress { position: 0 }).unwrap(),
+        };
+        handle(deps, mock_env(mock_sscrt().address, &[]), confirm_msg).unwrap();
+        let payment_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(1_000),
+            msg: to_binary(&ReceiveMsg::SendPayment { position: 0 }).unwrap(),
+        };
+        handle(deps, mock_env(mock_silk().address, &[]), payment_msg).unwrap();
+    }
+
+    // The user creates an organization and adds alice to it as a payer
+    fn create_organization_helper(
+        deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+    ) -> HumanAddr {
+        let handle_msg = HandleMsg::CreateOrganization {
+            name: "Acme".to_string(),
+        };
+        let handle_result = handle(deps, mock_env(mock_user_address(), &[]), handle_msg).unwrap();
+        let organization = match from_binary(&handle_result.data.unwrap()).unwrap() {
+            HandleAnswer::CreateOrganization { address } => address,
+            _ => panic!("Unexpected handle answer"),
+        };
+        let handle_msg = HandleMsg::SetOrganizationMember {
+            organization: organization.clone(),
+            address: HumanAddr::from("alice"),
+            role: Some(OrganizationRole::Payer),
+        };
+        handle(deps, mock_env(mock_user_address(), &[]), handle_msg).unwrap();
+        organization
+    }
+
+    fn create_payment_link_helper(
+        deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+        max_uses: u32,
+    ) {
+        let receive_msg = ReceiveMsg::CreatePaymentLink {
+            code_hash: Binary(hash_code("open-sesame")),
+            send_amount: Uint128(1_000),
+            description: Some("Donation".to_string()),
+            token: mock_silk(),
+            max_uses: Some(max_uses),
+            referrer: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(mock_fee().u128() * max_uses as u128),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        handle(deps, mock_env(mock_sscrt().address, &[]), handle_msg).unwrap();
+    }
+
+    fn create_send_requests_helper(
+        deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+        send_amounts: Vec<Uint128>,
+    ) {
+        for send_amount in send_amounts {
+            let receive_msg = ReceiveMsg::CreateSendRequest {
+                address: mock_contract_initiator_address(),
+                send_amount: send_amount,
+                description: Some("Payroll".to_string()),
+                token: mock_silk(),
+                skip_confirmation_if_verified: None,
+                referrer: None,
+                cancellation_policy: None,
+                cancellation_penalty: None,
+                hash_timelock: None,
+                milestones: None,
+                attachment_hash: None,
+            };
+            let handle_msg = HandleMsg::Receive {
+                sender: mock_user_address(),
+                from: mock_user_address(),
+                amount: mock_fee(),
+                msg: to_binary(&receive_msg).unwrap(),
+            };
+            handle(deps, mock_env(mock_sscrt().address, &[]), handle_msg).unwrap();
+        }
+    }
+
+    fn init_helper() -> (
+        StdResult<InitResponse>,
+        Extern<MockStorage, MockApi, MockQuerier>,
+    ) {
+        let env = mock_env(mock_contract_initiator_address(), &[]);
+        let mut deps = mock_dependencies(20, &[]);
+        let msg = InitMsg {
+            fee: mock_fee(),
+            shade: mock_shade(),
+            sscrt: mock_sscrt(),
+            treasury_address: mock_treasury_address(),
+        };
+        (init(&mut deps, env, msg), deps)
+    }
+
+    fn mock_expires_at() -> u64 {
+        mock_env(mock_user_address(), &[]).block.time + 100
+    }
+
+    fn mock_fee() -> Uint128 {
+        Uint128(1_000_000)
+    }
+
+    fn mock_silk() -> SecretContract {
+        SecretContract {
+            address: HumanAddr::from("mock-silk-address"),
+            contract_hash: "mock-silk-contract-hash".to_string(),
+        }
+    }
+
+    fn mock_treasury_address() -> HumanAddr {
+        HumanAddr::from("mock-treasury-address")
+    }
+
+    fn mock_contract() -> SecretContract {
+        let env = mock_env(mock_user_address(), &[]);
+        SecretContract {
+            address: env.contract.address,
+            contract_hash: env.contract_code_hash,
+        }
+    }
+
+    fn mock_contract_initiator_address() -> HumanAddr {
+        HumanAddr::from("admin")
+    }
+
+    fn mock_sscrt() -> SecretContract {
+        SecretContract {
+            address: HumanAddr::from("mock-sscrt-address"),
+            contract_hash: "mock-sscrt-contract-hash".to_string(),
+        }
+    }
+
     fn mock_shade() -> SecretContract {
         SecretContract {
             address: HumanAddr::from("mock-shade-address"),
@@ -585,131 +4196,4516 @@ mod tests {
         }
     }
 
-    fn mock_user_address() -> HumanAddr {
-        HumanAddr::from("gary")
+    fn mock_user_address() -> HumanAddr {
+        HumanAddr::from("gary")
+    }
+
+    // === INIT TEST ===
+    #[test]
+    fn test_init() {
+        // * it registers receive for sscrt and shade
+        let (init_result, deps) = init_helper();
+        assert_eq!(
+            init_result.unwrap().messages,
+            vec![
+                snip20::register_receive_msg(
+                    mock_env(mock_contract_initiator_address(), &[]).contract_code_hash,
+                    None,
+                    BLOCK_SIZE,
+                    mock_sscrt().contract_hash,
+                    mock_sscrt().address,
+                )
+                .unwrap(),
+                snip20::register_receive_msg(
+                    mock_env(mock_contract_initiator_address(), &[]).contract_code_hash,
+                    None,
+                    BLOCK_SIZE,
+                    mock_shade().contract_hash,
+                    mock_shade().address,
+                )
+                .unwrap(),
+            ],
+        );
+
+        // * it sets the correct Config
+        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        assert_eq!(
+            config,
+            Config {
+                admin: mock_contract_initiator_address(),
+                constant_shape: false,
+                fee: mock_fee(),
+                fee_discounts: vec![],
+                fee_tokens: vec![],
+                new_admin_nomination: None,
+                referral_share_bps: 0,
+                shade: mock_shade(),
+                sscrt: mock_sscrt(),
+                treasury_address: mock_treasury_address(),
+                treasury_recipients: vec![],
+            }
+        );
+
+        // * it stores the token => contract_hash in storage
+        let mut registered_tokens_storage =
+            RegisteredTokensReadonlyStorage::from_storage(&deps.storage);
+        assert_eq!(
+            registered_tokens_storage
+                .get_contract_hash(mock_sscrt().address)
+                .unwrap(),
+            mock_sscrt().contract_hash,
+        );
+        assert_eq!(
+            registered_tokens_storage
+                .get_contract_hash(mock_shade().address)
+                .unwrap(),
+            mock_shade().contract_hash,
+        )
+    }
+
+    // === QUERY TESTS ===
+    #[test]
+    fn test_query_config() {
+        let (_init_result, deps) = init_helper();
+        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        let query_result = query(&deps, QueryMsg::Config {}).unwrap();
+        let query_answer_config: Config = from_binary(&query_result).unwrap();
+        assert_eq!(query_answer_config, config);
+        // * it pads the response to a multiple of the block size
+        assert_eq!(query_result.len() % BLOCK_SIZE, 0);
+    }
+
+    #[test]
+    fn test_query_stats() {
+        let (_init_result, mut deps) = init_helper();
+        handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            HandleMsg::UpdateFeeTokens {
+                fee_tokens: vec![FeeToken {
+                    token: mock_silk(),
+                    fee: Uint128(500),
+                }],
+            },
+        )
+        .unwrap();
+        create_send_requests_helper(&mut deps, vec![Uint128(123_456), Uint128(1)]);
+        let confirm_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(0),
+            msg: to_binary(&ReceiveMsg::BatchConfirmAddress {
+                positions: vec![0, 1],
+            })
+            .unwrap(),
+        };
+        handle(&mut deps, mock_env(mock_sscrt().address, &[]), confirm_msg).unwrap();
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(123_456),
+            msg: to_binary(&ReceiveMsg::SendPayment { position: 0 }).unwrap(),
+        };
+        handle(&mut deps, mock_env(mock_silk().address, &[]), handle_msg).unwrap();
+
+        // * it returns the stats rounded down to two significant digits
+        let query_result = query(&deps, QueryMsg::Stats {}).unwrap();
+        let query_answer: QueryAnswer = from_binary(&query_result).unwrap();
+        let stats = match query_answer {
+            QueryAnswer::Stats { stats } => stats,
+            _ => panic!("Unexpected query answer"),
+        };
+        assert_eq!(
+            stats,
+            Stats {
+                send_requests_created: 2,
+                receive_requests_created: 0,
+                confirmed: 2,
+                cancelled: 0,
+                finalized: 1,
+                unique_participants: 2,
+                volumes: vec![TokenVolume {
+                    token: mock_silk().address,
+                    volume: Uint128(120_000),
+                }],
+            }
+        );
+
+        // * it keeps the exact numbers in a bucket for the day
+        let tokens = vec![mock_sscrt().address, mock_silk().address];
+        let (daily_stats, total) = get_daily_stats(&deps.storage, &tokens, 0, 10).unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(
+            daily_stats[0].day,
+            mock_env(mock_user_address(), &[]).block.time / SECONDS_PER_DAY
+        );
+        assert_eq!(
+            daily_stats[0].stats.volumes,
+            vec![TokenVolume {
+                token: mock_silk().address,
+                volume: Uint128(123_456),
+            }]
+        );
+
+        // when a payment is in a token that is not in the config
+        // * it does not keep a volume for it
+        let throwaway_token = SecretContract {
+            address: HumanAddr::from("throwaway-token"),
+            contract_hash: "throwaway-token-hash".to_string(),
+        };
+        let receive_msg = ReceiveMsg::CreateSendRequest {
+            address: mock_contract_initiator_address(),
+            send_amount: Uint128(1_000),
+            description: None,
+            token: throwaway_token.clone(),
+            skip_confirmation_if_verified: None,
+            referrer: None,
+            cancellation_policy: None,
+            cancellation_penalty: None,
+            hash_timelock: None,
+            milestones: None,
+            attachment_hash: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: mock_fee(),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg).unwrap();
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(0),
+            msg: to_binary(&ReceiveMsg::ConfirmAddress { position: 2 }).unwrap(),
+        };
+        handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg).unwrap();
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(1_000),
+            msg: to_binary(&ReceiveMsg::SendPayment { position: 2 }).unwrap(),
+        };
+        handle(
+            &mut deps,
+            mock_env(throwaway_token.address.clone(), &[]),
+            handle_msg,
+        )
+        .unwrap();
+        let stats = crate::stats::stats(&deps.storage, &[throwaway_token.address]).unwrap();
+        assert_eq!(stats.finalized, 2);
+        assert_eq!(stats.volumes, vec![]);
+    }
+
+    #[test]
+    fn test_query_txs() {
+        let (_init_result, mut deps) = init_helper();
+        create_send_requests_helper(&mut deps, vec![Uint128(1_000), Uint128(2_000)]);
+        let user_canonical = deps.api.canonical_address(&mock_user_address()).unwrap();
+
+        // when the page size is too large
+        // * it raises an error
+        let query_result = tx_page(&deps, &user_canonical, 0, MAX_TX_PAGE_SIZE + 1, true, None);
+        assert_eq!(
+            query_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Page size must be at most 50.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when fixed size is not requested
+        // * pages with fewer Txs are shorter
+        let full_page = tx_page(&deps, &user_canonical, 0, 2, false, None).unwrap();
+        let partial_page = tx_page(&deps, &user_canonical, 1, 1, false, None).unwrap();
+        let empty_page = tx_page(&deps, &user_canonical, 1, 2, false, None).unwrap();
+        assert!(full_page.len() > partial_page.len());
+        assert!(partial_page.len() > empty_page.len());
+
+        // when fixed size is requested
+        let full_page = tx_page(&deps, &user_canonical, 0, 2, true, None).unwrap();
+        let empty_page = tx_page(&deps, &user_canonical, 1, 2, true, None).unwrap();
+        // * every page has the same length
+        assert_eq!(full_page.len(), empty_page.len());
+        assert_eq!(full_page.len(), 2 * FIXED_SIZE_TX_LENGTH);
+        // * it fills the page up with placeholders
+        match from_binary(&empty_page).unwrap() {
+            QueryAnswer::Txs { txs, total } => {
+                assert_eq!(txs.len(), 2);
+                assert_eq!(txs[0].from, HumanAddr::default());
+                assert_eq!(total, Some(2));
+            }
+            _ => panic!("Unexpected query answer"),
+        }
+
+        // when a viewer's scope is given
+        // * it only counts and returns the Txs within the scope
+        let scope = ViewerScope {
+            tokens: Some(vec![mock_silk().address]),
+            ..ViewerScope::default()
+        };
+        let page = tx_page(&deps, &user_canonical, 0, 10, false, Some(scope)).unwrap();
+        match from_binary(&page).unwrap() {
+            QueryAnswer::Txs { txs, total } => {
+                assert_eq!(txs.len(), 2);
+                assert_eq!(total, Some(2));
+            }
+            _ => panic!("Unexpected query answer"),
+        }
+        let scope = ViewerScope {
+            to_time: Some(0),
+            ..ViewerScope::default()
+        };
+        let page = tx_page(&deps, &user_canonical, 0, 10, false, Some(scope)).unwrap();
+        match from_binary(&page).unwrap() {
+            QueryAnswer::Txs { txs, total } => {
+                assert_eq!(txs.len(), 0);
+                assert_eq!(total, Some(0));
+            }
+            _ => panic!("Unexpected query answer"),
+        }
+    }
+
+    // === HANDLE TESTS ===
+    #[test]
+    fn test_accept_new_admin_nomination() {
+        let (_init_result, mut deps) = init_helper();
+        let env = mock_env(mock_user_address(), &[]);
+
+        // when a new admin nomination does not exist
+        // * it raises an unauthorized error
+        let handle_msg = HandleMsg::AcceptNewAdminNomination {};
+        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // when a new admin nomination exists
+        let new_admin_nomination_msg = HandleMsg::NominateNewAdmin {
+            address: mock_user_address(),
+        };
+        let env = mock_env(mock_contract_initiator_address(), &[]);
+        handle(&mut deps, env.clone(), new_admin_nomination_msg).unwrap();
+
+        // = when accepting of new admin nomination is called by the wrong person
+        // = * it raises an error
+        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // = when accepting of new admin nomination is called by the nominated person
+        let env = mock_env(mock_user_address(), &[]);
+        handle(&mut deps, env, handle_msg).unwrap();
+        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        // = * it updates the admin
+        assert_eq!(config.admin, mock_user_address());
+        // = * it sets the new admin nomination to None
+        assert_eq!(config.new_admin_nomination, None);
+    }
+
+    #[test]
+    fn test_acknowledge_request() {
+        let (_init_result, mut deps) = init_helper();
+        create_acknowledged_request_helper(&mut deps);
+        let admin_canonical = deps
+            .api
+            .canonical_address(&mock_contract_initiator_address())
+            .unwrap();
+        let payment_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(1_000),
+            msg: to_binary(&ReceiveMsg::SendPayment { position: 0 }).unwrap(),
+        };
+
+        // when the sender asks to acknowledge receive requests
+        // * it creates the txs pending acknowledgement
+        let from_tx = tx_at_position(&mut deps.storage, &admin_canonical, 0).unwrap();
+        assert_eq!(from_tx.status, 7);
+        // * it can not be paid before it is acknowledged
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_silk().address, &[]),
+            payment_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Tx status at that position is incorrect.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when user is the creator of the receive request
+        // * it raises an unauthorized error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            HandleMsg::AcknowledgeRequest { position: 0 },
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // when user is the sender of the receive request
+        handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            HandleMsg::AcknowledgeRequest { position: 0 },
+        )
+        .unwrap();
+        // * it updates the txs to pending payment
+        let from_tx = tx_at_position(&mut deps.storage, &admin_canonical, 0).unwrap();
+        let to_tx = tx_at_position(
+            &mut deps.storage,
+            &from_tx.to,
+            from_tx.other_storage_position,
+        )
+        .unwrap();
+        assert_eq!(from_tx.status, 1);
+        assert_eq!(to_tx.status, 1);
+        // * it can be paid
+        handle(&mut deps, mock_env(mock_silk().address, &[]), payment_msg).unwrap();
+
+        // when the Tx has already been acknowledged
+        // * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            HandleMsg::AcknowledgeRequest { position: 0 },
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Tx status at that position is incorrect.".to_string(),
+                backtrace: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_allowance_payments() {
+        let (_init_result, mut deps) = init_helper();
+        let env = mock_env(mock_user_address(), &[]);
+        let user_canonical = deps.api.canonical_address(&mock_user_address()).unwrap();
+        let create_msg = HandleMsg::CreateSendRequest {
+            address: mock_contract_initiator_address(),
+            send_amount: Uint128(555),
+            description: None,
+            token: mock_silk(),
+            skip_confirmation_if_verified: None,
+            referrer: None,
+            cancellation_policy: None,
+            cancellation_penalty: None,
+            hash_timelock: None,
+            milestones: None,
+            attachment_hash: None,
+            fee_token: None,
+        };
+
+        // when the user creates a send request
+        let handle_result = handle(&mut deps, env.clone(), create_msg.clone());
+        // * it pulls the fee from the user with transfer_from
+        assert_eq!(
+            handle_result.unwrap().messages[0],
+            snip20::transfer_from_msg(
+                mock_user_address(),
+                env.contract.address.clone(),
+                mock_fee(),
+                None,
+                BLOCK_SIZE,
+                mock_sscrt().contract_hash,
+                mock_sscrt().address,
+            )
+            .unwrap()
+        );
+        // * it stores the Tx
+        let tx = tx_at_position(&mut deps.storage, &user_canonical, 0).unwrap();
+        assert_eq!(tx.status, 0);
+        assert_eq!(tx.fee, mock_fee());
+
+        // when the receiver confirms their address
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            HandleMsg::ConfirmAddress { position: 0 },
+        );
+        // * it pulls nothing with transfer_from
+        assert_eq!(handle_result.unwrap().messages, vec![]);
+        let tx = tx_at_position(&mut deps.storage, &user_canonical, 0).unwrap();
+        assert_eq!(tx.status, 1);
+
+        // when the user sends a payment for a Tx that does not exist
+        let handle_result = handle(
+            &mut deps,
+            env.clone(),
+            HandleMsg::SendPayment { position: 1 },
+        );
+        // * it raises an error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "AppendStorage access out of bounds".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when the user sends the payment
+        let handle_result = handle(
+            &mut deps,
+            env.clone(),
+            HandleMsg::SendPayment { position: 0 },
+        );
+        let messages = handle_result.unwrap().messages;
+        // * it pulls the payment with transfer_from
+        assert_eq!(
+            messages[0],
+            snip20::transfer_from_msg(
+                mock_user_address(),
+                env.contract.address.clone(),
+                Uint128(555),
+                None,
+                BLOCK_SIZE,
+                mock_silk().contract_hash,
+                mock_silk().address,
+            )
+            .unwrap()
+        );
+        // * it sends the payment to the receiver
+        assert_eq!(
+            messages[messages.len() - 1],
+            snip20::transfer_msg(
+                mock_contract_initiator_address(),
+                Uint128(555),
+                None,
+                BLOCK_SIZE,
+                mock_silk().contract_hash,
+                mock_silk().address,
+            )
+            .unwrap()
+        );
+        let tx = tx_at_position(&mut deps.storage, &user_canonical, 0).unwrap();
+        assert_eq!(tx.status, 3);
+
+        // when the user cancels a send request
+        handle(&mut deps, env.clone(), create_msg).unwrap();
+        let handle_result = handle(
+            &mut deps,
+            env.clone(),
+            HandleMsg::Cancel {
+                position: 1,
+                reason: None,
+            },
+        );
+        // * it cancels the Tx
+        handle_result.unwrap();
+        let tx = tx_at_position(&mut deps.storage, &user_canonical, 1).unwrap();
+        assert_eq!(tx.status, 2);
+
+        // when the action can't be paid with an allowance
+        let handle_result = allowance_payment(
+            &mut deps,
+            env,
+            ReceiveMsg::PayLink {
+                code: "open-sesame".to_string(),
+            },
+            None,
+        );
+        // * it raises an error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Action is not available with an allowance.".to_string(),
+                backtrace: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_approve_payment() {
+        let (_init_result, mut deps) = init_helper();
+        let organization = create_approval_helper(&mut deps);
+        let organization_canonical = deps.api.canonical_address(&organization).unwrap();
+        let approve_msg = HandleMsg::ApprovePayment {
+            organization: organization.clone(),
+            position: 0,
+        };
+
+        // when a payment is above the threshold
+        // * it is escrowed until it is approved
+        let tx = tx_at_position(&mut deps.storage, &organization_canonical, 0).unwrap();
+        assert_eq!(tx.status, 6);
+
+        // when the approver is only a payer
+        let handle_result = handle(
+            &mut deps,
+            mock_env(HumanAddr::from("alice"), &[]),
+            approve_msg.clone(),
+        );
+        // * it raises an unauthorized error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // when the first approver approves
+        let env = mock_env(mock_user_address(), &[]);
+        let handle_result = handle(&mut deps, env.clone(), approve_msg.clone());
+        // * it records the approval without releasing the payment
+        assert_eq!(handle_result.unwrap().messages, vec![]);
+        assert_eq!(
+            payment_approvals(&deps.storage, &organization_canonical, 0).unwrap(),
+            vec![PaymentApproval {
+                approver: mock_user_address(),
+                block_time: env.block.time,
+            }]
+        );
+
+        // when the same approver approves again
+        let handle_result = handle(&mut deps, env, approve_msg.clone());
+        // * it raises an error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Payment already approved.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when the second approver approves
+        let handle_result = handle(
+            &mut deps,
+            mock_env(HumanAddr::from("bob"), &[]),
+            approve_msg.clone(),
+        );
+        // * it releases the payment to the receiver
+        assert_eq!(
+            handle_result.unwrap().messages.last().unwrap(),
+            &snip20::transfer_msg(
+                mock_contract_initiator_address(),
+                Uint128(1_000),
+                None,
+                BLOCK_SIZE,
+                mock_silk().contract_hash,
+                mock_silk().address,
+            )
+            .unwrap()
+        );
+        let tx = tx_at_position(&mut deps.storage, &organization_canonical, 0).unwrap();
+        assert_eq!(tx.status, 3);
+        // * it keeps the approvals
+        assert_eq!(
+            payment_approvals(&deps.storage, &organization_canonical, 0)
+                .unwrap()
+                .len(),
+            2
+        );
+
+        // when the payment has already been released
+        let handle_result = handle(
+            &mut deps,
+            mock_env(HumanAddr::from("bob"), &[]),
+            approve_msg,
+        );
+        // * it raises an error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Payment is not awaiting approval.".to_string(),
+                backtrace: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_batch_cancel() {
+        let (_init_result, mut deps) = init_helper();
+        create_send_requests_helper(&mut deps, vec![Uint128(555_555), Uint128(1)]);
+
+        // when no positions are sent in
+        // * it raises an error
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(0),
+            msg: to_binary(&ReceiveMsg::BatchCancel {
+                positions: vec![],
+                reason: None,
+            })
+            .unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Positions must not be empty.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when the reason is too long
+        // * it raises an error
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(0),
+            msg: to_binary(&ReceiveMsg::BatchCancel {
+                positions: vec![0, 1],
+                reason: Some("a".repeat(257)),
+            })
+            .unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Reason must be at most 256 bytes.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when user sends in a token other than sscrt
+        // * it raises an error
+        let receive_msg = ReceiveMsg::BatchCancel {
+            positions: vec![0, 1],
+            reason: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(0),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_silk().address, &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Wrong token received.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when the creator sends in sscrt
+        let handle_result_unwrapped =
+            handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg).unwrap();
+        // * it refunds the fee of every Tx to the creator
+        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        let refund_msg = snip20::transfer_msg(
+            mock_user_address(),
+            mock_fee(),
+            None,
+            BLOCK_SIZE,
+            config.sscrt.contract_hash,
+            config.sscrt.address,
+        )
+        .unwrap();
+        assert_eq!(
+            handle_result_unwrapped.messages,
+            vec![refund_msg.clone(), refund_msg]
+        );
+        // * it updates the status of every Tx to cancelled
+        for position in 0..2 {
+            let to_tx = tx_at_position(
+                &mut deps.storage,
+                &deps
+                    .api
+                    .canonical_address(&mock_contract_initiator_address())
+                    .unwrap(),
+                position,
+            )
+            .unwrap();
+            let from_tx =
+                tx_at_position(&mut deps.storage, &to_tx.from, to_tx.other_storage_position)
+                    .unwrap();
+            assert_eq!(from_tx.status, 2);
+            assert_eq!(to_tx.status, 2);
+        }
+
+        // when one of the Txs is already cancelled
+        // * it raises an error
+        let receive_msg = ReceiveMsg::BatchCancel {
+            positions: vec![1],
+            reason: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(0),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Tx already cancelled.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when the counterparty cancels Txs with penalties in different fee tokens
+        // * it raises an error
+        handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            HandleMsg::UpdateFeeTokens {
+                fee_tokens: vec![FeeToken {
+                    token: mock_silk(),
+                    fee: Uint128(500),
+                }],
+            },
+        )
+        .unwrap();
+        let create_msg = |amount: Uint128| HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount,
+            msg: to_binary(&ReceiveMsg::CreateSendRequest {
+                address: mock_contract_initiator_address(),
+                send_amount: Uint128(1_000),
+                description: None,
+                token: mock_silk(),
+                skip_confirmation_if_verified: None,
+                referrer: None,
+                cancellation_policy: None,
+                cancellation_penalty: Some(Uint128(50)),
+                hash_timelock: None,
+                milestones: None,
+                attachment_hash: None,
+            })
+            .unwrap(),
+        };
+        handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            create_msg(mock_fee()),
+        )
+        .unwrap();
+        handle(
+            &mut deps,
+            mock_env(mock_silk().address, &[]),
+            create_msg(Uint128(500)),
+        )
+        .unwrap();
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(100),
+            msg: to_binary(&ReceiveMsg::BatchCancel {
+                positions: vec![2, 3],
+                reason: None,
+            })
+            .unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Cancellation penalties must be in the same fee token.".to_string(),
+                backtrace: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_batch_confirm_address() {
+        let (_init_result, mut deps) = init_helper();
+        create_send_requests_helper(&mut deps, vec![Uint128(555_555), Uint128(1)]);
+        let receive_msg = ReceiveMsg::BatchConfirmAddress {
+            positions: vec![0, 1],
+        };
+
+        // when no positions are sent in
+        // * it raises an error
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(0),
+            msg: to_binary(&ReceiveMsg::BatchConfirmAddress { positions: vec![] }).unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Positions must not be empty.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when user is not the receiver of the Txs
+        // * it raises an unauthorized error
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(0),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // when user is the receiver of the Txs
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(0),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            handle_msg.clone(),
+        )
+        .unwrap();
+        // * it updates the status of every Tx to pending payment
+        for position in 0..2 {
+            let to_tx = tx_at_position(
+                &mut deps.storage,
+                &deps
+                    .api
+                    .canonical_address(&mock_contract_initiator_address())
+                    .unwrap(),
+                position,
+            )
+            .unwrap();
+            let from_tx =
+                tx_at_position(&mut deps.storage, &to_tx.from, to_tx.other_storage_position)
+                    .unwrap();
+            assert_eq!(from_tx.status, 1);
+            assert_eq!(to_tx.status, 1);
+        }
+
+        // when the Txs are no longer waiting for address confirmation
+        // * it raises an error
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Tx not waiting for address confirmation.".to_string(),
+                backtrace: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_batch_send_payment() {
+        let (_init_result, mut deps) = init_helper();
+        create_send_requests_helper(&mut deps, vec![Uint128(555_555), Uint128(1)]);
+        let receive_msg = ReceiveMsg::BatchSendPayment {
+            positions: vec![0, 1],
+        };
+
+        // when no positions are sent in
+        // * it raises an error
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(0),
+            msg: to_binary(&ReceiveMsg::BatchSendPayment { positions: vec![] }).unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_silk().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Positions must not be empty.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when Txs are pending address confirmation
+        // * it raises an error
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(555_556),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_silk().address, &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Tx status at that position is incorrect.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when Txs are pending payment
+        let confirm_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(0),
+            msg: to_binary(&ReceiveMsg::BatchConfirmAddress {
+                positions: vec![0, 1],
+            })
+            .unwrap(),
+        };
+        handle(&mut deps, mock_env(mock_sscrt().address, &[]), confirm_msg).unwrap();
+        // = when user sends in the wrong token
+        // = * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_shade().address, &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Wrong token received.".to_string(),
+                backtrace: None
+            }
+        );
+        // = when user sends in the right token
+        // == when amount does not match the sum of the Txs
+        // == * it raises an error
+        let (_init_result, mut deps_two) = init_helper();
+        create_send_requests_helper(&mut deps_two, vec![Uint128(555_555), Uint128(1)]);
+        let confirm_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(0),
+            msg: to_binary(&ReceiveMsg::BatchConfirmAddress {
+                positions: vec![0, 1],
+            })
+            .unwrap(),
+        };
+        handle(
+            &mut deps_two,
+            mock_env(mock_sscrt().address, &[]),
+            confirm_msg,
+        )
+        .unwrap();
+        let wrong_amount_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(555_555),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result = handle(
+            &mut deps_two,
+            mock_env(mock_silk().address, &[]),
+            wrong_amount_msg,
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Wrong amount received.".to_string(),
+                backtrace: None
+            }
+        );
+        // == when amount matches the sum of the Txs
+        let (handle_result, batch_reads, batch_writes, _) =
+            counted_handle_helper(&mut deps, mock_env(mock_silk().address, &[]), handle_msg);
+        let handle_result_unwrapped = handle_result.unwrap();
+        // == * it sends each payment to its receiver
+        assert_eq!(
+            handle_result_unwrapped.messages,
+            vec![
+                snip20::transfer_msg(
+                    mock_contract_initiator_address(),
+                    Uint128(555_555),
+                    None,
+                    BLOCK_SIZE,
+                    mock_silk().contract_hash,
+                    mock_silk().address,
+                )
+                .unwrap(),
+                snip20::transfer_msg(
+                    mock_contract_initiator_address(),
+                    Uint128(1),
+                    None,
+                    BLOCK_SIZE,
+                    mock_silk().contract_hash,
+                    mock_silk().address,
+                )
+                .unwrap()
+            ]
+        );
+        // == * it accrues the fees for the treasury
+        assert_eq!(
+            fee_stats(&deps.storage, &mock_sscrt().address)
+                .unwrap()
+                .accrued,
+            Uint128(mock_fee().u128() * 2)
+        );
+        // == * it updates the status of every Tx to finalized
+        for position in 0..2 {
+            let from_tx = tx_at_position(
+                &mut deps.storage,
+                &deps.api.canonical_address(&mock_user_address()).unwrap(),
+                position,
+            )
+            .unwrap();
+            let to_tx = tx_at_position(
+                &mut deps.storage,
+                &from_tx.to,
+                from_tx.other_storage_position,
+            )
+            .unwrap();
+            assert_eq!(from_tx.status, 3);
+            assert_eq!(to_tx.status, 3);
+        }
+        // == * it uses no more storage accesses than paying the Txs one by one
+        let (_init_result, mut deps_three) = init_helper();
+        create_send_requests_helper(&mut deps_three, vec![Uint128(555_555), Uint128(1)]);
+        let confirm_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(0),
+            msg: to_binary(&ReceiveMsg::BatchConfirmAddress {
+                positions: vec![0, 1],
+            })
+            .unwrap(),
+        };
+        handle(
+            &mut deps_three,
+            mock_env(mock_sscrt().address, &[]),
+            confirm_msg,
+        )
+        .unwrap();
+        let mut single_reads = 0;
+        let mut single_writes = 0;
+        for (position, amount) in [(0, Uint128(555_555)), (1, Uint128(1))] {
+            let handle_msg = HandleMsg::Receive {
+                sender: mock_user_address(),
+                from: mock_user_address(),
+                amount,
+                msg: to_binary(&ReceiveMsg::SendPayment { position }).unwrap(),
+            };
+            let (handle_result, reads, writes, _) = counted_handle_helper(
+                &mut deps_three,
+                mock_env(mock_silk().address, &[]),
+                handle_msg,
+            );
+            handle_result.unwrap();
+            single_reads += reads;
+            single_writes += writes;
+        }
+        assert!(batch_reads <= single_reads);
+        assert!(batch_writes <= single_writes);
+    }
+
+    #[test]
+    fn test_block_address() {
+        let (_init_result, mut deps) = init_helper();
+        let receive_msg = ReceiveMsg::CreateReceiveRequest {
+            address: mock_contract_initiator_address(),
+            send_amount: Uint128(555_555),
+            description: None,
+            token: mock_silk(),
+            referrer: None,
+            cancellation_policy: None,
+            cancellation_penalty: None,
+            attachment_hash: None,
+        };
+        let create_receive_request_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: mock_fee(),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+
+        // when the counterparty has blocked the creator
+        let handle_msg = HandleMsg::BlockAddress {
+            address: mock_user_address(),
+        };
+        handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            handle_msg,
+        )
+        .unwrap();
+        // * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            create_receive_request_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Request not accepted by counterparty.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when the counterparty unblocks the creator
+        let handle_msg = HandleMsg::UnblockAddress {
+            address: mock_user_address(),
+        };
+        handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            handle_msg,
+        )
+        .unwrap();
+        // * it creates the request
+        handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            create_receive_request_msg,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_cancel() {
+        let (_init_result, mut deps) = init_helper();
+        let env = mock_env(mock_user_address(), &[]);
+        let description = Some("Mercy".to_string());
+
+        // when pending address confirmation
+        let send_amount: Uint128 = Uint128(555_555);
+        let receive_msg = ReceiveMsg::CreateSendRequest {
+            address: mock_contract_initiator_address(),
+            send_amount: send_amount,
+            description: description.clone(),
+            token: mock_silk(),
+            skip_confirmation_if_verified: None,
+            referrer: None,
+            cancellation_policy: None,
+            cancellation_penalty: None,
+            hash_timelock: None,
+            milestones: None,
+            attachment_hash: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: mock_fee(),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        handle_result.unwrap();
+        // = when the creator sends in a positive amount
+        let receive_msg = ReceiveMsg::Cancel {
+            position: 0,
+            reason: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(5),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result = handle(&mut deps, env.clone(), handle_msg);
+        // = * it raises an error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Wrong amount received.".to_string(),
+                backtrace: None
+            }
+        );
+        // = when user sends in a zero amount
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(0),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        // == when user sends in a non-sscrt token
+        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        // == * it raises an error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Wrong token received.".to_string(),
+                backtrace: None
+            }
+        );
+        // == when user sends in sscrt token
+        // === when the reason is too long
+        // === * it raises an error
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(0),
+            msg: to_binary(&ReceiveMsg::Cancel {
+                position: 0,
+                reason: Some("a".repeat(257)),
+            })
+            .unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Reason must be at most 256 bytes.".to_string(),
+                backtrace: None
+            }
+        );
+        // === when user tries to cancel a Tx that does not exist
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(0),
+            msg: to_binary(&ReceiveMsg::Cancel {
+                position: 1,
+                reason: None,
+            })
+            .unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        // === * it raises an error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "AppendStorage access out of bounds".to_string(),
+                backtrace: None
+            }
+        );
+        // === when user tries to cancel Tx that exists
+        // ==== when user tries to cancel Tx that is pending address confirmation
+        let receive_msg = ReceiveMsg::Cancel {
+            position: 0,
+            reason: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(0),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result_unwrapped = handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            handle_msg.clone(),
+        )
+        .unwrap();
+        let mut from_tx = tx_at_position(
+            &mut deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+            0,
+        )
+        .unwrap();
+        let mut to_tx = tx_at_position(
+            &mut deps.storage,
+            &from_tx.to,
+            from_tx.other_storage_position,
+        )
+        .unwrap();
+        // ==== * it sends the fee back to the creator
+        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        assert_eq!(
+            handle_result_unwrapped.messages,
+            vec![snip20::transfer_msg(
+                from_tx.creator.clone(),
+                from_tx.fee,
+                None,
+                BLOCK_SIZE,
+                config.sscrt.contract_hash,
+                config.sscrt.address,
+            )
+            .unwrap()]
+        );
+        // ==== * it updates the Tx and counter Tx to cancdl
+        assert_eq!(from_tx.status, 2);
+        assert_eq!(to_tx.status, 2);
+
+        // ==== when user tries to cancel Tx that is cancelled
+        // ==== * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Tx already cancelled.".to_string(),
+                backtrace: None
+            }
+        );
+        // ==== when user tries to cancel Tx that is pending payment
+        from_tx.status = 1;
+        to_tx.status = 1;
+        update_tx(&mut deps.storage, &from_tx.from.clone(), from_tx).unwrap();
+        update_tx(&mut deps.storage, &to_tx.to.clone(), to_tx).unwrap();
+        let receive_msg = ReceiveMsg::Cancel {
+            position: 0,
+            reason: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(0),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result_unwrapped = handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            handle_msg.clone(),
+        )
+        .unwrap();
+        let mut from_tx = tx_at_position(
+            &mut deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+            0,
+        )
+        .unwrap();
+        let mut to_tx = tx_at_position(
+            &mut deps.storage,
+            &from_tx.to,
+            from_tx.other_storage_position,
+        )
+        .unwrap();
+        // ==== * it sends the fee back to the creator
+        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        assert_eq!(
+            handle_result_unwrapped.messages,
+            vec![snip20::transfer_msg(
+                from_tx.creator.clone(),
+                from_tx.fee,
+                None,
+                BLOCK_SIZE,
+                config.sscrt.contract_hash,
+                config.sscrt.address,
+            )
+            .unwrap()]
+        );
+        // ==== * it updates the Tx and counter Tx to cancel
+        assert_eq!(from_tx.status, 2);
+        assert_eq!(to_tx.status, 2);
+        // ==== when user tries to cancel Tx that is finalized
+        from_tx.status = 3;
+        to_tx.status = 3;
+        update_tx(&mut deps.storage, &from_tx.from.clone(), from_tx).unwrap();
+        update_tx(&mut deps.storage, &to_tx.to.clone(), to_tx).unwrap();
+        // ==== * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Tx already finalized.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when the counterparty cancels
+        let create_msg = |cancellation_policy: Option<CancellationPolicy>| HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: mock_fee(),
+            msg: to_binary(&ReceiveMsg::CreateSendRequest {
+                address: mock_contract_initiator_address(),
+                send_amount: send_amount,
+                description: description.clone(),
+                token: mock_silk(),
+                skip_confirmation_if_verified: None,
+                referrer: None,
+                cancellation_policy: cancellation_policy,
+                cancellation_penalty: Some(Uint128(500)),
+                hash_timelock: None,
+                milestones: None,
+                attachment_hash: None,
+            })
+            .unwrap(),
+        };
+        handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            create_msg(None),
+        )
+        .unwrap();
+        let receive_msg = ReceiveMsg::Cancel {
+            position: 1,
+            reason: Some("Wrong invoice".to_string()),
+        };
+        // = when the counterparty does not send in the penalty
+        // = * it raises an error
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(0),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Wrong amount received.".to_string(),
+                backtrace: None
+            }
+        );
+        // = when the counterparty sends in the penalty
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(500),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        // == when the penalty is sent in a token other than the fee token
+        // == * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_silk().address, &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Wrong token received.".to_string(),
+                backtrace: None
+            }
+        );
+        // == when the penalty is sent in the fee token
+        let handle_result_unwrapped =
+            handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg).unwrap();
+        // = * it passes the penalty on to the creator instead of refunding the fee
+        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        assert_eq!(
+            handle_result_unwrapped.messages,
+            vec![snip20::transfer_msg(
+                mock_user_address(),
+                Uint128(500),
+                None,
+                BLOCK_SIZE,
+                config.sscrt.contract_hash,
+                config.sscrt.address,
+            )
+            .unwrap()]
+        );
+        // = * it accrues the fee for the treasury
+        assert_eq!(
+            fee_stats(&deps.storage, &mock_sscrt().address)
+                .unwrap()
+                .accrued,
+            mock_fee()
+        );
+        // = * it stores the reason on the Tx and counter Tx
+        let to_tx = tx_at_position(
+            &mut deps.storage,
+            &deps
+                .api
+                .canonical_address(&mock_contract_initiator_address())
+                .unwrap(),
+            1,
+        )
+        .unwrap();
+        let from_tx =
+            tx_at_position(&mut deps.storage, &to_tx.from, to_tx.other_storage_position).unwrap();
+        assert_eq!(from_tx.status, 2);
+        assert_eq!(to_tx.status, 2);
+        assert_eq!(
+            from_tx.cancellation_reason,
+            Some("Wrong invoice".to_string())
+        );
+        assert_eq!(to_tx.cancellation_reason, Some("Wrong invoice".to_string()));
+
+        // when the Tx can only be cancelled by the creator
+        handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            create_msg(Some(CancellationPolicy::CreatorOnly)),
+        )
+        .unwrap();
+        // = when the counterparty tries to cancel
+        // = * it raises an error
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(500),
+            msg: to_binary(&ReceiveMsg::Cancel {
+                position: 2,
+                reason: None,
+            })
+            .unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Only the creator can cancel this Tx.".to_string(),
+                backtrace: None
+            }
+        );
+        // = when the creator cancels
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(0),
+            msg: to_binary(&ReceiveMsg::Cancel {
+                position: 2,
+                reason: None,
+            })
+            .unwrap(),
+        };
+        let handle_result_unwrapped =
+            handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg).unwrap();
+        // = * it refunds the fee without a penalty
+        assert_eq!(
+            handle_result_unwrapped.messages,
+            vec![snip20::transfer_msg(
+                mock_user_address(),
+                mock_fee(),
+                None,
+                BLOCK_SIZE,
+                mock_sscrt().contract_hash,
+                mock_sscrt().address,
+            )
+            .unwrap()]
+        );
+        let from_tx = tx_at_position(
+            &mut deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+            2,
+        )
+        .unwrap();
+        assert_eq!(from_tx.status, 2);
+
+        // when the Tx can only be cancelled before confirmation
+        // = when the address has not been confirmed
+        handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            create_msg(Some(CancellationPolicy::BeforeConfirmation)),
+        )
+        .unwrap();
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(500),
+            msg: to_binary(&ReceiveMsg::Cancel {
+                position: 3,
+                reason: None,
+            })
+            .unwrap(),
+        };
+        let handle_result_unwrapped =
+            handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg).unwrap();
+        // = * the counterparty can cancel it and the penalty goes to the creator
+        assert_eq!(
+            handle_result_unwrapped.messages,
+            vec![snip20::transfer_msg(
+                mock_user_address(),
+                Uint128(500),
+                None,
+                BLOCK_SIZE,
+                mock_sscrt().contract_hash,
+                mock_sscrt().address,
+            )
+            .unwrap()]
+        );
+        // = when the address has been confirmed
+        // = * it raises an error
+        handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            create_msg(Some(CancellationPolicy::BeforeConfirmation)),
+        )
+        .unwrap();
+        let confirm_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(0),
+            msg: to_binary(&ReceiveMsg::ConfirmAddress { position: 4 }).unwrap(),
+        };
+        handle(&mut deps, mock_env(mock_sscrt().address, &[]), confirm_msg).unwrap();
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(0),
+            msg: to_binary(&ReceiveMsg::Cancel {
+                position: 4,
+                reason: None,
+            })
+            .unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Tx can not be cancelled after address confirmation.".to_string(),
+                backtrace: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_cancel_milestone() {
+        let (_init_result, mut deps) = init_helper();
+        create_milestones_helper(&mut deps, CancellationPolicy::CreatorOnly);
+        let user_canonical = deps.api.canonical_address(&mock_user_address()).unwrap();
+
+        // when the cancellation policy does not allow the user to cancel
+        // * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            HandleMsg::CancelMilestone {
+                position: 0,
+                index: 0,
+            },
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Only the creator can cancel this Tx.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when the cancellation policy allows the user to cancel
+        // = when some milestones are still pending
+        let handle_result_unwrapped = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            HandleMsg::CancelMilestone {
+                position: 0,
+                index: 1,
+            },
+        )
+        .unwrap();
+        // = * it returns the amount of the milestone to the sender
+        assert_eq!(
+            handle_result_unwrapped.messages,
+            vec![snip20::transfer_msg(
+                mock_user_address(),
+                Uint128(600),
+                None,
+                BLOCK_SIZE,
+                mock_silk().contract_hash,
+                mock_silk().address,
+            )
+            .unwrap()]
+        );
+        // = * it keeps the Tx escrowed
+        let from_tx = tx_at_position(&mut deps.storage, &user_canonical, 0).unwrap();
+        assert_eq!(from_tx.status, 6);
+        assert_eq!(
+            from_tx.milestones.unwrap()[1].status,
+            MilestoneStatus::Cancelled
+        );
+        // = when no milestones are pending and none were released
+        let handle_result_unwrapped = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            HandleMsg::CancelMilestone {
+                position: 0,
+                index: 0,
+            },
+        )
+        .unwrap();
+        // = * it returns the fee and the amount of the milestone to the sender
+        assert_eq!(
+            handle_result_unwrapped.messages,
+            vec![
+                snip20::transfer_msg(
+                    mock_user_address(),
+                    mock_fee(),
+                    None,
+                    BLOCK_SIZE,
+                    mock_sscrt().contract_hash,
+                    mock_sscrt().address,
+                )
+                .unwrap(),
+                snip20::transfer_msg(
+                    mock_user_address(),
+                    Uint128(400),
+                    None,
+                    BLOCK_SIZE,
+                    mock_silk().contract_hash,
+                    mock_silk().address,
+                )
+                .unwrap()
+            ]
+        );
+        // = * it cancels the Tx
+        let from_tx = tx_at_position(&mut deps.storage, &user_canonical, 0).unwrap();
+        assert_eq!(from_tx.status, 2);
+
+        // when the Tx can only be cancelled before address confirmation
+        let (_init_result, mut deps) = init_helper();
+        create_milestones_helper(&mut deps, CancellationPolicy::BeforeConfirmation);
+        let handle_msg = HandleMsg::CancelMilestone {
+            position: 0,
+            index: 1,
+        };
+        // = when the deadline of the milestone has not passed
+        // = * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Tx can not be cancelled after address confirmation.".to_string(),
+                backtrace: None
+            }
+        );
+        // = when the deadline of the milestone has passed
+        // == when user is the receiver
+        // == * it raises an error
+        let mut env = mock_env(mock_contract_initiator_address(), &[]);
+        env.block.time = mock_expires_at();
+        let handle_result = handle(&mut deps, env, handle_msg.clone());
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Tx can not be cancelled after address confirmation.".to_string(),
+                backtrace: None
+            }
+        );
+        // == when user is the sender
+        // == * it returns the amount of the milestone to the sender
+        let mut env = mock_env(mock_user_address(), &[]);
+        env.block.time = mock_expires_at();
+        let handle_result_unwrapped = handle(&mut deps, env, handle_msg).unwrap();
+        assert_eq!(handle_result_unwrapped.messages.len(), 1);
+    }
+
+    #[test]
+    fn test_claim() {
+        let (_init_result, mut deps) = init_helper();
+        let handle_msg = HandleMsg::Claim {
+            code: "open-sesame".to_string(),
+            recipient: mock_contract_initiator_address(),
+        };
+
+        // when claim does not exist
+        // * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Claim not found.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when claim is not funded
+        // * it raises an error
+        create_claim_helper(&mut deps, false);
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Claim not funded.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when claim is funded
+        let (_init_result, mut deps) = init_helper();
+        create_claim_helper(&mut deps, true);
+        // = when claim has expired
+        // = * it raises an error
+        let mut env = mock_env(mock_contract_initiator_address(), &[]);
+        env.block.time = mock_expires_at();
+        let handle_result = handle(&mut deps, env, handle_msg.clone());
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Claim expired.".to_string(),
+                backtrace: None
+            }
+        );
+        // = when claim has not expired
+        let handle_result_unwrapped = handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            handle_msg,
+        )
+        .unwrap();
+        // = * it sends the funds to the recipient
+        assert_eq!(
+            handle_result_unwrapped.messages,
+            vec![snip20::transfer_msg(
+                mock_contract_initiator_address(),
+                Uint128(1_000),
+                None,
+                BLOCK_SIZE,
+                mock_silk().contract_hash,
+                mock_silk().address,
+            )
+            .unwrap()]
+        );
+        // = * it stores a finalized Tx from the sender to the recipient
+        let to_tx = tx_at_position(
+            &mut deps.storage,
+            &deps
+                .api
+                .canonical_address(&mock_contract_initiator_address())
+                .unwrap(),
+            0,
+        )
+        .unwrap();
+        assert_eq!(
+            to_tx.from,
+            deps.api.canonical_address(&mock_user_address()).unwrap()
+        );
+        assert_eq!(to_tx.amount, Uint128(1_000));
+        assert_eq!(to_tx.status, 3);
+        // = * it accrues the fee for the treasury
+        assert_eq!(
+            fee_stats(&deps.storage, &mock_sscrt().address)
+                .unwrap()
+                .accrued,
+            mock_fee()
+        );
+        // = * it removes the claim
+        assert_eq!(claim_by_code(&deps.storage, "open-sesame").unwrap(), None);
+    }
+
+    #[test]
+    fn test_claim_htlc() {
+        let (_init_result, mut deps) = init_helper();
+        let handle_result_unwrapped = create_htlc_helper(&mut deps);
+        let user_canonical = deps.api.canonical_address(&mock_user_address()).unwrap();
+        let admin_canonical = deps
+            .api
+            .canonical_address(&mock_contract_initiator_address())
+            .unwrap();
+
+        // when payment is sent for a hash timelocked Tx
+        // * it escrows the payment
+        assert_eq!(handle_result_unwrapped.messages, vec![]);
+        let to_tx = tx_at_position(&mut deps.storage, &admin_canonical, 0).unwrap();
+        assert_eq!(to_tx.status, 6);
+
+        // when user is not the receiver
+        // * it raises an unauthorized error
+        let handle_msg = HandleMsg::ClaimHtlc {
+            position: 0,
+            preimage: Binary(b"swap-secret".to_vec()),
+        };
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // when user is the receiver
+        // = when preimage is too long
+        // = * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            HandleMsg::ClaimHtlc {
+                position: 0,
+                preimage: Binary(vec![1; 65]),
+            },
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Preimage must be at most 64 bytes.".to_string(),
+                backtrace: None
+            }
+        );
+        // = when preimage does not match the hashlock
+        // = * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            HandleMsg::ClaimHtlc {
+                position: 0,
+                preimage: Binary(b"wrong-secret".to_vec()),
+            },
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Preimage does not match hashlock.".to_string(),
+                backtrace: None
+            }
+        );
+        // = when timelock has passed
+        // = * it raises an error
+        let mut env = mock_env(mock_contract_initiator_address(), &[]);
+        env.block.time = mock_expires_at();
+        let handle_result = handle(&mut deps, env, handle_msg.clone());
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Timelock has passed.".to_string(),
+                backtrace: None
+            }
+        );
+        // = when preimage matches before the timelock
+        let handle_result_unwrapped = handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            handle_msg,
+        )
+        .unwrap();
+        // = * it releases the payment to the receiver
+        assert_eq!(
+            handle_result_unwrapped.messages,
+            vec![snip20::transfer_msg(
+                mock_contract_initiator_address(),
+                Uint128(1_000),
+                None,
+                BLOCK_SIZE,
+                mock_silk().contract_hash,
+                mock_silk().address,
+            )
+            .unwrap()]
+        );
+        // = * it finalizes the Txs and stores the preimage
+        let from_tx = tx_at_position(&mut deps.storage, &user_canonical, 0).unwrap();
+        let to_tx = tx_at_position(&mut deps.storage, &admin_canonical, 0).unwrap();
+        assert_eq!(from_tx.status, 3);
+        assert_eq!(to_tx.status, 3);
+        assert_eq!(from_tx.preimage, Some(Binary(b"swap-secret".to_vec())));
+        assert_eq!(to_tx.preimage, Some(Binary(b"swap-secret".to_vec())));
+        // = * it accrues the fee for the treasury
+        assert_eq!(
+            fee_stats(&deps.storage, &mock_sscrt().address)
+                .unwrap()
+                .accrued,
+            mock_fee()
+        );
+    }
+
+    #[test]
+    fn test_close_payment_link() {
+        let (_init_result, mut deps) = init_helper();
+        let handle_msg = HandleMsg::ClosePaymentLink {
+            code_hash: Binary(hash_code("open-sesame")),
+        };
+
+        // when payment link does not exist
+        // * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Payment link not found.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when payment link exists
+        create_payment_link_helper(&mut deps, 3);
+        let pay_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(1_000),
+            msg: to_binary(&ReceiveMsg::PayLink {
+                code: "open-sesame".to_string(),
+            })
+            .unwrap(),
+        };
+        handle(&mut deps, mock_env(mock_silk().address, &[]), pay_msg).unwrap();
+        // = when user is not the receiver
+        // = * it raises an unauthorized error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+        // = when user is the receiver
+        let handle_result_unwrapped = handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            handle_msg,
+        )
+        .unwrap();
+        // = * it refunds the fees for the uses that are left
+        assert_eq!(
+            handle_result_unwrapped.messages,
+            vec![snip20::transfer_msg(
+                mock_contract_initiator_address(),
+                Uint128(mock_fee().u128() * 2),
+                None,
+                BLOCK_SIZE,
+                mock_sscrt().contract_hash,
+                mock_sscrt().address,
+            )
+            .unwrap()]
+        );
+        // = * it removes the payment link
+        assert_eq!(
+            payment_link_by_code(&deps.storage, "open-sesame").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_confirm_address() {
+        let (_init_result, mut deps) = init_helper();
+        let env = mock_env(mock_user_address(), &[]);
+        let description = Some("Mercy".to_string());
+
+        // when send receive exists
+        let send_amount: Uint128 = Uint128(555_555);
+        let receive_msg = ReceiveMsg::CreateSendRequest {
+            address: mock_contract_initiator_address(),
+            send_amount: send_amount,
+            description: description.clone(),
+            token: mock_silk(),
+            skip_confirmation_if_verified: None,
+            referrer: None,
+            cancellation_policy: None,
+            cancellation_penalty: None,
+            hash_timelock: None,
+            milestones: None,
+            attachment_hash: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: mock_fee(),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        handle_result.unwrap();
+        // = when user sends in a positive amount
+        let receive_msg = ReceiveMsg::ConfirmAddress { position: 1 };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(5),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result = handle(&mut deps, env.clone(), handle_msg);
+        // = * it raises an error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Wrong amount received.".to_string(),
+                backtrace: None
+            }
+        );
+        // = when user sends in a zero amount
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(0),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        // == when user sends in a non-sscrt token
+        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        // == * it raises an error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Wrong token received.".to_string(),
+                backtrace: None
+            }
+        );
+        // == when user sends in sscrt token
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        // === when user tries to confirm address of a Tx that does not exist
+        // === * it raises an error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "AppendStorage access out of bounds".to_string(),
+                backtrace: None
+            }
+        );
+        // === when user tries to confirm address of a Tx that exists
+        // ==== when user tries to confirm Tx that is pending address confirmation
+        // ===== when user tries to confirm Tx that that they are not the receiver of
+        let receive_msg = ReceiveMsg::ConfirmAddress { position: 0 };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(0),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // ===== when user tries to confirm Tx that that they are the receiver of
+        // ===== * it updates the Tx and counter Tx to pending payment
+        let receive_msg = ReceiveMsg::ConfirmAddress { position: 0 };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(0),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result_unwrapped = handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            handle_msg.clone(),
+        )
+        .unwrap();
+        assert_eq!(handle_result_unwrapped.messages, vec![]);
+        let from_tx = tx_at_position(
+            &mut deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+            0,
+        )
+        .unwrap();
+        let to_tx = tx_at_position(
+            &mut deps.storage,
+            &from_tx.to,
+            from_tx.other_storage_position,
+        )
+        .unwrap();
+        assert_eq!(from_tx.status, 1);
+        assert_eq!(to_tx.status, 1);
+        // ===== * it adds the receiver to the sender's address book
+        let address_book_entry = address_book_entry(
+            &deps.storage,
+            &from_tx.from,
+            &deps
+                .api
+                .canonical_address(&mock_contract_initiator_address())
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            address_book_entry,
+            Some(AddressBookEntry {
+                label: None,
+                address: mock_contract_initiator_address(),
+                verified_at: env.block.time,
+            })
+        );
+
+        // ==== when user tries to confirm Tx that is not pending address confirmation
+        // ==== * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Tx not waiting for address confirmation.".to_string(),
+                backtrace: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_create_claim() {
+        let (_init_result, mut deps) = init_helper();
+        let receive_msg = |code_hash: Binary, expires_at: u64| HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: mock_fee(),
+            msg: to_binary(&ReceiveMsg::CreateClaim {
+                code_hash,
+                send_amount: Uint128(1_000),
+                description: Some("Welcome".to_string()),
+                token: mock_silk(),
+                expires_at,
+            })
+            .unwrap(),
+        };
+
+        // when code hash is not a SHA-256 hash
+        // * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            receive_msg(Binary(b"open-sesame".to_vec()), mock_expires_at()),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Code hash must be a SHA-256 hash.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when expiry is not in the future
+        // * it raises an error
+        let code_hash = Binary(hash_code("open-sesame"));
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            receive_msg(
+                code_hash.clone(),
+                mock_env(mock_user_address(), &[]).block.time,
+            ),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Expiry must be in the future.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when details are valid
+        handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            receive_msg(code_hash.clone(), mock_expires_at()),
+        )
+        .unwrap();
+        // * it stores an unfunded claim that can be queried with the code
+        let query_result = query(
+            &deps,
+            QueryMsg::Claim {
+                code: "open-sesame".to_string(),
+            },
+        )
+        .unwrap();
+        let query_answer: QueryAnswer = from_binary(&query_result).unwrap();
+        let claim = match query_answer {
+            QueryAnswer::Claim { claim } => claim,
+            _ => panic!("Unexpected query answer"),
+        };
+        assert_eq!(
+            claim,
+            Claim {
+                sender: mock_user_address(),
+                send_amount: Uint128(1_000),
+                token: mock_silk(),
+                description: Some("Welcome".to_string()),
+                fee: mock_fee(),
+                fee_token: mock_sscrt(),
+                funded: false,
+                expires_at: mock_expires_at(),
+            }
+        );
+
+        // when code is already in use
+        // * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            receive_msg(code_hash, mock_expires_at()),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Claim code is already in use.".to_string(),
+                backtrace: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_create_organization() {
+        let (_init_result, mut deps) = init_helper();
+
+        // when the user creates an organization
+        let organization = create_organization_helper(&mut deps);
+        let organization_canonical = deps.api.canonical_address(&organization).unwrap();
+        // * it stores the organization with the user as admin
+        let stored = organization_at(&deps.storage, &organization_canonical).unwrap();
+        assert_eq!(stored.name, "Acme".to_string());
+        assert_eq!(stored.admin, mock_user_address());
+        assert_eq!(
+            stored.role(&mock_user_address()),
+            Some(OrganizationRole::Approver)
+        );
+        // * it starts the audit trail
+        let (entries, total) =
+            get_audit_trail(&deps.storage, &organization_canonical, 0, 10).unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(
+            entries[1].action,
+            OrganizationAction::Handle {
+                msg: HandleMsg::CreateOrganization {
+                    name: "Acme".to_string()
+                }
+            }
+        );
+
+        // when another organization is created
+        // * it gets a different address
+        assert_ne!(create_organization_helper(&mut deps), organization);
+
+        // when a send request is made out to an organization
+        let receive_msg = ReceiveMsg::CreateSendRequest {
+            address: organization,
+            send_amount: Uint128(1_000),
+            description: None,
+            token: mock_silk(),
+            skip_confirmation_if_verified: None,
+            referrer: None,
+            cancellation_policy: None,
+            cancellation_penalty: None,
+            hash_timelock: None,
+            milestones: None,
+            attachment_hash: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: mock_fee(),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        // * it raises an error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Organizations can not receive payments.".to_string(),
+                backtrace: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_create_payment_link() {
+        let (_init_result, mut deps) = init_helper();
+        let receive_msg = ReceiveMsg::CreatePaymentLink {
+            code_hash: Binary(hash_code("open-sesame")),
+            send_amount: Uint128(1_000),
+            description: Some("Donation".to_string()),
+            token: mock_silk(),
+            max_uses: Some(2),
+            referrer: None,
+        };
+
+        // when code hash is not a SHA-256 hash
+        // * it raises an error
+        let mut plaintext_receive_msg = receive_msg.clone();
+        if let ReceiveMsg::CreatePaymentLink { code_hash, .. } = &mut plaintext_receive_msg {
+            *code_hash = Binary(b"open-sesame".to_vec());
+        }
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(mock_fee().u128() * 2),
+            msg: to_binary(&plaintext_receive_msg).unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Code hash must be a SHA-256 hash.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when the fee for every use overflows
+        // * it raises an error
+        let mut overflowing_receive_msg = receive_msg.clone();
+        if let ReceiveMsg::CreatePaymentLink { max_uses, .. } = &mut overflowing_receive_msg {
+            *max_uses = Some(u32::MAX);
+        }
+        handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            HandleMsg::UpdateFee {
+                fee: Uint128(u128::MAX / 1_000_000),
+            },
+        )
+        .unwrap();
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: mock_fee(),
+            msg: to_binary(&overflowing_receive_msg).unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Total fee overflows.".to_string(),
+                backtrace: None
+            }
+        );
+        handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            HandleMsg::UpdateFee { fee: mock_fee() },
+        )
+        .unwrap();
+
+        // when user does not send in the fee for every use
+        // * it raises an error
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: mock_fee(),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Wrong amount received.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when user sends in the fee for every use
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(mock_fee().u128() * 2),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            handle_msg.clone(),
+        )
+        .unwrap();
+        // * it stores the payment link under the hash of the code
+        let query_result = query(
+            &deps,
+            QueryMsg::PaymentLink {
+                code: "open-sesame".to_string(),
+            },
+        )
+        .unwrap();
+        let query_answer: QueryAnswer = from_binary(&query_result).unwrap();
+        let payment_link = match query_answer {
+            QueryAnswer::PaymentLink { payment_link } => payment_link,
+            _ => panic!("Unexpected query answer"),
+        };
+        assert_eq!(
+            payment_link,
+            PaymentLink {
+                receiver: mock_contract_initiator_address(),
+                send_amount: Uint128(1_000),
+                token: mock_silk(),
+                description: Some("Donation".to_string()),
+                referrer: None,
+                fee: mock_fee(),
+                fee_token: mock_sscrt(),
+                uses: 0,
+                max_uses: 2,
+            }
+        );
+
+        // when code is already in use
+        // * it raises an error
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Payment link code is already in use.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when max uses is zero
+        // * it raises an error
+        let receive_msg = ReceiveMsg::CreatePaymentLink {
+            code_hash: Binary(hash_code("abracadabra")),
+            send_amount: Uint128(1_000),
+            description: None,
+            token: mock_silk(),
+            max_uses: Some(0),
+            referrer: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(0),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Max uses must be greater than zero.".to_string(),
+                backtrace: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_create_receive_request() {
+        let (_init_result, mut deps) = init_helper();
+        let env = mock_env(mock_user_address(), &[]);
+        let description = Some("Mercy".to_string());
+
+        // when incorrect fee amount is sent in
+        let receive_msg = ReceiveMsg::CreateReceiveRequest {
+            address: mock_user_address(),
+            send_amount: Uint128(555555),
+            description: description.clone(),
+            token: mock_silk(),
+            referrer: None,
+            cancellation_policy: None,
+            cancellation_penalty: None,
+            attachment_hash: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(555),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result = handle(&mut deps, env.clone(), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Wrong amount received.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when correct fee amount is sent in
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: mock_fee(),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        // = when incorrect fee token is sent in
+        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Wrong token received.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // = when correct fee token is sent in
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        // == when sender sets the receiver to themselves
+        // == * it raises an error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "From and to addresses must be different.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // == when sender sets the receiver to somebody else
+        let send_amount: Uint128 = Uint128(555_555);
+        let receive_msg = ReceiveMsg::CreateReceiveRequest {
+            address: mock_contract_initiator_address(),
+            send_amount: send_amount,
+            description: description.clone(),
+            token: mock_silk(),
+            referrer: None,
+            cancellation_policy: None,
+            cancellation_penalty: None,
+            attachment_hash: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: mock_fee(),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        let handle_result_unwrapped = handle_result.unwrap();
+        assert_eq!(
+            handle_result_unwrapped.messages,
+            vec![snip20::register_receive_msg(
+                mock_env(mock_sscrt().address, &[]).contract_code_hash,
+                None,
+                BLOCK_SIZE,
+                mock_silk().contract_hash,
+                mock_silk().address,
+            )
+            .unwrap()]
+        );
+        // == * it creates the txs
+        let from_tx = tx_at_position(
+            &mut deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+            0,
+        )
+        .unwrap();
+        let to_tx = tx_at_position(
+            &mut deps.storage,
+            &from_tx.to,
+            from_tx.other_storage_position,
+        )
+        .unwrap();
+        assert_eq!(
+            from_tx,
+            Tx {
+                position: 0,
+                other_storage_position: 0,
+                fee: mock_fee(),
+                fee_token: mock_sscrt(),
+                from: deps
+                    .api
+                    .canonical_address(&mock_contract_initiator_address())
+                    .unwrap(),
+                to: deps.api.canonical_address(&mock_user_address()).unwrap(),
+                creator: mock_user_address(),
+                referrer: None,
+                amount: send_amount,
+                token: mock_silk(),
+                description: description.clone(),
+                status: 1,
+                block_time: env.block.time,
+                block_height: env.block.height,
+                cancellation_policy: CancellationPolicy::EitherParty,
+                cancellation_penalty: Uint128(0),
+                cancellation_reason: None,
+                refunded: Uint128(0),
+                refund_of: None,
+                hash_timelock: None,
+                preimage: None,
+                milestones: None,
+                milestone_of: None,
+                memo: None,
+                attachment_hash: None,
+            }
+        );
+        assert_eq!(
+            to_tx,
+            Tx {
+                position: 0,
+                other_storage_position: 0,
+                fee: mock_fee(),
+                fee_token: mock_sscrt(),
+                from: deps
+                    .api
+                    .canonical_address(&mock_contract_initiator_address())
+                    .unwrap(),
+                to: deps.api.canonical_address(&mock_user_address()).unwrap(),
+                creator: mock_user_address(),
+                referrer: None,
+                amount: send_amount,
+                token: mock_silk(),
+                description: description,
+                status: 1,
+                block_time: env.block.time,
+                block_height: env.block.height,
+                cancellation_policy: CancellationPolicy::EitherParty,
+                cancellation_penalty: Uint128(0),
+                cancellation_reason: None,
+                refunded: Uint128(0),
+                refund_of: None,
+                hash_timelock: None,
+                preimage: None,
+                milestones: None,
+                milestone_of: None,
+                memo: None,
+                attachment_hash: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_create_send_request() {
+        let (_init_result, mut deps) = init_helper();
+        let env = mock_env(mock_user_address(), &[]);
+        let description = Some("Mercy".to_string());
+
+        // when incorrect fee amount is sent in
+        let receive_msg = ReceiveMsg::CreateSendRequest {
+            address: mock_user_address(),
+            send_amount: Uint128(555555),
+            description: description.clone(),
+            token: mock_silk(),
+            skip_confirmation_if_verified: None,
+            referrer: None,
+            cancellation_policy: None,
+            cancellation_penalty: None,
+            hash_timelock: None,
+            milestones: None,
+            attachment_hash: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(555),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result = handle(&mut deps, env.clone(), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Wrong amount received.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when correct fee amount is sent in
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: mock_fee(),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        // = when incorrect fee token is sent in
+        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Wrong token received.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // = when correct fee token is sent in
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        // == when sender sets the receiver to themselves
+        // == * it raises an error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "From and to addresses must be different.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // == when sender sets the receiver to somebody else
+        let send_amount: Uint128 = Uint128(555_555);
+        let receive_msg = ReceiveMsg::CreateSendRequest {
+            address: mock_contract_initiator_address(),
+            send_amount: send_amount,
+            description: description.clone(),
+            token: mock_silk(),
+            skip_confirmation_if_verified: None,
+            referrer: None,
+            cancellation_policy: None,
+            cancellation_penalty: None,
+            hash_timelock: None,
+            milestones: None,
+            attachment_hash: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: mock_fee(),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        let handle_result_unwrapped = handle_result.unwrap();
+        // == * it registers a token if it is not registered already
+        assert_eq!(
+            handle_result_unwrapped.messages,
+            vec![snip20::register_receive_msg(
+                mock_env(mock_sscrt().address, &[]).contract_code_hash,
+                None,
+                BLOCK_SIZE,
+                mock_silk().contract_hash,
+                mock_silk().address,
+            )
+            .unwrap()]
+        );
+        // == * it creates the txs
+        let from_tx = tx_at_position(
+            &mut deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+            0,
+        )
+        .unwrap();
+        let to_tx = tx_at_position(
+            &mut deps.storage,
+            &from_tx.to,
+            from_tx.other_storage_position,
+        )
+        .unwrap();
+        assert_eq!(
+            from_tx,
+            Tx {
+                position: 0,
+                other_storage_position: 0,
+                fee: mock_fee(),
+                fee_token: mock_sscrt(),
+                from: deps.api.canonical_address(&mock_user_address()).unwrap(),
+                to: deps
+                    .api
+                    .canonical_address(&mock_contract_initiator_address())
+                    .unwrap(),
+                creator: mock_user_address(),
+                referrer: None,
+                amount: send_amount,
+                token: mock_silk(),
+                description: description.clone(),
+                status: 0,
+                block_time: env.block.time,
+                block_height: env.block.height,
+                cancellation_policy: CancellationPolicy::EitherParty,
+                cancellation_penalty: Uint128(0),
+                cancellation_reason: None,
+                refunded: Uint128(0),
+                refund_of: None,
+                hash_timelock: None,
+                preimage: None,
+                milestones: None,
+                milestone_of: None,
+                memo: None,
+                attachment_hash: None,
+            }
+        );
+        assert_eq!(
+            to_tx,
+            Tx {
+                position: 0,
+                other_storage_position: 0,
+                fee: mock_fee(),
+                fee_token: mock_sscrt(),
+                from: deps.api.canonical_address(&mock_user_address()).unwrap(),
+                to: deps
+                    .api
+                    .canonical_address(&mock_contract_initiator_address())
+                    .unwrap(),
+                creator: mock_user_address(),
+                referrer: None,
+                amount: send_amount,
+                token: mock_silk(),
+                description: description.clone(),
+                status: 0,
+                block_time: env.block.time,
+                block_height: env.block.height,
+                cancellation_policy: CancellationPolicy::EitherParty,
+                cancellation_penalty: Uint128(0),
+                cancellation_reason: None,
+                refunded: Uint128(0),
+                refund_of: None,
+                hash_timelock: None,
+                preimage: None,
+                milestones: None,
+                milestone_of: None,
+                memo: None,
+                attachment_hash: None,
+            }
+        );
+
+        // == when sender wants to skip confirmation
+        let receive_msg = ReceiveMsg::CreateSendRequest {
+            address: mock_contract_initiator_address(),
+            send_amount: send_amount,
+            description: description.clone(),
+            token: mock_silk(),
+            skip_confirmation_if_verified: Some(true),
+            referrer: None,
+            cancellation_policy: None,
+            cancellation_penalty: None,
+            hash_timelock: None,
+            milestones: None,
+            attachment_hash: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: mock_fee(),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        // === when the receiver's address has not been verified
+        // === * it creates the txs pending address confirmation
+        handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            handle_msg.clone(),
+        )
+        .unwrap();
+        let from_tx = tx_at_position(
+            &mut deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+            1,
+        )
+        .unwrap();
+        assert_eq!(from_tx.status, 0);
+        // === when the receiver's address has been verified
+        // === * it creates the txs pending payment
+        let confirm_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(0),
+            msg: to_binary(&ReceiveMsg::ConfirmAddress { position: 1 }).unwrap(),
+        };
+        handle(&mut deps, mock_env(mock_sscrt().address, &[]), confirm_msg).unwrap();
+        handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg).unwrap();
+        let from_tx = tx_at_position(
+            &mut deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+            2,
+        )
+        .unwrap();
+        let to_tx = tx_at_position(
+            &mut deps.storage,
+            &from_tx.to,
+            from_tx.other_storage_position,
+        )
+        .unwrap();
+        assert_eq!(from_tx.status, 1);
+        assert_eq!(to_tx.status, 1);
+
+        // == when milestone amounts do not add up to the send amount
+        // == * it raises an error
+        let receive_msg = ReceiveMsg::CreateSendRequest {
+            address: mock_contract_initiator_address(),
+            send_amount: send_amount,
+            description: description.clone(),
+            token: mock_silk(),
+            skip_confirmation_if_verified: None,
+            referrer: None,
+            cancellation_policy: None,
+            cancellation_penalty: None,
+            hash_timelock: None,
+            milestones: Some(vec![Milestone {
+                label: "Design".to_string(),
+                amount: (send_amount - Uint128(1)).unwrap(),
+                deadline: None,
+                status: MilestoneStatus::Pending,
+            }]),
+            attachment_hash: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: mock_fee(),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Milestone amounts must add up to the send amount.".to_string(),
+                backtrace: None
+            }
+        );
+        let milestones_msg = |milestones: Vec<Milestone>| HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: mock_fee(),
+            msg: to_binary(&ReceiveMsg::CreateSendRequest {
+                address: mock_contract_initiator_address(),
+                send_amount: Uint128(milestones.len() as u128),
+                description: description.clone(),
+                token: mock_silk(),
+                skip_confirmation_if_verified: None,
+                referrer: None,
+                cancellation_policy: None,
+                cancellation_penalty: None,
+                hash_timelock: None,
+                milestones: Some(milestones),
+                attachment_hash: None,
+            })
+            .unwrap(),
+        };
+        let milestone = |label: String| Milestone {
+            label,
+            amount: Uint128(1),
+            deadline: None,
+            status: MilestoneStatus::Pending,
+        };
+        // == when a milestone label is too long
+        // == * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            milestones_msg(vec![milestone("a".repeat(257))]),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Milestone label must be at most 256 bytes.".to_string(),
+                backtrace: None
+            }
+        );
+        // == when there are too many milestones
+        // == * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            milestones_msg(vec![milestone("Design".to_string()); MAX_MILESTONES + 1]),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Txs can have at most 5 milestones.".to_string(),
+                backtrace: None
+            }
+        );
+        // == when the milestone labels fit and there are at most five milestones
+        // == * it creates the Txs
+        handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            milestones_msg(vec![milestone("a".repeat(256)); MAX_MILESTONES]),
+        )
+        .unwrap();
+
+        // == when description is too long
+        // == * it raises an error
+        let receive_msg = ReceiveMsg::CreateSendRequest {
+            address: mock_contract_initiator_address(),
+            send_amount: send_amount,
+            description: Some("a".repeat(257)),
+            token: mock_silk(),
+            skip_confirmation_if_verified: None,
+            referrer: None,
+            cancellation_policy: None,
+            cancellation_penalty: None,
+            hash_timelock: None,
+            milestones: None,
+            attachment_hash: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: mock_fee(),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Description must be at most 256 bytes.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // == when the token code hash is too long
+        // == * it raises an error
+        let receive_msg = ReceiveMsg::CreateSendRequest {
+            address: mock_contract_initiator_address(),
+            send_amount: send_amount,
+            description: description.clone(),
+            token: SecretContract {
+                address: mock_silk().address,
+                contract_hash: "a".repeat(65),
+            },
+            skip_confirmation_if_verified: None,
+            referrer: None,
+            cancellation_policy: None,
+            cancellation_penalty: None,
+            hash_timelock: None,
+            milestones: None,
+            attachment_hash: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: mock_fee(),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Token address and code hash must be at most 64 bytes.".to_string(),
+                backtrace: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_create_send_requests() {
+        let (_init_result, mut deps) = init_helper();
+        let request = SendRequest {
+            address: mock_contract_initiator_address(),
+            send_amount: Uint128(555_555),
+            description: Some("Salary".to_string()),
+            token: mock_silk(),
+            skip_confirmation_if_verified: None,
+            referrer: None,
+            cancellation_policy: None,
+            cancellation_penalty: None,
+            hash_timelock: None,
+            milestones: None,
+            attachment_hash: None,
+        };
+
+        // when no requests are sent in
+        // * it raises an error
+        let receive_msg = ReceiveMsg::CreateSendRequests { requests: vec![] };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(0),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Requests must not be empty.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when fee for a single request is sent in for multiple requests
+        // * it raises an error
+        let receive_msg = ReceiveMsg::CreateSendRequests {
+            requests: vec![request.clone(), request.clone()],
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: mock_fee(),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Wrong amount received.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when fee for every request is sent in
+        // = when a request has a send amount of zero
+        // = * it raises an error
+        let mut zero_request = request.clone();
+        zero_request.send_amount = Uint128(0);
+        let receive_msg = ReceiveMsg::CreateSendRequests {
+            requests: vec![zero_request],
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: mock_fee(),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Send amount must be greater than zero.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // = when every request is valid
+        let mut second_request = request.clone();
+        second_request.send_amount = Uint128(1);
+        let receive_msg = ReceiveMsg::CreateSendRequests {
+            requests: vec![request, second_request],
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(mock_fee().u128() * 2),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result_unwrapped =
+            handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg).unwrap();
+        // = * it registers the token once
+        assert_eq!(
+            handle_result_unwrapped.messages,
+            vec![snip20::register_receive_msg(
+                mock_env(mock_sscrt().address, &[]).contract_code_hash,
+                None,
+                BLOCK_SIZE,
+                mock_silk().contract_hash,
+                mock_silk().address,
+            )
+            .unwrap()]
+        );
+        // = * it returns the positions of the created Txs
+        let handle_answer: HandleAnswer =
+            from_binary(&handle_result_unwrapped.data.unwrap()).unwrap();
+        let positions = match handle_answer {
+            HandleAnswer::CreateSendRequests { positions } => positions,
+            _ => panic!("Unexpected handle answer"),
+        };
+        assert_eq!(positions, vec![0, 1]);
+        // = * it creates the txs
+        for (position, send_amount) in [(0, Uint128(555_555)), (1, Uint128(1))] {
+            let from_tx = tx_at_position(
+                &mut deps.storage,
+                &deps.api.canonical_address(&mock_user_address()).unwrap(),
+                position,
+            )
+            .unwrap();
+            assert_eq!(from_tx.amount, send_amount);
+            assert_eq!(from_tx.fee, mock_fee());
+            assert_eq!(from_tx.status, 0);
+        }
+    }
+
+    #[test]
+    fn test_decline() {
+        let (_init_result, mut deps) = init_helper();
+        create_acknowledged_request_helper(&mut deps);
+        let admin_canonical = deps
+            .api
+            .canonical_address(&mock_contract_initiator_address())
+            .unwrap();
+        let handle_msg = HandleMsg::Decline {
+            position: 0,
+            reason: Some("Phishing".to_string()),
+        };
+
+        // when user is the creator of the receive request
+        // * it raises an unauthorized error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // when user is the sender of the receive request
+        // = when the reason is too long
+        // = * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            HandleMsg::Decline {
+                position: 0,
+                reason: Some("a".repeat(257)),
+            },
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Reason must be at most 256 bytes.".to_string(),
+                backtrace: None
+            }
+        );
+        // = when the reason fits
+        handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            handle_msg.clone(),
+        )
+        .unwrap();
+        // = * it updates the txs to declined with the reason
+        let from_tx = tx_at_position(&mut deps.storage, &admin_canonical, 0).unwrap();
+        let to_tx = tx_at_position(
+            &mut deps.storage,
+            &from_tx.to,
+            from_tx.other_storage_position,
+        )
+        .unwrap();
+        assert_eq!(from_tx.status, 8);
+        assert_eq!(to_tx.status, 8);
+        assert_eq!(to_tx.cancellation_reason, Some("Phishing".to_string()));
+        // = * it accrues the fee for the treasury
+        assert_eq!(
+            fee_stats(&deps.storage, &mock_sscrt().address)
+                .unwrap()
+                .accrued,
+            mock_fee()
+        );
+
+        // when the Tx has already been declined
+        // * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            handle_msg,
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Tx status at that position is incorrect.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when the request has been acknowledged
+        // * it raises an error, so that the cancellation policy and penalty can not be skipped
+        let receive_msg = ReceiveMsg::CreateReceiveRequest {
+            address: mock_contract_initiator_address(),
+            send_amount: Uint128(1_000),
+            description: Some("Invoice".to_string()),
+            token: mock_silk(),
+            referrer: None,
+            cancellation_policy: Some(CancellationPolicy::CreatorOnly),
+            cancellation_penalty: Some(Uint128(500)),
+            attachment_hash: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: mock_fee(),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg).unwrap();
+        handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            HandleMsg::AcknowledgeRequest { position: 1 },
+        )
+        .unwrap();
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            HandleMsg::Decline {
+                position: 1,
+                reason: None,
+            },
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Tx status at that position is incorrect.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when the request is a send request
+        // * it raises an error
+        create_send_requests_helper(&mut deps, vec![Uint128(1_000)]);
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            HandleMsg::Decline {
+                position: 2,
+                reason: None,
+            },
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Tx is not a receive request.".to_string(),
+                backtrace: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_fund_claim() {
+        let (_init_result, mut deps) = init_helper();
+        create_claim_helper(&mut deps, false);
+        let fund_msg = |from: HumanAddr, amount: u128| HandleMsg::Receive {
+            sender: from.clone(),
+            from: from,
+            amount: Uint128(amount),
+            msg: to_binary(&ReceiveMsg::FundClaim {
+                code_hash: Binary(hash_code("open-sesame")),
+            })
+            .unwrap(),
+        };
+
+        // when user is not the sender
+        // * it raises an unauthorized error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_silk().address, &[]),
+            fund_msg(mock_contract_initiator_address(), 1_000),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // when user is the sender
+        // = when user sends in the wrong amount
+        // = * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_silk().address, &[]),
+            fund_msg(mock_user_address(), 999),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Wrong amount received.".to_string(),
+                backtrace: None
+            }
+        );
+        // = when user sends in the right amount and token
+        // = * it marks the claim as funded
+        handle(
+            &mut deps,
+            mock_env(mock_silk().address, &[]),
+            fund_msg(mock_user_address(), 1_000),
+        )
+        .unwrap();
+        assert!(
+            claim_by_code(&deps.storage, "open-sesame")
+                .unwrap()
+                .unwrap()
+                .funded
+        );
+        // = when claim is already funded
+        // = * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_silk().address, &[]),
+            fund_msg(mock_user_address(), 1_000),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Claim already funded.".to_string(),
+                backtrace: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_grant_viewer() {
+        let (_init_result, mut deps) = init_helper();
+        let user_canonical = deps.api.canonical_address(&mock_user_address()).unwrap();
+        let viewer_canonical = deps
+            .api
+            .canonical_address(&mock_contract_initiator_address())
+            .unwrap();
+        let env = mock_env(mock_user_address(), &[]);
+        let grant_msg = |address: HumanAddr| HandleMsg::GrantViewer {
+            address,
+            scope: ViewerScope {
+                tokens: Some(vec![mock_silk().address]),
+                ..ViewerScope::default()
+            },
+            expires_at: Some(env.block.time + 10),
+        };
+
+        // when the user grants themselves
+        // * it raises an error
+        let handle_result = handle(&mut deps, env.clone(), grant_msg(mock_user_address()));
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Can not grant a viewer to yourself.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when the user grants another address
+        handle(
+            &mut deps,
+            env.clone(),
+            grant_msg(mock_contract_initiator_address()),
+        )
+        .unwrap();
+        // * the viewer can see the Txs within the scope
+        assert_eq!(
+            verify_viewer(
+                &deps,
+                &user_canonical,
+                &viewer_canonical,
+                Some(env.block.time)
+            )
+            .unwrap(),
+            ViewerScope {
+                tokens: Some(vec![mock_silk().address]),
+                ..ViewerScope::default()
+            }
+        );
+        // * nobody else can
+        assert_eq!(
+            verify_viewer(
+                &deps,
+                &viewer_canonical,
+                &user_canonical,
+                Some(env.block.time)
+            )
+            .unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // when the viewer does not send the time
+        // * the viewer can not see the Txs
+        assert_eq!(
+            verify_viewer(&deps, &user_canonical, &viewer_canonical, None).unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // when the grant has expired
+        // * the viewer can no longer see the Txs
+        assert_eq!(
+            verify_viewer(
+                &deps,
+                &user_canonical,
+                &viewer_canonical,
+                Some(env.block.time + 10)
+            )
+            .unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+    }
+
+    #[test]
+    fn test_nominate_new_admin() {
+        let (_init_result, mut deps) = init_helper();
+        let env = mock_env(mock_user_address(), &[]);
+
+        // when called by non-admin
+        // * it raises an unauthorized error
+        let handle_msg = HandleMsg::NominateNewAdmin {
+            address: mock_user_address(),
+        };
+        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // when admin calls this
+        let env = mock_env(mock_contract_initiator_address(), &[]);
+        let handle_result = handle(&mut deps, env, handle_msg);
+        handle_result.unwrap();
+        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        assert_eq!(config.new_admin_nomination, Some(mock_user_address()))
+    }
+
+    #[test]
+    fn test_on_behalf_of() {
+        let (_init_result, mut deps) = init_helper();
+        let organization = create_organization_helper(&mut deps);
+        let organization_canonical = deps.api.canonical_address(&organization).unwrap();
+        let receive_msg = ReceiveMsg::CreateReceiveRequest {
+            address: organization.clone(),
+            send_amount: Uint128(1_000),
+            description: Some("Invoice".to_string()),
+            token: mock_silk(),
+            referrer: None,
+            cancellation_policy: None,
+            cancellation_penalty: None,
+            attachment_hash: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: mock_fee(),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg).unwrap();
+        let payment_msg = |from: HumanAddr, msg: ReceiveMsg| HandleMsg::Receive {
+            sender: from.clone(),
+            from,
+            amount: Uint128(1_000),
+            msg: to_binary(&ReceiveMsg::OnBehalfOf {
+                organization: organization.clone(),
+                msg: Box::new(msg),
+            })
+            .unwrap(),
+        };
+
+        // when the sender is not a member
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_silk().address, &[]),
+            payment_msg(
+                mock_contract_initiator_address(),
+                ReceiveMsg::SendPayment { position: 0 },
+            ),
+        );
+        // * it raises an unauthorized error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // when the action is not available to organizations
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_silk().address, &[]),
+            payment_msg(
+                HumanAddr::from("alice"),
+                ReceiveMsg::PayLink {
+                    code: "open-sesame".to_string(),
+                },
+            ),
+        );
+        // * it raises an error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Action is not available to organizations.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when a payer pays on behalf of the organization
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_silk().address, &[]),
+            payment_msg(
+                HumanAddr::from("alice"),
+                ReceiveMsg::SendPayment { position: 0 },
+            ),
+        );
+        // * it pays the Tx from the organization's history
+        let handle_result_unwrapped = handle_result.unwrap();
+        assert_eq!(
+            handle_result_unwrapped.messages.last().unwrap(),
+            &snip20::transfer_msg(
+                mock_contract_initiator_address(),
+                Uint128(1_000),
+                None,
+                BLOCK_SIZE,
+                mock_silk().contract_hash,
+                mock_silk().address,
+            )
+            .unwrap()
+        );
+        let tx = tx_at_position(&mut deps.storage, &organization_canonical, 0).unwrap();
+        assert_eq!(tx.from, organization_canonical);
+        assert_eq!(tx.status, 3);
+        // * it records the payment in the audit trail
+        let (entries, total) =
+            get_audit_trail(&deps.storage, &organization_canonical, 0, 10).unwrap();
+        assert_eq!(total, 3);
+        assert_eq!(entries[0].member, HumanAddr::from("alice"));
+        assert_eq!(
+            entries[0].action,
+            OrganizationAction::Receive {
+                amount: Uint128(1_000),
+                msg: ReceiveMsg::SendPayment { position: 0 },
+            }
+        );
+
+        // when a member handles a message on behalf of the organization
+        let handle_msg = HandleMsg::OnBehalfOf {
+            organization: organization.clone(),
+            msg: Box::new(HandleMsg::SetMemo {
+                position: 0,
+                memo: Some("Q3 hosting".to_string()),
+            }),
+        };
+        handle(
+            &mut deps,
+            mock_env(HumanAddr::from("alice"), &[]),
+            handle_msg,
+        )
+        .unwrap();
+        // * it acts as the organization
+        let tx = tx_at_position(&mut deps.storage, &organization_canonical, 0).unwrap();
+        assert_eq!(tx.memo, Some("Q3 hosting".to_string()));
+        let (_entries, total) =
+            get_audit_trail(&deps.storage, &organization_canonical, 0, 10).unwrap();
+        assert_eq!(total, 4);
+    }
+
+    #[test]
+    fn test_pay_link() {
+        let (_init_result, mut deps) = init_helper();
+        let pay_msg = |amount: u128| HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(amount),
+            msg: to_binary(&ReceiveMsg::PayLink {
+                code: "open-sesame".to_string(),
+            })
+            .unwrap(),
+        };
+
+        // when payment link does not exist
+        // * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_silk().address, &[]),
+            pay_msg(1_000),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Payment link not found.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when payment link exists
+        create_payment_link_helper(&mut deps, 2);
+        // = when user sends in the wrong amount
+        // = * it raises an error
+        let handle_result = handle(&mut deps, mock_env(mock_silk().address, &[]), pay_msg(999));
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Wrong amount received.".to_string(),
+                backtrace: None
+            }
+        );
+        // = when user sends in the right amount and token
+        let handle_result_unwrapped = handle(
+            &mut deps,
+            mock_env(mock_silk().address, &[]),
+            pay_msg(1_000),
+        )
+        .unwrap();
+        // = * it forwards the payment to the receiver
+        assert_eq!(
+            handle_result_unwrapped.messages,
+            vec![snip20::transfer_msg(
+                mock_contract_initiator_address(),
+                Uint128(1_000),
+                None,
+                BLOCK_SIZE,
+                mock_silk().contract_hash,
+                mock_silk().address,
+            )
+            .unwrap()]
+        );
+        // = * it stores a finalized Tx with the user as the payer
+        let user_canonical = deps.api.canonical_address(&mock_user_address()).unwrap();
+        let from_tx = tx_at_position(&mut deps.storage, &user_canonical, 0).unwrap();
+        assert_eq!(from_tx.from, user_canonical);
+        assert_eq!(from_tx.creator, mock_contract_initiator_address());
+        assert_eq!(from_tx.amount, Uint128(1_000));
+        assert_eq!(from_tx.status, 3);
+        // = * it accrues the fee for the treasury
+        assert_eq!(
+            fee_stats(&deps.storage, &mock_sscrt().address)
+                .unwrap()
+                .accrued,
+            mock_fee()
+        );
+        // = * it counts the use
+        assert_eq!(
+            payment_link_by_code(&deps.storage, "open-sesame")
+                .unwrap()
+                .unwrap()
+                .uses,
+            1
+        );
+        // = when the last use is paid
+        // = * it removes the payment link
+        handle(
+            &mut deps,
+            mock_env(mock_silk().address, &[]),
+            pay_msg(1_000),
+        )
+        .unwrap();
+        assert_eq!(
+            payment_link_by_code(&deps.storage, "open-sesame").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_prune_history() {
+        let (_init_result, mut deps) = init_helper();
+        create_send_requests_helper(
+            &mut deps,
+            vec![Uint128(1_000), Uint128(1_000), Uint128(1_000)],
+        );
+        let user_canonical = deps.api.canonical_address(&mock_user_address()).unwrap();
+        let admin_canonical = deps
+            .api
+            .canonical_address(&mock_contract_initiator_address())
+            .unwrap();
+        let confirm_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(0),
+            msg: to_binary(&ReceiveMsg::ConfirmAddress { position: 0 }).unwrap(),
+        };
+        handle(&mut deps, mock_env(mock_sscrt().address, &[]), confirm_msg).unwrap();
+        let payment_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(1_000),
+            msg: to_binary(&ReceiveMsg::SendPayment { position: 0 }).unwrap(),
+        };
+        handle(&mut deps, mock_env(mock_silk().address, &[]), payment_msg).unwrap();
+        let cancel_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(0),
+            msg: to_binary(&ReceiveMsg::Cancel {
+                position: 1,
+                reason: None,
+            })
+            .unwrap(),
+        };
+        handle(&mut deps, mock_env(mock_sscrt().address, &[]), cancel_msg).unwrap();
+        let pruned = |handle_result: HandleResponse| match from_binary(&handle_result.data.unwrap())
+            .unwrap()
+        {
+            HandleAnswer::PruneHistory { pruned } => pruned,
+            _ => panic!("Unexpected handle answer"),
+        };
+
+        // when neither before_position nor before_time is set
+        // * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            HandleMsg::PruneHistory {
+                before_position: None,
+                before_time: None,
+            },
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Either before_position or before_time must be set.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when before_time is set
+        // * it only prunes Txs created before then
+        let handle_result_unwrapped = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            HandleMsg::PruneHistory {
+                before_position: None,
+                before_time: Some(mock_env(mock_user_address(), &[]).block.time),
+            },
+        )
+        .unwrap();
+        assert_eq!(pruned(handle_result_unwrapped), 0);
+
+        // when before_position is set
+        let handle_result_unwrapped = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            HandleMsg::PruneHistory {
+                before_position: Some(3),
+                before_time: None,
+            },
+        )
+        .unwrap();
+        // * it prunes the finalized and cancelled Txs before the position
+        assert_eq!(pruned(handle_result_unwrapped), 2);
+        for position in [0, 1] {
+            let tx = tx_at_position(&mut deps.storage, &user_canonical, position).unwrap();
+            assert_eq!(tx.status, 9);
+            assert_eq!(tx.to, user_canonical);
+            assert_eq!(tx.amount, Uint128(0));
+            assert_eq!(tx.description, None);
+            assert_eq!(tx.other_storage_position, position);
+        }
+        // * it does not prune pending Txs
+        let tx = tx_at_position(&mut deps.storage, &user_canonical, 2).unwrap();
+        assert_eq!(tx.status, 0);
+        // * it does not touch the counterparty's copies
+        let tx = tx_at_position(&mut deps.storage, &admin_canonical, 0).unwrap();
+        assert_eq!(tx.status, 3);
+        assert_eq!(tx.amount, Uint128(1_000));
+        // * the counterparty can still refund their copy
+        let refund_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(400),
+            msg: to_binary(&ReceiveMsg::Refund { position: 0 }).unwrap(),
+        };
+        let handle_result_unwrapped =
+            handle(&mut deps, mock_env(mock_silk().address, &[]), refund_msg).unwrap();
+        assert_eq!(
+            handle_result_unwrapped.messages,
+            vec![snip20::transfer_msg(
+                mock_user_address(),
+                Uint128(400),
+                None,
+                BLOCK_SIZE,
+                mock_silk().contract_hash,
+                mock_silk().address,
+            )
+            .unwrap()]
+        );
+        let tx = tx_at_position(&mut deps.storage, &admin_canonical, 0).unwrap();
+        assert_eq!(tx.status, 5);
+        let tx = tx_at_position(&mut deps.storage, &user_canonical, 0).unwrap();
+        assert_eq!(tx.status, 9);
+    }
+
+    #[test]
+    fn test_reclaim() {
+        let (_init_result, mut deps) = init_helper();
+        create_claim_helper(&mut deps, true);
+        let handle_msg = HandleMsg::Reclaim {
+            code_hash: Binary(hash_code("open-sesame")),
+        };
+
+        // when user is not the sender
+        // * it raises an unauthorized error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // when user is the sender
+        // = when funded claim has not expired
+        // = * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Claim has not expired yet.".to_string(),
+                backtrace: None
+            }
+        );
+        // = when funded claim has expired
+        let mut env = mock_env(mock_user_address(), &[]);
+        env.block.time = mock_expires_at();
+        let handle_result_unwrapped = handle(&mut deps, env, handle_msg).unwrap();
+        // = * it returns the fee and the funds to the sender
+        assert_eq!(
+            handle_result_unwrapped.messages,
+            vec![
+                snip20::transfer_msg(
+                    mock_user_address(),
+                    mock_fee(),
+                    None,
+                    BLOCK_SIZE,
+                    mock_sscrt().contract_hash,
+                    mock_sscrt().address,
+                )
+                .unwrap(),
+                snip20::transfer_msg(
+                    mock_user_address(),
+                    Uint128(1_000),
+                    None,
+                    BLOCK_SIZE,
+                    mock_silk().contract_hash,
+                    mock_silk().address,
+                )
+                .unwrap()
+            ]
+        );
+        // = * it removes the claim
+        assert_eq!(claim_by_code(&deps.storage, "open-sesame").unwrap(), None);
+    }
+
+    #[test]
+    fn test_refund() {
+        let (_init_result, mut deps) = init_helper();
+        create_send_requests_helper(&mut deps, vec![Uint128(1_000)]);
+        let confirm_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(0),
+            msg: to_binary(&ReceiveMsg::ConfirmAddress { position: 0 }).unwrap(),
+        };
+        handle(&mut deps, mock_env(mock_sscrt().address, &[]), confirm_msg).unwrap();
+        let refund_msg = |from: HumanAddr, amount: u128, position: u32| HandleMsg::Receive {
+            sender: from.clone(),
+            from: from,
+            amount: Uint128(amount),
+            msg: to_binary(&ReceiveMsg::Refund { position }).unwrap(),
+        };
+
+        // when Tx is not finalized
+        // * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_silk().address, &[]),
+            refund_msg(mock_contract_initiator_address(), 1_000, 0),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Only finalized Txs can be refunded.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when Tx is finalized
+        let payment_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(1_000),
+            msg: to_binary(&ReceiveMsg::SendPayment { position: 0 }).unwrap(),
+        };
+        handle(&mut deps, mock_env(mock_silk().address, &[]), payment_msg).unwrap();
+        // = when user is not the receiver
+        // = * it raises an unauthorized error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_silk().address, &[]),
+            refund_msg(mock_user_address(), 1_000, 0),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+        // = when user is the receiver
+        // == when user sends in the wrong token
+        // == * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            refund_msg(mock_contract_initiator_address(), 1_000, 0),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Wrong token received.".to_string(),
+                backtrace: None
+            }
+        );
+        // == when user sends in more than the amount of the Tx
+        // == * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_silk().address, &[]),
+            refund_msg(mock_contract_initiator_address(), 1_001, 0),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Refund must be greater than zero and at most the amount not yet refunded."
+                    .to_string(),
+                backtrace: None
+            }
+        );
+        // == when user sends back part of the amount
+        let handle_result_unwrapped = handle(
+            &mut deps,
+            mock_env(mock_silk().address, &[]),
+            refund_msg(mock_contract_initiator_address(), 400, 0),
+        )
+        .unwrap();
+        // == * it sends the refund to the sender
+        assert_eq!(
+            handle_result_unwrapped.messages,
+            vec![snip20::transfer_msg(
+                mock_user_address(),
+                Uint128(400),
+                None,
+                BLOCK_SIZE,
+                mock_silk().contract_hash,
+                mock_silk().address,
+            )
+            .unwrap()]
+        );
+        // == * it updates the Tx and counter Tx to partially refunded
+        let user_canonical = deps.api.canonical_address(&mock_user_address()).unwrap();
+        let admin_canonical = deps
+            .api
+            .canonical_address(&mock_contract_initiator_address())
+            .unwrap();
+        let from_tx = tx_at_position(&mut deps.storage, &user_canonical, 0).unwrap();
+        let to_tx = tx_at_position(&mut deps.storage, &admin_canonical, 0).unwrap();
+        assert_eq!(from_tx.status, 5);
+        assert_eq!(to_tx.status, 5);
+        assert_eq!(from_tx.refunded, Uint128(400));
+        assert_eq!(to_tx.refunded, Uint128(400));
+        // == * it stores a finalized refund Tx linked to the original in both histories
+        let refund_from_tx = tx_at_position(&mut deps.storage, &admin_canonical, 1).unwrap();
+        let refund_to_tx = tx_at_position(&mut deps.storage, &user_canonical, 1).unwrap();
+        assert_eq!(refund_from_tx.from, admin_canonical);
+        assert_eq!(refund_from_tx.to, user_canonical);
+        assert_eq!(refund_from_tx.amount, Uint128(400));
+        assert_eq!(refund_from_tx.fee, Uint128(0));
+        assert_eq!(refund_from_tx.status, 3);
+        assert_eq!(refund_from_tx.refund_of, Some(0));
+        assert_eq!(refund_to_tx.refund_of, Some(0));
+        // == when user sends back the rest of the amount
+        // == * it updates the Tx and counter Tx to refunded
+        handle(
+            &mut deps,
+            mock_env(mock_silk().address, &[]),
+            refund_msg(mock_contract_initiator_address(), 600, 0),
+        )
+        .unwrap();
+        let from_tx = tx_at_position(&mut deps.storage, &user_canonical, 0).unwrap();
+        let to_tx = tx_at_position(&mut deps.storage, &admin_canonical, 0).unwrap();
+        assert_eq!(from_tx.status, 4);
+        assert_eq!(to_tx.status, 4);
+        // == when Tx is already refunded
+        // == * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_silk().address, &[]),
+            refund_msg(mock_contract_initiator_address(), 1, 0),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Only finalized Txs can be refunded.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when Tx is a refund
+        // * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_silk().address, &[]),
+            refund_msg(mock_user_address(), 400, 1),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Refunds can not be refunded.".to_string(),
+                backtrace: None
+            }
+        );
     }
 
-    // === INIT TEST ===
     #[test]
-    fn test_init() {
-        // * it registers receive for sscrt and shade
-        let (init_result, deps) = init_helper();
+    fn test_refund_htlc() {
+        let (_init_result, mut deps) = init_helper();
+        create_htlc_helper(&mut deps);
+        let handle_msg = HandleMsg::RefundHtlc { position: 0 };
+
+        // when payment is escrowed
+        // * it can not be cancelled
+        let cancel_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(0),
+            msg: to_binary(&ReceiveMsg::Cancel {
+                position: 0,
+                reason: None,
+            })
+            .unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), cancel_msg);
         assert_eq!(
-            init_result.unwrap().messages,
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Escrowed Txs can not be cancelled.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when user is not the sender
+        // * it raises an unauthorized error
+        let mut env = mock_env(mock_contract_initiator_address(), &[]);
+        env.block.time = mock_expires_at();
+        let handle_result = handle(&mut deps, env, handle_msg.clone());
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // when user is the sender
+        // = when timelock has not passed
+        // = * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Timelock has not passed yet.".to_string(),
+                backtrace: None
+            }
+        );
+        // = when timelock has passed
+        let mut env = mock_env(mock_user_address(), &[]);
+        env.block.time = mock_expires_at();
+        let handle_result_unwrapped = handle(&mut deps, env, handle_msg).unwrap();
+        // = * it returns the fee and the payment to the sender
+        assert_eq!(
+            handle_result_unwrapped.messages,
             vec![
-                snip20::register_receive_msg(
-                    mock_env(mock_contract_initiator_address(), &[]).contract_code_hash,
+                snip20::transfer_msg(
+                    mock_user_address(),
+                    mock_fee(),
                     None,
                     BLOCK_SIZE,
                     mock_sscrt().contract_hash,
                     mock_sscrt().address,
                 )
                 .unwrap(),
-                snip20::register_receive_msg(
-                    mock_env(mock_contract_initiator_address(), &[]).contract_code_hash,
+                snip20::transfer_msg(
+                    mock_user_address(),
+                    Uint128(1_000),
                     None,
                     BLOCK_SIZE,
-                    mock_shade().contract_hash,
-                    mock_shade().address,
+                    mock_silk().contract_hash,
+                    mock_silk().address,
                 )
-                .unwrap(),
-            ],
+                .unwrap()
+            ]
         );
+        // = * it updates the Txs to cancelled
+        let from_tx = tx_at_position(
+            &mut deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+            0,
+        )
+        .unwrap();
+        assert_eq!(from_tx.status, 2);
+    }
 
-        // * it sets the correct Config
-        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+    #[test]
+    fn test_reject_payment() {
+        let (_init_result, mut deps) = init_helper();
+        let organization = create_approval_helper(&mut deps);
+        let organization_canonical = deps.api.canonical_address(&organization).unwrap();
+        let approve_msg = HandleMsg::ApprovePayment {
+            organization: organization.clone(),
+            position: 0,
+        };
+        handle(
+            &mut deps,
+            mock_env(HumanAddr::from("bob"), &[]),
+            approve_msg,
+        )
+        .unwrap();
+
+        // when an approver rejects the payment
+        let reject_msg = HandleMsg::RejectPayment {
+            organization: organization.clone(),
+            position: 0,
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_user_address(), &[]), reject_msg);
+        // * it returns the payment to the member who paid it
         assert_eq!(
-            config,
-            Config {
-                admin: mock_contract_initiator_address(),
-                fee: mock_fee(),
-                new_admin_nomination: None,
-                shade: mock_shade(),
-                sscrt: mock_sscrt(),
-                treasury_address: mock_treasury_address(),
-            }
+            handle_result.unwrap().messages,
+            vec![snip20::transfer_msg(
+                HumanAddr::from("alice"),
+                Uint128(1_000),
+                None,
+                BLOCK_SIZE,
+                mock_silk().contract_hash,
+                mock_silk().address,
+            )
+            .unwrap()]
         );
-
-        // * it stores the token => contract_hash in storage
-        let mut registered_tokens_storage =
-            RegisteredTokensReadonlyStorage::from_storage(&deps.storage);
+        // * the Tx can be paid again
+        let tx = tx_at_position(&mut deps.storage, &organization_canonical, 0).unwrap();
+        assert_eq!(tx.status, 1);
+        // * it clears the approvals
         assert_eq!(
-            registered_tokens_storage
-                .get_contract_hash(mock_sscrt().address)
-                .unwrap(),
-            mock_sscrt().contract_hash,
+            payment_approvals(&deps.storage, &organization_canonical, 0).unwrap(),
+            vec![]
         );
         assert_eq!(
-            registered_tokens_storage
-                .get_contract_hash(mock_shade().address)
-                .unwrap(),
-            mock_shade().contract_hash,
+            escrowed_payment(&deps.storage, &organization_canonical, 0).unwrap(),
+            None
+        );
+
+        // when the organization has spending limits
+        set_spending_limit_settings(
+            &mut deps.storage,
+            &organization_canonical,
+            &SpendingLimitSettings {
+                limits: SpendingLimits {
+                    token_limits: vec![TokenSpendingLimit {
+                        token: mock_silk().address,
+                        daily_maximum: Some(Uint128(1_500)),
+                        weekly_maximum: None,
+                        counterparty_daily_maximum: None,
+                    }],
+                    cooling_off_period: None,
+                },
+                pending: None,
+            },
+        )
+        .unwrap();
+        let payment_msg = HandleMsg::Receive {
+            sender: HumanAddr::from("alice"),
+            from: HumanAddr::from("alice"),
+            amount: Uint128(1_000),
+            msg: to_binary(&ReceiveMsg::OnBehalfOf {
+                organization: organization.clone(),
+                msg: Box::new(ReceiveMsg::SendPayment { position: 0 }),
+            })
+            .unwrap(),
+        };
+        handle(
+            &mut deps,
+            mock_env(mock_silk().address, &[]),
+            payment_msg.clone(),
         )
+        .unwrap();
+        let reject_msg = HandleMsg::RejectPayment {
+            organization: organization.clone(),
+            position: 0,
+        };
+        handle(&mut deps, mock_env(mock_user_address(), &[]), reject_msg).unwrap();
+        // * the rejected payment no longer counts towards them
+        handle(&mut deps, mock_env(mock_silk().address, &[]), payment_msg).unwrap();
+        let tx = tx_at_position(&mut deps.storage, &organization_canonical, 0).unwrap();
+        assert_eq!(tx.status, 6);
     }
 
-    // === QUERY TESTS ===
     #[test]
-    fn test_query_config() {
-        let (_init_result, deps) = init_helper();
-        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
-        let query_result = query(&deps, QueryMsg::Config {}).unwrap();
-        let query_answer_config: Config = from_binary(&query_result).unwrap();
-        assert_eq!(query_answer_config, config);
+    fn test_release_milestone() {
+        let (_init_result, mut deps) = init_helper();
+        create_milestones_helper(&mut deps, CancellationPolicy::EitherParty);
+        let user_canonical = deps.api.canonical_address(&mock_user_address()).unwrap();
+        let admin_canonical = deps
+            .api
+            .canonical_address(&mock_contract_initiator_address())
+            .unwrap();
+        let handle_msg = HandleMsg::ReleaseMilestone {
+            position: 0,
+            index: 0,
+        };
+
+        // when payment is sent for a Tx with milestones
+        // * it escrows the payment
+        let from_tx = tx_at_position(&mut deps.storage, &user_canonical, 0).unwrap();
+        assert_eq!(from_tx.status, 6);
+
+        // when user is not the sender
+        // * it raises an unauthorized error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // when user is the sender
+        // = when milestone does not exist
+        // = * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            HandleMsg::ReleaseMilestone {
+                position: 0,
+                index: 2,
+            },
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Milestone not found.".to_string(),
+                backtrace: None
+            }
+        );
+        // = when milestone is pending
+        let handle_result_unwrapped = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            handle_msg.clone(),
+        )
+        .unwrap();
+        // = * it sends the amount of the milestone to the receiver
+        assert_eq!(
+            handle_result_unwrapped.messages,
+            vec![snip20::transfer_msg(
+                mock_contract_initiator_address(),
+                Uint128(400),
+                None,
+                BLOCK_SIZE,
+                mock_silk().contract_hash,
+                mock_silk().address,
+            )
+            .unwrap()]
+        );
+        // = * it stores a release linked to the milestone Tx for both users
+        let from_tx = tx_at_position(&mut deps.storage, &user_canonical, 1).unwrap();
+        let to_tx = tx_at_position(&mut deps.storage, &admin_canonical, 1).unwrap();
+        assert_eq!(from_tx.amount, Uint128(400));
+        assert_eq!(from_tx.description, Some("Design".to_string()));
+        assert_eq!(from_tx.status, 3);
+        assert_eq!(from_tx.milestone_of, Some(0));
+        assert_eq!(to_tx.milestone_of, Some(0));
+        // = * it keeps the milestone Tx escrowed until all milestones are settled
+        let from_tx = tx_at_position(&mut deps.storage, &user_canonical, 0).unwrap();
+        assert_eq!(from_tx.status, 6);
+        assert_eq!(
+            from_tx.milestones.unwrap()[0].status,
+            MilestoneStatus::Released
+        );
+        // = when milestone has already been released
+        // = * it raises an error
+        let handle_result = handle(&mut deps, mock_env(mock_user_address(), &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Milestone already settled.".to_string(),
+                backtrace: None
+            }
+        );
+        // = when the last milestone is released
+        handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            HandleMsg::ReleaseMilestone {
+                position: 0,
+                index: 1,
+            },
+        )
+        .unwrap();
+        // = * it finalizes the milestone Tx
+        let from_tx = tx_at_position(&mut deps.storage, &user_canonical, 0).unwrap();
+        let to_tx = tx_at_position(&mut deps.storage, &admin_canonical, 0).unwrap();
+        assert_eq!(from_tx.status, 3);
+        assert_eq!(to_tx.status, 3);
+        // = * it accrues the fee for the treasury
+        assert_eq!(
+            fee_stats(&deps.storage, &mock_sscrt().address)
+                .unwrap()
+                .accrued,
+            mock_fee()
+        );
     }
 
-    // === HANDLE TESTS ===
     #[test]
-    fn test_accept_new_admin_nomination() {
+    fn test_revoke_viewer() {
         let (_init_result, mut deps) = init_helper();
+        let user_canonical = deps.api.canonical_address(&mock_user_address()).unwrap();
+        let viewer_canonical = deps
+            .api
+            .canonical_address(&mock_contract_initiator_address())
+            .unwrap();
         let env = mock_env(mock_user_address(), &[]);
-
-        // when a new admin nomination does not exist
-        // * it raises an unauthorized error
-        let handle_msg = HandleMsg::AcceptNewAdminNomination {};
-        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
-        assert_eq!(
-            handle_result.unwrap_err(),
-            StdError::Unauthorized { backtrace: None }
-        );
-
-        // when a new admin nomination exists
-        let new_admin_nomination_msg = HandleMsg::NominateNewAdmin {
-            address: mock_user_address(),
+        let grant_msg = HandleMsg::GrantViewer {
+            address: mock_contract_initiator_address(),
+            scope: ViewerScope::default(),
+            expires_at: None,
         };
-        let env = mock_env(mock_contract_initiator_address(), &[]);
-        handle(&mut deps, env.clone(), new_admin_nomination_msg).unwrap();
+        handle(&mut deps, env.clone(), grant_msg).unwrap();
+        verify_viewer(&deps, &user_canonical, &viewer_canonical, None).unwrap();
 
-        // = when accepting of new admin nomination is called by the wrong person
-        // = * it raises an error
-        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        // when the user revokes the viewer
+        let revoke_msg = HandleMsg::RevokeViewer {
+            address: mock_contract_initiator_address(),
+        };
+        handle(&mut deps, env, revoke_msg).unwrap();
+        // * the viewer can no longer see the Txs
         assert_eq!(
-            handle_result.unwrap_err(),
+            verify_viewer(&deps, &user_canonical, &viewer_canonical, None).unwrap_err(),
             StdError::Unauthorized { backtrace: None }
         );
-
-        // = when accepting of new admin nomination is called by the nominated person
-        let env = mock_env(mock_user_address(), &[]);
-        handle(&mut deps, env, handle_msg).unwrap();
-        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
-        // = * it updates the admin
-        assert_eq!(config.admin, mock_user_address());
-        // = * it sets the new admin nomination to None
-        assert_eq!(config.new_admin_nomination, None);
     }
 
     #[test]
-    fn test_cancel() {
+    fn test_send_payment() {
         let (_init_result, mut deps) = init_helper();
         let env = mock_env(mock_user_address(), &[]);
         let description = Some("Mercy".to_string());
 
-        // when pending address confirmation
+        // when pending address tx exists
         let send_amount: Uint128 = Uint128(555_555);
         let receive_msg = ReceiveMsg::CreateSendRequest {
             address: mock_contract_initiator_address(),
             send_amount: send_amount,
             description: description.clone(),
             token: mock_silk(),
+            skip_confirmation_if_verified: None,
+            referrer: None,
+            cancellation_policy: None,
+            cancellation_penalty: None,
+            hash_timelock: None,
+            milestones: None,
+            attachment_hash: None,
         };
         let handle_msg = HandleMsg::Receive {
             sender: mock_user_address(),
@@ -719,12 +8715,33 @@ mod tests {
         };
         let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
         handle_result.unwrap();
-        // = when user sends in a positive amount
-        let receive_msg = ReceiveMsg::Cancel { position: 1 };
+
+        // = when user tries to send payment for tx that does not eist
+        let receive_msg = ReceiveMsg::SendPayment { position: 1 };
         let handle_msg = HandleMsg::Receive {
             sender: mock_user_address(),
             from: mock_user_address(),
-            amount: Uint128(5),
+            amount: mock_fee(),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        // = * it raises an error
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "AppendStorage access out of bounds".to_string(),
+                backtrace: None
+            }
+        );
+
+        // = when user tries to send payment for tx that exists
+        let receive_msg = ReceiveMsg::SendPayment { position: 0 };
+        // == when user sends in an amount that does not match the tx
+        // == * it raises an error
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(send_amount.u128() + 1),
             msg: to_binary(&receive_msg).unwrap(),
         };
         let handle_result = handle(&mut deps, env.clone(), handle_msg);
@@ -736,15 +8753,19 @@ mod tests {
                 backtrace: None
             }
         );
-        // = when user sends in a zero amount
+        // = when user sends in the correct amount
         let handle_msg = HandleMsg::Receive {
             sender: mock_user_address(),
             from: mock_user_address(),
-            amount: Uint128(0),
+            amount: send_amount,
             msg: to_binary(&receive_msg).unwrap(),
         };
-        // == when user sends in a non-sscrt token
-        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        // == when user sends in the wrong token
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            handle_msg.clone(),
+        );
         // == * it raises an error
         assert_eq!(
             handle_result.unwrap_err(),
@@ -753,157 +8774,460 @@ mod tests {
                 backtrace: None
             }
         );
-        // == when user sends in sscrt token
+        // == when user sends in the right token
+        let handle_result = handle(&mut deps, mock_env(mock_silk().address, &[]), handle_msg);
+        // ==== when user sends payment for Tx that is pending address confirmation
+        // ==== * it raises an error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Tx status at that position is incorrect.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // ==== when user sends payment for Tx that is cancelled
+        let mut from_tx = tx_at_position(
+            &mut deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+            0,
+        )
+        .unwrap();
+        let mut to_tx = tx_at_position(
+            &mut deps.storage,
+            &from_tx.to,
+            from_tx.other_storage_position,
+        )
+        .unwrap();
+        from_tx.status = 2;
+        to_tx.status = 2;
+        update_tx(&mut deps.storage, &from_tx.from.clone(), from_tx.clone()).unwrap();
+        update_tx(&mut deps.storage, &to_tx.to.clone(), to_tx.clone()).unwrap();
+        // ==== * it raises an error
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: send_amount,
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_silk().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Tx status at that position is incorrect.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // ==== when user sends payment for Tx that is already finalized
+        from_tx.status = 3;
+        to_tx.status = 3;
+        update_tx(&mut deps.storage, &from_tx.from.clone(), from_tx.clone()).unwrap();
+        update_tx(&mut deps.storage, &to_tx.to.clone(), to_tx.clone()).unwrap();
+        // ==== * it raises an error
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: send_amount,
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_silk().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Tx status at that position is incorrect.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // ==== when user sends payment for Tx that is pending payment
+        from_tx.status = 1;
+        to_tx.status = 1;
+        update_tx(&mut deps.storage, &from_tx.from.clone(), from_tx.clone()).unwrap();
+        update_tx(&mut deps.storage, &to_tx.to.clone(), to_tx).unwrap();
+        // ===== when user user is not the from for that Tx
+        // ===== * it raises an unauthorized error
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: send_amount,
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_silk().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+        // ===== when user is the the from for that Tx
+        // ===== * it sends payment to receiver
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: send_amount,
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result_unwrapped =
+            handle(&mut deps, mock_env(mock_silk().address, &[]), handle_msg).unwrap();
+        assert_eq!(
+            handle_result_unwrapped.messages,
+            vec![snip20::transfer_msg(
+                deps.api.human_address(&from_tx.to).unwrap(),
+                from_tx.amount,
+                None,
+                BLOCK_SIZE,
+                from_tx.token.contract_hash,
+                from_tx.token.address,
+            )
+            .unwrap()]
+        );
+        // ===== * it accrues the fee for the treasury
+        assert_eq!(
+            fee_stats(&deps.storage, &mock_sscrt().address)
+                .unwrap()
+                .accrued,
+            from_tx.fee
+        );
+        // ==== * it updates the status to finalized
+        let from_tx = tx_at_position(
+            &mut deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+            0,
+        )
+        .unwrap();
+        let to_tx = tx_at_position(
+            &mut deps.storage,
+            &from_tx.to,
+            from_tx.other_storage_position,
+        )
+        .unwrap();
+        assert_eq!(from_tx.status, 3);
+        assert_eq!(to_tx.status, 3);
+    }
+
+    #[test]
+    fn test_set_acceptance_policy() {
+        let (_init_result, mut deps) = init_helper();
+        let handle_msg = HandleMsg::SetAcceptancePolicy {
+            contacts_only: true,
+            minimum_amount: Some(Uint128(5)),
+            allowed_tokens: Some(vec![mock_silk().address]),
+            acknowledge_receive_requests: None,
+        };
+        handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            handle_msg,
+        )
+        .unwrap();
+        // * it stores the policy
+        assert_eq!(
+            acceptance_policy(
+                &deps.storage,
+                &deps
+                    .api
+                    .canonical_address(&mock_contract_initiator_address())
+                    .unwrap()
+            )
+            .unwrap(),
+            AcceptancePolicy {
+                contacts_only: true,
+                minimum_amount: Some(Uint128(5)),
+                allowed_tokens: Some(vec![mock_silk().address]),
+                acknowledge_receive_requests: false,
+            }
+        );
+
+        // when amount is below the counterparty's minimum
+        // * it raises an error
+        let receive_msg = ReceiveMsg::CreateSendRequest {
+            address: mock_contract_initiator_address(),
+            send_amount: Uint128(4),
+            description: None,
+            token: mock_silk(),
+            skip_confirmation_if_verified: None,
+            referrer: None,
+            cancellation_policy: None,
+            cancellation_penalty: None,
+            hash_timelock: None,
+            milestones: None,
+            attachment_hash: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: mock_fee(),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
         let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
-        // === when user tries to cancel a Tx that does not exist
-        // === * it raises an error
         assert_eq!(
             handle_result.unwrap_err(),
             StdError::GenericErr {
-                msg: "AppendStorage access out of bounds".to_string(),
+                msg: "Amount is below counterparty's minimum.".to_string(),
                 backtrace: None
             }
         );
-        // === when user tries to cancel Tx that exists
-        // ==== when user tries to cancel Tx that is pending address confirmation
-        let receive_msg = ReceiveMsg::Cancel { position: 0 };
+
+        // when token is not accepted by the counterparty
+        // * it raises an error
+        let receive_msg = ReceiveMsg::CreateSendRequest {
+            address: mock_contract_initiator_address(),
+            send_amount: Uint128(5),
+            description: None,
+            token: mock_shade(),
+            skip_confirmation_if_verified: None,
+            referrer: None,
+            cancellation_policy: None,
+            cancellation_penalty: None,
+            hash_timelock: None,
+            milestones: None,
+            attachment_hash: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: mock_fee(),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Token not accepted by counterparty.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when send request meets the policy
+        // * it creates the request
+        let receive_msg = ReceiveMsg::CreateSendRequest {
+            address: mock_contract_initiator_address(),
+            send_amount: Uint128(5),
+            description: None,
+            token: mock_silk(),
+            skip_confirmation_if_verified: None,
+            referrer: None,
+            cancellation_policy: None,
+            cancellation_penalty: None,
+            hash_timelock: None,
+            milestones: None,
+            attachment_hash: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: mock_fee(),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg).unwrap();
+
+        // when receive request is from somebody outside the counterparty's address book
+        // * it raises an error
+        let receive_msg = ReceiveMsg::CreateReceiveRequest {
+            address: mock_contract_initiator_address(),
+            send_amount: Uint128(5),
+            description: None,
+            token: mock_silk(),
+            referrer: None,
+            cancellation_policy: None,
+            cancellation_penalty: None,
+            attachment_hash: None,
+        };
         let handle_msg = HandleMsg::Receive {
             sender: mock_user_address(),
             from: mock_user_address(),
-            amount: Uint128(0),
+            amount: mock_fee(),
             msg: to_binary(&receive_msg).unwrap(),
         };
-        let handle_result_unwrapped = handle(
+        let handle_result = handle(
             &mut deps,
             mock_env(mock_sscrt().address, &[]),
             handle_msg.clone(),
-        )
-        .unwrap();
-        let mut from_tx = tx_at_position(
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Counterparty only accepts receive requests from contacts.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // when receive request is from somebody in the counterparty's address book
+        store_verified_address(
             &mut deps.storage,
+            &deps
+                .api
+                .canonical_address(&mock_contract_initiator_address())
+                .unwrap(),
             &deps.api.canonical_address(&mock_user_address()).unwrap(),
-            0,
-        )
-        .unwrap();
-        let mut to_tx = tx_at_position(
-            &mut deps.storage,
-            &from_tx.to,
-            from_tx.other_storage_position,
+            mock_user_address(),
+            5,
         )
         .unwrap();
-        // ==== * it sends the fee back to the creator
-        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
-        assert_eq!(
-            handle_result_unwrapped.messages,
-            vec![snip20::transfer_msg(
-                from_tx.creator.clone(),
-                from_tx.fee,
-                None,
-                BLOCK_SIZE,
-                config.sscrt.contract_hash,
-                config.sscrt.address,
-            )
-            .unwrap()]
-        );
-        // ==== * it updates the Tx and counter Tx to cancdl
-        assert_eq!(from_tx.status, 2);
-        assert_eq!(to_tx.status, 2);
+        // * it creates the request
+        handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg).unwrap();
+    }
 
-        // ==== when user tries to cancel Tx that is cancelled
-        // ==== * it raises an error
+    #[test]
+    fn test_set_address_book_label() {
+        let (_init_result, mut deps) = init_helper();
+        let label = Some("Supplier".to_string());
+        let handle_msg = HandleMsg::SetAddressBookLabel {
+            address: mock_contract_initiator_address(),
+            label: label.clone(),
+        };
+
+        // when address is not in the user's address book
+        // * it raises an error
         let handle_result = handle(
             &mut deps,
-            mock_env(mock_sscrt().address, &[]),
+            mock_env(mock_user_address(), &[]),
             handle_msg.clone(),
         );
         assert_eq!(
             handle_result.unwrap_err(),
             StdError::GenericErr {
-                msg: "Tx already cancelled.".to_string(),
+                msg: "Address is not in address book.".to_string(),
                 backtrace: None
             }
         );
-        // ==== when user tries to cancel Tx that is pending payment
-        from_tx.status = 1;
-        to_tx.status = 1;
-        update_tx(&mut deps.storage, &from_tx.from.clone(), from_tx).unwrap();
-        update_tx(&mut deps.storage, &to_tx.to.clone(), to_tx).unwrap();
-        let receive_msg = ReceiveMsg::Cancel { position: 0 };
-        let handle_msg = HandleMsg::Receive {
-            sender: mock_user_address(),
-            from: mock_user_address(),
-            amount: Uint128(0),
-            msg: to_binary(&receive_msg).unwrap(),
-        };
-        let handle_result_unwrapped = handle(
-            &mut deps,
-            mock_env(mock_sscrt().address, &[]),
-            handle_msg.clone(),
-        )
-        .unwrap();
-        let mut from_tx = tx_at_position(
+
+        // when address is in the user's address book
+        store_verified_address(
             &mut deps.storage,
             &deps.api.canonical_address(&mock_user_address()).unwrap(),
-            0,
+            &deps
+                .api
+                .canonical_address(&mock_contract_initiator_address())
+                .unwrap(),
+            mock_contract_initiator_address(),
+            5,
         )
         .unwrap();
-        let mut to_tx = tx_at_position(
-            &mut deps.storage,
-            &from_tx.to,
-            from_tx.other_storage_position,
+        handle(&mut deps, mock_env(mock_user_address(), &[]), handle_msg).unwrap();
+        // * it updates the label
+        let (entries, total) = get_address_book_entries(
+            &deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+            0,
+            10,
         )
         .unwrap();
-        // ==== * it sends the fee back to the creator
-        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        assert_eq!(total, 1);
         assert_eq!(
-            handle_result_unwrapped.messages,
-            vec![snip20::transfer_msg(
-                from_tx.creator.clone(),
-                from_tx.fee,
-                None,
-                BLOCK_SIZE,
-                config.sscrt.contract_hash,
-                config.sscrt.address,
-            )
-            .unwrap()]
+            entries,
+            vec![AddressBookEntry {
+                label: label,
+                address: mock_contract_initiator_address(),
+                verified_at: 5,
+            }]
         );
-        // ==== * it updates the Tx and counter Tx to cancel
-        assert_eq!(from_tx.status, 2);
-        assert_eq!(to_tx.status, 2);
-        // ==== when user tries to cancel Tx that is finalized
-        from_tx.status = 3;
-        to_tx.status = 3;
-        update_tx(&mut deps.storage, &from_tx.from.clone(), from_tx).unwrap();
-        update_tx(&mut deps.storage, &to_tx.to.clone(), to_tx).unwrap();
-        // ==== * it raises an error
+    }
+
+    #[test]
+    fn test_set_approval_policies() {
+        let (_init_result, mut deps) = init_helper();
+        let organization = create_organization_helper(&mut deps);
+        let policies_msg = |required_approvals: u32| HandleMsg::SetApprovalPolicies {
+            organization: organization.clone(),
+            approval_policies: vec![ApprovalPolicy {
+                token: mock_silk().address,
+                threshold: Uint128(10_000),
+                required_approvals,
+            }],
+        };
+
+        // when a policy requires no approvals
         let handle_result = handle(
             &mut deps,
-            mock_env(mock_sscrt().address, &[]),
-            handle_msg.clone(),
+            mock_env(mock_user_address(), &[]),
+            policies_msg(0),
         );
+        // * it raises an error
         assert_eq!(
             handle_result.unwrap_err(),
             StdError::GenericErr {
-                msg: "Tx already finalized.".to_string(),
+                msg: "Required approvals must be greater than zero.".to_string(),
                 backtrace: None
             }
         );
+
+        // when called by someone other than the organization's admin
+        let handle_result = handle(
+            &mut deps,
+            mock_env(HumanAddr::from("alice"), &[]),
+            policies_msg(2),
+        );
+        // * it raises an unauthorized error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // when called by the organization's admin
+        handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            policies_msg(2),
+        )
+        .unwrap();
+        // * payments above the threshold need the approvals
+        let stored = organization_at(
+            &deps.storage,
+            &deps.api.canonical_address(&organization).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            stored.required_approvals(&mock_silk().address, Uint128(10_001)),
+            Some(2)
+        );
+        assert_eq!(
+            stored.required_approvals(&mock_silk().address, Uint128(10_000)),
+            None
+        );
     }
 
     #[test]
-    fn test_confirm_address() {
+    fn test_set_fee_exemption() {
         let (_init_result, mut deps) = init_helper();
-        let env = mock_env(mock_user_address(), &[]);
-        let description = Some("Mercy".to_string());
+        let handle_msg = HandleMsg::SetFeeExemption {
+            address: mock_user_address(),
+            exempt: true,
+        };
 
-        // when send receive exists
-        let send_amount: Uint128 = Uint128(555_555);
+        // when called by non-admin
+        // * it raises an unauthorized error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // when admin calls this
+        handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            handle_msg,
+        )
+        .unwrap();
         let receive_msg = ReceiveMsg::CreateSendRequest {
             address: mock_contract_initiator_address(),
-            send_amount: send_amount,
-            description: description.clone(),
+            send_amount: Uint128(555_555),
+            description: Some("Partner".to_string()),
             token: mock_silk(),
+            skip_confirmation_if_verified: None,
+            referrer: None,
+            cancellation_policy: None,
+            cancellation_penalty: None,
+            hash_timelock: None,
+            milestones: None,
+            attachment_hash: None,
         };
+        // = when exempt user sends in the fee
+        // = * it raises an error
         let handle_msg = HandleMsg::Receive {
             sender: mock_user_address(),
             from: mock_user_address(),
@@ -911,17 +9235,6 @@ mod tests {
             msg: to_binary(&receive_msg).unwrap(),
         };
         let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
-        handle_result.unwrap();
-        // = when user sends in a positive amount
-        let receive_msg = ReceiveMsg::ConfirmAddress { position: 1 };
-        let handle_msg = HandleMsg::Receive {
-            sender: mock_user_address(),
-            from: mock_user_address(),
-            amount: Uint128(5),
-            msg: to_binary(&receive_msg).unwrap(),
-        };
-        let handle_result = handle(&mut deps, env.clone(), handle_msg);
-        // = * it raises an error
         assert_eq!(
             handle_result.unwrap_err(),
             StdError::GenericErr {
@@ -929,630 +9242,1320 @@ mod tests {
                 backtrace: None
             }
         );
-        // = when user sends in a zero amount
+        // = when exempt user sends in nothing
+        // = * it creates the Tx with no fee
         let handle_msg = HandleMsg::Receive {
             sender: mock_user_address(),
             from: mock_user_address(),
             amount: Uint128(0),
             msg: to_binary(&receive_msg).unwrap(),
         };
-        // == when user sends in a non-sscrt token
-        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
-        // == * it raises an error
+        handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg).unwrap();
+        let from_tx = tx_at_position(
+            &mut deps.storage,
+            &deps.api.canonical_address(&mock_user_address()).unwrap(),
+            0,
+        )
+        .unwrap();
+        assert_eq!(from_tx.fee, Uint128(0));
+
+        // when admin removes the exemption
+        // * it charges the fee again
+        let handle_msg = HandleMsg::SetFeeExemption {
+            address: mock_user_address(),
+            exempt: false,
+        };
+        handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            handle_msg,
+        )
+        .unwrap();
+        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
         assert_eq!(
-            handle_result.unwrap_err(),
-            StdError::GenericErr {
-                msg: "Wrong token received.".to_string(),
-                backtrace: None
-            }
+            effective_fee(&deps, &config, &mock_user_address(), config.fee).unwrap(),
+            mock_fee()
         );
-        // == when user sends in sscrt token
-        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
-        // === when user tries to confirm address of a Tx that does not exist
-        // === * it raises an error
+    }
+
+    #[test]
+    fn test_set_memo() {
+        let (_init_result, mut deps) = init_helper();
+        create_send_requests_helper(&mut deps, vec![Uint128(1_000)]);
+        let user_canonical = deps.api.canonical_address(&mock_user_address()).unwrap();
+        let admin_canonical = deps
+            .api
+            .canonical_address(&mock_contract_initiator_address())
+            .unwrap();
+
+        // when memo is too long
+        // * it raises an error
+        let handle_msg = HandleMsg::SetMemo {
+            position: 0,
+            memo: Some("a".repeat(257)),
+        };
+        let handle_result = handle(&mut deps, mock_env(mock_user_address(), &[]), handle_msg);
         assert_eq!(
             handle_result.unwrap_err(),
             StdError::GenericErr {
-                msg: "AppendStorage access out of bounds".to_string(),
+                msg: "Memo must be at most 256 bytes.".to_string(),
                 backtrace: None
             }
         );
-        // === when user tries to confirm address of a Tx that exists
-        // ==== when user tries to confirm Tx that is pending address confirmation
-        // ===== when user tries to confirm Tx that that they are not the receiver of
-        let receive_msg = ReceiveMsg::ConfirmAddress { position: 0 };
-        let handle_msg = HandleMsg::Receive {
-            sender: mock_user_address(),
-            from: mock_user_address(),
-            amount: Uint128(0),
-            msg: to_binary(&receive_msg).unwrap(),
+
+        // when memo is valid
+        let handle_msg = HandleMsg::SetMemo {
+            position: 0,
+            memo: Some("Pay before Friday".to_string()),
+        };
+        handle(&mut deps, mock_env(mock_user_address(), &[]), handle_msg).unwrap();
+        // * it sets the memo on the user's copy of the Tx only
+        let from_tx = tx_at_position(&mut deps.storage, &user_canonical, 0).unwrap();
+        let to_tx = tx_at_position(&mut deps.storage, &admin_canonical, 0).unwrap();
+        assert_eq!(from_tx.memo, Some("Pay before Friday".to_string()));
+        assert_eq!(to_tx.memo, None);
+        // * it stores the description and memo padded
+        let store = cosmwasm_storage::ReadonlyPrefixedStorage::multilevel(
+            &[crate::constants::PREFIX_TXS, user_canonical.as_slice()],
+            &deps.storage,
+        );
+        let stored_tx: Tx = secret_toolkit::storage::AppendStore::<Tx, _>::attach(&store)
+            .unwrap()
+            .unwrap()
+            .get_at(0)
+            .unwrap();
+        assert_eq!(stored_tx.description.unwrap().len(), 64);
+        assert_eq!(stored_tx.memo.unwrap().len(), 64);
+
+        // when memo ends in spaces
+        let handle_msg = HandleMsg::SetMemo {
+            position: 0,
+            memo: Some("Pay before Friday  ".to_string()),
         };
+        handle(&mut deps, mock_env(mock_user_address(), &[]), handle_msg).unwrap();
+        // * it keeps the spaces
+        let from_tx = tx_at_position(&mut deps.storage, &user_canonical, 0).unwrap();
+        assert_eq!(from_tx.memo, Some("Pay before Friday  ".to_string()));
+    }
+
+    #[test]
+    fn test_set_organization_member() {
+        let (_init_result, mut deps) = init_helper();
+        let organization = create_organization_helper(&mut deps);
+        let organization_canonical = deps.api.canonical_address(&organization).unwrap();
+        let member_msg =
+            |address: HumanAddr, role: Option<OrganizationRole>| HandleMsg::SetOrganizationMember {
+                organization: organization.clone(),
+                address,
+                role,
+            };
+
+        // when called by someone other than the organization's admin
         let handle_result = handle(
             &mut deps,
-            mock_env(mock_sscrt().address, &[]),
-            handle_msg.clone(),
+            mock_env(HumanAddr::from("alice"), &[]),
+            member_msg(HumanAddr::from("alice"), Some(OrganizationRole::Approver)),
         );
+        // * it raises an unauthorized error
         assert_eq!(
             handle_result.unwrap_err(),
             StdError::Unauthorized { backtrace: None }
         );
 
-        // ===== when user tries to confirm Tx that that they are the receiver of
-        // ===== * it updates the Tx and counter Tx to pending payment
-        let receive_msg = ReceiveMsg::ConfirmAddress { position: 0 };
-        let handle_msg = HandleMsg::Receive {
-            sender: mock_contract_initiator_address(),
-            from: mock_contract_initiator_address(),
-            amount: Uint128(0),
-            msg: to_binary(&receive_msg).unwrap(),
-        };
-        let handle_result_unwrapped = handle(
-            &mut deps,
-            mock_env(mock_sscrt().address, &[]),
-            handle_msg.clone(),
-        )
-        .unwrap();
-        assert_eq!(handle_result_unwrapped.messages, vec![]);
-        let from_tx = tx_at_position(
-            &mut deps.storage,
-            &deps.api.canonical_address(&mock_user_address()).unwrap(),
-            0,
-        )
-        .unwrap();
-        let to_tx = tx_at_position(
-            &mut deps.storage,
-            &from_tx.to,
-            from_tx.other_storage_position,
-        )
-        .unwrap();
-        assert_eq!(from_tx.status, 1);
-        assert_eq!(to_tx.status, 1);
-
-        // ==== when user tries to confirm Tx that is not pending address confirmation
-        // ==== * it raises an error
+        // when the admin changes their own role
         let handle_result = handle(
             &mut deps,
-            mock_env(mock_sscrt().address, &[]),
-            handle_msg.clone(),
+            mock_env(mock_user_address(), &[]),
+            member_msg(mock_user_address(), Some(OrganizationRole::Viewer)),
         );
+        // * it raises an error
         assert_eq!(
             handle_result.unwrap_err(),
             StdError::GenericErr {
-                msg: "Tx not waiting for address confirmation.".to_string(),
+                msg: "The admin's role can not be changed.".to_string(),
                 backtrace: None
             }
         );
+
+        // when the admin changes a member's role
+        handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            member_msg(HumanAddr::from("alice"), Some(OrganizationRole::Viewer)),
+        )
+        .unwrap();
+        // * it replaces the member's role
+        let stored = organization_at(&deps.storage, &organization_canonical).unwrap();
+        assert_eq!(
+            stored.members,
+            vec![OrganizationMember {
+                address: HumanAddr::from("alice"),
+                role: OrganizationRole::Viewer,
+            }]
+        );
+
+        // when the admin removes a member
+        handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            member_msg(HumanAddr::from("alice"), None),
+        )
+        .unwrap();
+        // * it removes the member
+        let stored = organization_at(&deps.storage, &organization_canonical).unwrap();
+        assert_eq!(stored.members, vec![]);
+        assert_eq!(stored.role(&HumanAddr::from("alice")), None);
     }
 
     #[test]
-    fn test_create_receive_request() {
+    fn test_set_spending_limits() {
         let (_init_result, mut deps) = init_helper();
         let env = mock_env(mock_user_address(), &[]);
-        let description = Some("Mercy".to_string());
-
-        // when incorrect fee amount is sent in
-        let receive_msg = ReceiveMsg::CreateReceiveRequest {
-            address: mock_user_address(),
-            send_amount: Uint128(555555),
-            description: description.clone(),
-            token: mock_silk(),
+        let user_canonical = deps.api.canonical_address(&mock_user_address()).unwrap();
+        let limits = SpendingLimits {
+            token_limits: vec![TokenSpendingLimit {
+                token: mock_silk().address,
+                daily_maximum: Some(Uint128(1_500)),
+                weekly_maximum: None,
+                counterparty_daily_maximum: Some(Uint128(1_000)),
+            }],
+            cooling_off_period: Some(SPENDING_LIMITS_DELAY + 10),
         };
-        let handle_msg = HandleMsg::Receive {
-            sender: mock_user_address(),
-            from: mock_user_address(),
-            amount: Uint128(555),
-            msg: to_binary(&receive_msg).unwrap(),
+        for (address, send_amount) in [
+            (mock_contract_initiator_address(), 1_000),
+            (mock_contract_initiator_address(), 100),
+            (HumanAddr::from("alice"), 1_000),
+        ] {
+            let receive_msg = ReceiveMsg::CreateSendRequest {
+                address,
+                send_amount: Uint128(send_amount),
+                description: None,
+                token: mock_silk(),
+                skip_confirmation_if_verified: None,
+                referrer: None,
+                cancellation_policy: None,
+                cancellation_penalty: None,
+                hash_timelock: None,
+                milestones: None,
+                attachment_hash: None,
+            };
+            let handle_msg = HandleMsg::Receive {
+                sender: mock_user_address(),
+                from: mock_user_address(),
+                amount: mock_fee(),
+                msg: to_binary(&receive_msg).unwrap(),
+            };
+            handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg).unwrap();
+        }
+        for (address, position) in [
+            (mock_contract_initiator_address(), 0),
+            (mock_contract_initiator_address(), 1),
+            (HumanAddr::from("alice"), 0),
+        ] {
+            let handle_msg = HandleMsg::Receive {
+                sender: address.clone(),
+                from: address,
+                amount: Uint128(0),
+                msg: to_binary(&ReceiveMsg::ConfirmAddress { position }).unwrap(),
+            };
+            handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg).unwrap();
+        }
+        let payment = |position: u32, amount: u128, time: u64| {
+            let mut env = mock_env(mock_silk().address, &[]);
+            env.block.time = time;
+            let handle_msg = HandleMsg::Receive {
+                sender: mock_user_address(),
+                from: mock_user_address(),
+                amount: Uint128(amount),
+                msg: to_binary(&ReceiveMsg::SendPayment { position }).unwrap(),
+            };
+            (env, handle_msg)
         };
-        let handle_result = handle(&mut deps, env.clone(), handle_msg);
+
+        // when the user sets spending limits
+        let handle_msg = HandleMsg::SetSpendingLimits {
+            token_limits: limits.token_limits.clone(),
+            cooling_off_period: limits.cooling_off_period,
+        };
+        handle(&mut deps, env.clone(), handle_msg).unwrap();
+        // * they only take effect after the delay
+        let settings = spending_limit_settings(&deps.storage, &user_canonical).unwrap();
+        assert_eq!(settings.limits, SpendingLimits::default());
+        assert_eq!(
+            settings.pending,
+            Some(PendingSpendingLimits {
+                limits: limits.clone(),
+                effective_at: env.block.time + SPENDING_LIMITS_DELAY,
+            })
+        );
+        assert_eq!(
+            settings.at(env.block.time + SPENDING_LIMITS_DELAY).limits,
+            limits
+        );
+
+        // when the limits have taken effect
+        // = when the counterparty is new and in its cooling-off period
+        let (payment_env, handle_msg) = payment(0, 1_000, env.block.time + SPENDING_LIMITS_DELAY);
+        let handle_result = handle(&mut deps, payment_env, handle_msg);
+        // = * it raises an error
         assert_eq!(
             handle_result.unwrap_err(),
             StdError::GenericErr {
-                msg: "Wrong amount received.".to_string(),
+                msg: "Counterparty is new and still in its cooling-off period.".to_string(),
                 backtrace: None
             }
         );
 
-        // when correct fee amount is sent in
+        // = when the payment is within the limits
+        let time = env.block.time + SPENDING_LIMITS_DELAY + 10;
+        let (payment_env, handle_msg) = payment(0, 1_000, time);
+        // = * it sends the payment
+        handle(&mut deps, payment_env, handle_msg).unwrap();
+
+        // = when the payment goes over the counterparty's daily maximum
+        let (payment_env, handle_msg) = payment(1, 100, time);
+        let handle_result = handle(&mut deps, payment_env, handle_msg);
+        // = * it raises an error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Payment exceeds the daily spending limit for this counterparty.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // = when the payment goes over the token's daily maximum
+        let (payment_env, handle_msg) = payment(2, 1_000, time);
+        let handle_result = handle(&mut deps, payment_env, handle_msg);
+        // = * it raises an error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Payment exceeds the daily spending limit for this token.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // = when a payment link goes over the token's daily maximum
+        create_payment_link_helper(&mut deps, 1);
+        let mut link_env = mock_env(mock_silk().address, &[]);
+        link_env.block.time = time;
         let handle_msg = HandleMsg::Receive {
             sender: mock_user_address(),
             from: mock_user_address(),
-            amount: mock_fee(),
-            msg: to_binary(&receive_msg).unwrap(),
+            amount: Uint128(1_000),
+            msg: to_binary(&ReceiveMsg::PayLink {
+                code: "open-sesame".to_string(),
+            })
+            .unwrap(),
         };
-        // = when incorrect fee token is sent in
-        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        let handle_result = handle(&mut deps, link_env, handle_msg);
+        // = * it raises an error
         assert_eq!(
             handle_result.unwrap_err(),
             StdError::GenericErr {
-                msg: "Wrong token received.".to_string(),
+                msg: "Payment exceeds the daily spending limit for this token.".to_string(),
                 backtrace: None
             }
         );
 
-        // = when correct fee token is sent in
-        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
-        // == when sender sets the receiver to themselves
-        // == * it raises an error
+        // = when the user funds a claim, which anyone with the code can redeem
+        create_claim_helper(&mut deps, false);
+        let mut claim_env = mock_env(mock_silk().address, &[]);
+        claim_env.block.time = time;
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(1_000),
+            msg: to_binary(&ReceiveMsg::FundClaim {
+                code_hash: Binary(hash_code("open-sesame")),
+            })
+            .unwrap(),
+        };
+        let handle_result = handle(&mut deps, claim_env, handle_msg);
+        // = * it is held to the cooling-off period of a new counterparty
         assert_eq!(
             handle_result.unwrap_err(),
             StdError::GenericErr {
-                msg: "From and to addresses must be different.".to_string(),
+                msg: "Counterparty is new and still in its cooling-off period.".to_string(),
                 backtrace: None
             }
         );
+    }
 
-        // == when sender sets the receiver to somebody else
-        let send_amount: Uint128 = Uint128(555_555);
-        let receive_msg = ReceiveMsg::CreateReceiveRequest {
-            address: mock_contract_initiator_address(),
-            send_amount: send_amount,
-            description: description.clone(),
-            token: mock_silk(),
+    #[test]
+    fn test_update_constant_shape() {
+        let (_init_result, mut deps) = init_helper();
+        let confirm_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(0),
+            msg: to_binary(&ReceiveMsg::ConfirmAddress { position: 0 }).unwrap(),
         };
-        let handle_msg = HandleMsg::Receive {
+        let payment_msg = HandleMsg::Receive {
             sender: mock_user_address(),
             from: mock_user_address(),
-            amount: mock_fee(),
-            msg: to_binary(&receive_msg).unwrap(),
+            amount: Uint128(1_000),
+            msg: to_binary(&ReceiveMsg::SendPayment { position: 0 }).unwrap(),
         };
-        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
-        let handle_result_unwrapped = handle_result.unwrap();
+        let cancel_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(0),
+            msg: to_binary(&ReceiveMsg::Cancel {
+                position: 1,
+                reason: None,
+            })
+            .unwrap(),
+        };
+
+        // when constant shape mode is off
+        // * status transitions use different storage accesses and message counts
+        create_send_requests_helper(&mut deps, vec![Uint128(1_000), Uint128(1_000)]);
+        let (confirm_result, confirm_reads, confirm_writes, _) = counted_handle_helper(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            confirm_msg.clone(),
+        );
+        let (cancel_result, cancel_reads, cancel_writes, _) = counted_handle_helper(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            cancel_msg.clone(),
+        );
+        assert_ne!(
+            (confirm_reads, confirm_writes),
+            (cancel_reads, cancel_writes)
+        );
+        assert_ne!(
+            confirm_result.unwrap().messages.len(),
+            cancel_result.unwrap().messages.len()
+        );
+
+        // when user is not the admin
+        // * it raises an unauthorized error
+        let handle_msg = HandleMsg::UpdateConstantShape {
+            constant_shape: true,
+        };
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            handle_msg.clone(),
+        );
         assert_eq!(
-            handle_result_unwrapped.messages,
-            vec![snip20::register_receive_msg(
-                mock_env(mock_sscrt().address, &[]).contract_code_hash,
-                None,
-                BLOCK_SIZE,
-                mock_silk().contract_hash,
-                mock_silk().address,
-            )
-            .unwrap()]
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
         );
-        // == * it creates the txs
-        let from_tx = tx_at_position(
+
+        // when user is the admin
+        // * it updates the config
+        handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            handle_msg,
+        )
+        .unwrap();
+        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        assert!(config.constant_shape);
+
+        // when constant shape mode is on
+        // * status transitions use the same storage accesses and message count
+        let (_init_result, mut deps) = init_helper();
+        handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            HandleMsg::UpdateConstantShape {
+                constant_shape: true,
+            },
+        )
+        .unwrap();
+        create_send_requests_helper(&mut deps, vec![Uint128(1_000), Uint128(1_000)]);
+        let mut shapes: Vec<(u32, u32, usize)> = vec![];
+        let mut message_counts: Vec<usize> = vec![];
+        for (env, msg) in [
+            (mock_env(mock_sscrt().address, &[]), confirm_msg.clone()),
+            (mock_env(mock_silk().address, &[]), payment_msg),
+            (mock_env(mock_sscrt().address, &[]), cancel_msg.clone()),
+        ] {
+            let (handle_result, reads, writes, written_bytes) =
+                counted_handle_helper(&mut deps, env, msg);
+            shapes.push((reads, writes, written_bytes));
+            message_counts.push(handle_result.unwrap().messages.len());
+        }
+        // * failed status transitions use the same storage accesses too
+        for msg in [confirm_msg, cancel_msg] {
+            let (handle_result, reads, writes, written_bytes) =
+                counted_handle_helper(&mut deps, mock_env(mock_sscrt().address, &[]), msg);
+            assert!(handle_result.is_err());
+            shapes.push((reads, writes, written_bytes));
+        }
+
+        // * heavier status transitions use the same storage accesses and message count too
+        handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            HandleMsg::UpdateReferralShare {
+                referral_share_bps: 2_000,
+            },
+        )
+        .unwrap();
+        set_spending_limit_settings(
             &mut deps.storage,
             &deps.api.canonical_address(&mock_user_address()).unwrap(),
-            0,
+            &SpendingLimitSettings {
+                limits: SpendingLimits {
+                    token_limits: vec![TokenSpendingLimit {
+                        token: mock_silk().address,
+                        daily_maximum: Some(Uint128(1_000_000)),
+                        weekly_maximum: Some(Uint128(1_000_000)),
+                        counterparty_daily_maximum: Some(Uint128(1_000_000)),
+                    }],
+                    cooling_off_period: Some(0),
+                },
+                pending: None,
+            },
         )
         .unwrap();
-        let to_tx = tx_at_position(
-            &mut deps.storage,
-            &from_tx.to,
-            from_tx.other_storage_position,
+        let request_msg = |referrer: Option<HumanAddr>,
+                           cancellation_penalty: Option<Uint128>,
+                           hash_timelock: Option<HashTimelock>,
+                           milestones: Option<Vec<Milestone>>| {
+            HandleMsg::Receive {
+                sender: mock_user_address(),
+                from: mock_user_address(),
+                amount: mock_fee(),
+                msg: to_binary(&ReceiveMsg::CreateSendRequest {
+                    address: mock_contract_initiator_address(),
+                    send_amount: Uint128(1_000),
+                    description: Some("Heavy".to_string()),
+                    token: mock_silk(),
+                    skip_confirmation_if_verified: None,
+                    referrer,
+                    cancellation_policy: None,
+                    cancellation_penalty,
+                    hash_timelock,
+                    milestones,
+                    attachment_hash: Some(Binary(vec![1; 32])),
+                })
+                .unwrap(),
+            }
+        };
+        let referrer = Some(mock_contract().address);
+        let milestones = vec![
+            Milestone {
+                label: "Design".to_string(),
+                amount: Uint128(400),
+                deadline: None,
+                status: MilestoneStatus::Pending,
+            },
+            Milestone {
+                label: "Build".to_string(),
+                amount: Uint128(600),
+                deadline: Some(mock_expires_at()),
+                status: MilestoneStatus::Pending,
+            },
+        ];
+        let hash_timelock = HashTimelock {
+            hashlock: Binary(Sha256::digest(b"swap-secret").to_vec()),
+            timelock: mock_expires_at(),
+        };
+        // Referral payout with spending limits, hash timelock and milestones at positions 2 to 4
+        for msg in [
+            request_msg(referrer.clone(), None, None, None),
+            request_msg(referrer.clone(), None, Some(hash_timelock), None),
+            request_msg(referrer.clone(), None, None, Some(milestones)),
+        ] {
+            handle(&mut deps, mock_env(mock_sscrt().address, &[]), msg).unwrap();
+        }
+        for position in 2..5 {
+            let confirm_msg = HandleMsg::Receive {
+                sender: mock_contract_initiator_address(),
+                from: mock_contract_initiator_address(),
+                amount: Uint128(0),
+                msg: to_binary(&ReceiveMsg::ConfirmAddress { position }).unwrap(),
+            };
+            let payment_msg = HandleMsg::Receive {
+                sender: mock_user_address(),
+                from: mock_user_address(),
+                amount: Uint128(1_000),
+                msg: to_binary(&ReceiveMsg::SendPayment { position }).unwrap(),
+            };
+            for (env, msg) in [
+                (mock_env(mock_sscrt().address, &[]), confirm_msg),
+                (mock_env(mock_silk().address, &[]), payment_msg),
+            ] {
+                let (handle_result, reads, writes, written_bytes) =
+                    counted_handle_helper(&mut deps, env, msg);
+                shapes.push((reads, writes, written_bytes));
+                message_counts.push(handle_result.unwrap().messages.len());
+            }
+        }
+        // The counterparty cancels with a penalty and a referrer at position 5
+        handle(
+            &mut deps,
+            mock_env(mock_sscrt().address, &[]),
+            request_msg(referrer, Some(Uint128(500)), None, None),
         )
         .unwrap();
-        assert_eq!(
-            from_tx,
-            Tx {
-                position: 0,
-                other_storage_position: 0,
-                fee: mock_fee(),
-                from: deps
-                    .api
-                    .canonical_address(&mock_contract_initiator_address())
-                    .unwrap(),
-                to: deps.api.canonical_address(&mock_user_address()).unwrap(),
-                creator: mock_user_address(),
-                amount: send_amount,
-                token: mock_silk(),
-                description: description.clone(),
-                status: 1,
-                block_time: env.block.time,
-                block_height: env.block.height,
+        let cancel_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(500),
+            msg: to_binary(&ReceiveMsg::Cancel {
+                position: 5,
+                reason: Some("Wrong invoice".to_string()),
+            })
+            .unwrap(),
+        };
+        let (handle_result, reads, writes, written_bytes) =
+            counted_handle_helper(&mut deps, mock_env(mock_sscrt().address, &[]), cancel_msg);
+        shapes.push((reads, writes, written_bytes));
+        message_counts.push(handle_result.unwrap().messages.len());
+        // Payments in many tokens do not grow the records the transitions write
+        let mut position: u32 = 6;
+        for index in 0..30 {
+            let token = SecretContract {
+                address: HumanAddr::from(format!("token-{}", index)),
+                contract_hash: "a".repeat(64),
+            };
+            let receive_msg = ReceiveMsg::CreateSendRequest {
+                address: mock_contract_initiator_address(),
+                send_amount: Uint128(1_000),
+                description: None,
+                token: token.clone(),
+                skip_confirmation_if_verified: None,
+                referrer: None,
+                cancellation_policy: None,
+                cancellation_penalty: None,
+                hash_timelock: None,
+                milestones: None,
+                attachment_hash: None,
+            };
+            let handle_msg = HandleMsg::Receive {
+                sender: mock_user_address(),
+                from: mock_user_address(),
+                amount: mock_fee(),
+                msg: to_binary(&receive_msg).unwrap(),
+            };
+            handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg).unwrap();
+            for (env, msg) in [
+                (
+                    mock_env(mock_sscrt().address, &[]),
+                    HandleMsg::Receive {
+                        sender: mock_contract_initiator_address(),
+                        from: mock_contract_initiator_address(),
+                        amount: Uint128(0),
+                        msg: to_binary(&ReceiveMsg::ConfirmAddress { position }).unwrap(),
+                    },
+                ),
+                (
+                    mock_env(token.address, &[]),
+                    HandleMsg::Receive {
+                        sender: mock_user_address(),
+                        from: mock_user_address(),
+                        amount: Uint128(1_000),
+                        msg: to_binary(&ReceiveMsg::SendPayment { position }).unwrap(),
+                    },
+                ),
+            ] {
+                let (handle_result, reads, writes, written_bytes) =
+                    counted_handle_helper(&mut deps, env, msg);
+                shapes.push((reads, writes, written_bytes));
+                message_counts.push(handle_result.unwrap().messages.len());
             }
-        );
-        assert_eq!(
-            to_tx,
-            Tx {
-                position: 0,
-                other_storage_position: 0,
-                fee: mock_fee(),
-                from: deps
-                    .api
-                    .canonical_address(&mock_contract_initiator_address())
-                    .unwrap(),
-                to: deps.api.canonical_address(&mock_user_address()).unwrap(),
-                creator: mock_user_address(),
-                amount: send_amount,
-                token: mock_silk(),
-                description: description,
-                status: 1,
-                block_time: env.block.time,
-                block_height: env.block.height,
+            position += 1;
+        }
+        // Txs with every field at its maximum size, one paid and one cancelled by the counterparty
+        let token = SecretContract {
+            address: HumanAddr::from("t".repeat(64)),
+            contract_hash: "a".repeat(64),
+        };
+        let milestones = vec![
+            Milestone {
+                label: "a".repeat(256),
+                amount: Uint128(200),
+                deadline: Some(mock_expires_at()),
+                status: MilestoneStatus::Pending,
+            };
+            MAX_MILESTONES
+        ];
+        for _ in 0..2 {
+            let receive_msg = ReceiveMsg::CreateSendRequest {
+                address: mock_contract_initiator_address(),
+                send_amount: Uint128(1_000),
+                description: Some("a".repeat(256)),
+                token: token.clone(),
+                skip_confirmation_if_verified: None,
+                referrer: Some(mock_contract().address),
+                cancellation_policy: None,
+                cancellation_penalty: Some(Uint128(500)),
+                hash_timelock: None,
+                milestones: Some(milestones.clone()),
+                attachment_hash: Some(Binary(vec![1; 32])),
+            };
+            let handle_msg = HandleMsg::Receive {
+                sender: mock_user_address(),
+                from: mock_user_address(),
+                amount: mock_fee(),
+                msg: to_binary(&receive_msg).unwrap(),
+            };
+            handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg).unwrap();
+        }
+        for position in [position, position + 1] {
+            for address in [mock_user_address(), mock_contract_initiator_address()] {
+                let handle_msg = HandleMsg::SetMemo {
+                    position,
+                    memo: Some("a".repeat(256)),
+                };
+                handle(&mut deps, mock_env(address, &[]), handle_msg).unwrap();
             }
-        );
+        }
+        for (env, msg) in [
+            (
+                mock_env(mock_sscrt().address, &[]),
+                HandleMsg::Receive {
+                    sender: mock_contract_initiator_address(),
+                    from: mock_contract_initiator_address(),
+                    amount: Uint128(0),
+                    msg: to_binary(&ReceiveMsg::ConfirmAddress { position }).unwrap(),
+                },
+            ),
+            (
+                mock_env(mock_sscrt().address, &[]),
+                HandleMsg::Receive {
+                    sender: mock_contract_initiator_address(),
+                    from: mock_contract_initiator_address(),
+                    amount: Uint128(0),
+                    msg: to_binary(&ReceiveMsg::ConfirmAddress {
+                        position: position + 1,
+                    })
+                    .unwrap(),
+                },
+            ),
+            (
+                mock_env(token.address.clone(), &[]),
+                HandleMsg::Receive {
+                    sender: mock_user_address(),
+                    from: mock_user_address(),
+                    amount: Uint128(1_000),
+                    msg: to_binary(&ReceiveMsg::SendPayment { position }).unwrap(),
+                },
+            ),
+            (
+                mock_env(mock_sscrt().address, &[]),
+                HandleMsg::Receive {
+                    sender: mock_contract_initiator_address(),
+                    from: mock_contract_initiator_address(),
+                    amount: Uint128(500),
+                    msg: to_binary(&ReceiveMsg::Cancel {
+                        position: position + 1,
+                        reason: Some("a".repeat(256)),
+                    })
+                    .unwrap(),
+                },
+            ),
+        ] {
+            let (handle_result, reads, writes, written_bytes) =
+                counted_handle_helper(&mut deps, env, msg);
+            shapes.push((reads, writes, written_bytes));
+            message_counts.push(handle_result.unwrap().messages.len());
+        }
+        // An organization payment is escrowed until it is approved
+        let organization = create_approval_helper(&mut deps);
+        let receive_msg = ReceiveMsg::CreateReceiveRequest {
+            address: organization.clone(),
+            send_amount: Uint128(1_000),
+            description: Some("Invoice".to_string()),
+            token: mock_silk(),
+            referrer: None,
+            cancellation_policy: None,
+            cancellation_penalty: None,
+            attachment_hash: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: mock_fee(),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg).unwrap();
+        let payment_msg = HandleMsg::Receive {
+            sender: HumanAddr::from("alice"),
+            from: HumanAddr::from("alice"),
+            amount: Uint128(1_000),
+            msg: to_binary(&ReceiveMsg::OnBehalfOf {
+                organization: organization.clone(),
+                msg: Box::new(ReceiveMsg::SendPayment { position: 1 }),
+            })
+            .unwrap(),
+        };
+        let (handle_result, reads, writes, written_bytes) =
+            counted_handle_helper(&mut deps, mock_env(mock_silk().address, &[]), payment_msg);
+        shapes.push((reads, writes, written_bytes));
+        message_counts.push(handle_result.unwrap().messages.len());
+        // An organization cancels a request of its own, which its audit trail keeps a copy of
+        let receive_msg = ReceiveMsg::CreateSendRequest {
+            address: mock_contract_initiator_address(),
+            send_amount: Uint128(1_000),
+            description: Some("a".repeat(256)),
+            token: token.clone(),
+            skip_confirmation_if_verified: None,
+            referrer: Some(mock_contract().address),
+            cancellation_policy: None,
+            cancellation_penalty: Some(Uint128(500)),
+            hash_timelock: None,
+            milestones: Some(milestones),
+            attachment_hash: Some(Binary(vec![1; 32])),
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: mock_fee(),
+            msg: to_binary(&ReceiveMsg::OnBehalfOf {
+                organization: organization.clone(),
+                msg: Box::new(receive_msg),
+            })
+            .unwrap(),
+        };
+        handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg).unwrap();
+        let cancel_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(0),
+            msg: to_binary(&ReceiveMsg::OnBehalfOf {
+                organization,
+                msg: Box::new(ReceiveMsg::Cancel {
+                    position: 2,
+                    reason: Some("a".repeat(256)),
+                }),
+            })
+            .unwrap(),
+        };
+        let (handle_result, reads, writes, written_bytes) =
+            counted_handle_helper(&mut deps, mock_env(mock_sscrt().address, &[]), cancel_msg);
+        shapes.push((reads, writes, written_bytes));
+        message_counts.push(handle_result.unwrap().messages.len());
+        assert!(shapes.iter().all(|shape| shape == &shapes[0]));
+        assert!(message_counts.iter().all(|count| count == &SHAPE_MESSAGES));
     }
 
     #[test]
-    fn test_create_send_request() {
+    fn test_update_fee() {
         let (_init_result, mut deps) = init_helper();
         let env = mock_env(mock_user_address(), &[]);
-        let description = Some("Mercy".to_string());
+        let new_fee = Uint128(555);
 
-        // when incorrect fee amount is sent in
-        let receive_msg = ReceiveMsg::CreateSendRequest {
-            address: mock_user_address(),
-            send_amount: Uint128(555555),
-            description: description.clone(),
-            token: mock_silk(),
+        // when called by non-admin
+        // * it raises an unauthorized error
+        let handle_msg = HandleMsg::UpdateFee { fee: new_fee };
+        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // when admin calls this
+        let env = mock_env(mock_contract_initiator_address(), &[]);
+        let handle_result = handle(&mut deps, env, handle_msg);
+        handle_result.unwrap();
+        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        assert_eq!(config.fee, new_fee)
+    }
+
+    #[test]
+    fn test_update_fee_discounts() {
+        let (_init_result, mut deps) = init_helper();
+        let fee_discount = FeeDiscount {
+            token: mock_shade(),
+            minimum_balance: Uint128(1_000),
+            discount_bps: 5_000,
         };
-        let handle_msg = HandleMsg::Receive {
-            sender: mock_user_address(),
-            from: mock_user_address(),
-            amount: Uint128(555),
-            msg: to_binary(&receive_msg).unwrap(),
+        let handle_msg = HandleMsg::UpdateFeeDiscounts {
+            fee_discounts: vec![fee_discount.clone()],
         };
-        let handle_result = handle(&mut deps, env.clone(), handle_msg);
+
+        // when called by non-admin
+        // * it raises an unauthorized error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            handle_msg.clone(),
+        );
         assert_eq!(
             handle_result.unwrap_err(),
-            StdError::GenericErr {
-                msg: "Wrong amount received.".to_string(),
-                backtrace: None
-            }
+            StdError::Unauthorized { backtrace: None }
         );
 
-        // when correct fee amount is sent in
-        let handle_msg = HandleMsg::Receive {
-            sender: mock_user_address(),
-            from: mock_user_address(),
-            amount: mock_fee(),
-            msg: to_binary(&receive_msg).unwrap(),
+        // when admin calls this
+        // = when a discount is more than 100%
+        // = * it raises an error
+        let mut invalid_fee_discount = fee_discount.clone();
+        invalid_fee_discount.discount_bps = 10_001;
+        let invalid_handle_msg = HandleMsg::UpdateFeeDiscounts {
+            fee_discounts: vec![invalid_fee_discount],
         };
-        // = when incorrect fee token is sent in
-        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            invalid_handle_msg,
+        );
         assert_eq!(
             handle_result.unwrap_err(),
             StdError::GenericErr {
-                msg: "Wrong token received.".to_string(),
+                msg: "Discount can not be more than 10000 basis points.".to_string(),
                 backtrace: None
             }
         );
 
-        // = when correct fee token is sent in
-        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
-        // == when sender sets the receiver to themselves
-        // == * it raises an error
+        // = when discounts are valid
+        // = * it updates the fee discounts
+        handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            handle_msg,
+        )
+        .unwrap();
+        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        assert_eq!(config.fee_discounts, vec![fee_discount]);
+
+        // when user has not set a viewing key for the discount token
+        // * it charges the full fee
         assert_eq!(
-            handle_result.unwrap_err(),
-            StdError::GenericErr {
-                msg: "From and to addresses must be different.".to_string(),
-                backtrace: None
-            }
+            effective_fee(&deps, &config, &mock_user_address(), config.fee).unwrap(),
+            mock_fee()
         );
 
-        // == when sender sets the receiver to somebody else
-        let send_amount: Uint128 = Uint128(555_555);
-        let receive_msg = ReceiveMsg::CreateSendRequest {
-            address: mock_contract_initiator_address(),
-            send_amount: send_amount,
-            description: description.clone(),
-            token: mock_silk(),
-        };
-        let handle_msg = HandleMsg::Receive {
-            sender: mock_user_address(),
-            from: mock_user_address(),
-            amount: mock_fee(),
-            msg: to_binary(&receive_msg).unwrap(),
+        // when user's balance can not be queried with their viewing key
+        // * it charges the full fee
+        let handle_msg = HandleMsg::SetFeeDiscountKey {
+            token: mock_shade().address,
+            key: "DoTheRightThing.".to_string(),
         };
-        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
-        let handle_result_unwrapped = handle_result.unwrap();
-        // == * it registers a token if it is not registered already
+        handle(&mut deps, mock_env(mock_user_address(), &[]), handle_msg).unwrap();
         assert_eq!(
-            handle_result_unwrapped.messages,
-            vec![snip20::register_receive_msg(
-                mock_env(mock_sscrt().address, &[]).contract_code_hash,
-                None,
-                BLOCK_SIZE,
-                mock_silk().contract_hash,
-                mock_silk().address,
+            fee_discount_key(
+                &deps.storage,
+                &deps.api.canonical_address(&mock_user_address()).unwrap(),
+                &mock_shade().address
             )
-            .unwrap()]
-        );
-        // == * it creates the txs
-        let from_tx = tx_at_position(
-            &mut deps.storage,
-            &deps.api.canonical_address(&mock_user_address()).unwrap(),
-            0,
-        )
-        .unwrap();
-        let to_tx = tx_at_position(
-            &mut deps.storage,
-            &from_tx.to,
-            from_tx.other_storage_position,
-        )
-        .unwrap();
-        assert_eq!(
-            from_tx,
-            Tx {
-                position: 0,
-                other_storage_position: 0,
-                fee: mock_fee(),
-                from: deps.api.canonical_address(&mock_user_address()).unwrap(),
-                to: deps
-                    .api
-                    .canonical_address(&mock_contract_initiator_address())
-                    .unwrap(),
-                creator: mock_user_address(),
-                amount: send_amount,
-                token: mock_silk(),
-                description: description.clone(),
-                status: 0,
-                block_time: env.block.time,
-                block_height: env.block.height,
-            }
+            .unwrap(),
+            Some("DoTheRightThing.".to_string())
         );
         assert_eq!(
-            to_tx,
-            Tx {
-                position: 0,
-                other_storage_position: 0,
-                fee: mock_fee(),
-                from: deps.api.canonical_address(&mock_user_address()).unwrap(),
-                to: deps
-                    .api
-                    .canonical_address(&mock_contract_initiator_address())
-                    .unwrap(),
-                creator: mock_user_address(),
-                amount: send_amount,
-                token: mock_silk(),
-                description: description,
-                status: 0,
-                block_time: env.block.time,
-                block_height: env.block.height,
-            }
+            effective_fee(&deps, &config, &mock_user_address(), config.fee).unwrap(),
+            mock_fee()
         );
     }
 
     #[test]
-    fn test_nominate_new_admin() {
+    fn test_update_fee_tokens() {
         let (_init_result, mut deps) = init_helper();
-        let env = mock_env(mock_user_address(), &[]);
+        let fee_token = FeeToken {
+            token: mock_silk(),
+            fee: Uint128(500),
+        };
+        let handle_msg = HandleMsg::UpdateFeeTokens {
+            fee_tokens: vec![fee_token.clone()],
+        };
 
         // when called by non-admin
         // * it raises an unauthorized error
-        let handle_msg = HandleMsg::NominateNewAdmin {
-            address: mock_user_address(),
-        };
-        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            handle_msg.clone(),
+        );
         assert_eq!(
             handle_result.unwrap_err(),
             StdError::Unauthorized { backtrace: None }
         );
 
         // when admin calls this
-        let env = mock_env(mock_contract_initiator_address(), &[]);
-        let handle_result = handle(&mut deps, env, handle_msg);
-        handle_result.unwrap();
-        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
-        assert_eq!(config.new_admin_nomination, Some(mock_user_address()))
-    }
+        // = when a fee token is SSCRT
+        // = * it raises an error
+        let invalid_handle_msg = HandleMsg::UpdateFeeTokens {
+            fee_tokens: vec![FeeToken {
+                token: mock_sscrt(),
+                fee: Uint128(500),
+            }],
+        };
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            invalid_handle_msg,
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Fee tokens must be different from SSCRT and each other.".to_string(),
+                backtrace: None
+            }
+        );
 
-    #[test]
-    fn test_send_payment() {
-        let (_init_result, mut deps) = init_helper();
-        let env = mock_env(mock_user_address(), &[]);
-        let description = Some("Mercy".to_string());
+        // = when the fee tokens are valid
+        handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            handle_msg,
+        )
+        .unwrap();
+        // = * it stores the fee tokens
+        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        assert_eq!(config.fee_tokens, vec![fee_token]);
+        assert_eq!(
+            accepted_fee_token(&config, Some(mock_shade().address)).unwrap_err(),
+            StdError::GenericErr {
+                msg: "Token is not accepted for fees.".to_string(),
+                backtrace: None
+            }
+        );
 
-        // when pending address tx exists
-        let send_amount: Uint128 = Uint128(555_555);
+        // == when a user pays the fee for a request in a fee token
         let receive_msg = ReceiveMsg::CreateSendRequest {
             address: mock_contract_initiator_address(),
-            send_amount: send_amount,
-            description: description.clone(),
-            token: mock_silk(),
+            send_amount: Uint128(1_000),
+            description: None,
+            token: mock_sscrt(),
+            skip_confirmation_if_verified: None,
+            referrer: None,
+            cancellation_policy: None,
+            cancellation_penalty: None,
+            hash_timelock: None,
+            milestones: None,
+            attachment_hash: None,
         };
+        // === when the amount is the SSCRT fee
         let handle_msg = HandleMsg::Receive {
             sender: mock_user_address(),
             from: mock_user_address(),
             amount: mock_fee(),
             msg: to_binary(&receive_msg).unwrap(),
         };
-        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
-        handle_result.unwrap();
+        let handle_result = handle(&mut deps, mock_env(mock_silk().address, &[]), handle_msg);
+        // === * it raises an error
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Wrong amount received.".to_string(),
+                backtrace: None
+            }
+        );
 
-        // = when user tries to send payment for tx that does not eist
-        let receive_msg = ReceiveMsg::SendPayment { position: 1 };
+        // === when the amount is the fee token's fee
         let handle_msg = HandleMsg::Receive {
             sender: mock_user_address(),
             from: mock_user_address(),
-            amount: mock_fee(),
+            amount: Uint128(500),
             msg: to_binary(&receive_msg).unwrap(),
         };
-        // = * it raises an error
+        handle(&mut deps, mock_env(mock_silk().address, &[]), handle_msg).unwrap();
+        // === * it records the fee token in the Tx
+        let user_canonical = deps.api.canonical_address(&mock_user_address()).unwrap();
+        let tx = tx_at_position(&mut deps.storage, &user_canonical, 0).unwrap();
+        assert_eq!(tx.fee, Uint128(500));
+        assert_eq!(tx.fee_token, mock_silk());
+        assert_eq!(
+            fee_stats(&deps.storage, &mock_silk().address)
+                .unwrap()
+                .collected,
+            Uint128(500)
+        );
+
+        // ==== when the user cancels the Tx
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(0),
+            msg: to_binary(&ReceiveMsg::Cancel {
+                position: 0,
+                reason: None,
+            })
+            .unwrap(),
+        };
         let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
+        // ==== * it refunds the fee in the fee token
         assert_eq!(
-            handle_result.unwrap_err(),
-            StdError::GenericErr {
-                msg: "AppendStorage access out of bounds".to_string(),
-                backtrace: None
-            }
+            handle_result.unwrap().messages,
+            vec![snip20::transfer_msg(
+                mock_user_address(),
+                Uint128(500),
+                None,
+                BLOCK_SIZE,
+                mock_silk().contract_hash,
+                mock_silk().address,
+            )
+            .unwrap()]
+        );
+        assert_eq!(
+            fee_stats(&deps.storage, &mock_silk().address)
+                .unwrap()
+                .refunded,
+            Uint128(500)
         );
 
-        // = when user tries to send payment for tx that exists
-        let receive_msg = ReceiveMsg::SendPayment { position: 0 };
-        // == when user sends in an amount that does not match the tx
-        // == * it raises an error
+        // == when a user pays the fee for a claim in a fee token
+        let code_hash = Binary(hash_code("fee-token"));
         let handle_msg = HandleMsg::Receive {
             sender: mock_user_address(),
             from: mock_user_address(),
-            amount: Uint128(send_amount.u128() + 1),
-            msg: to_binary(&receive_msg).unwrap(),
+            amount: Uint128(500),
+            msg: to_binary(&ReceiveMsg::CreateClaim {
+                code_hash: code_hash.clone(),
+                send_amount: Uint128(1_000),
+                description: None,
+                token: mock_silk(),
+                expires_at: mock_expires_at(),
+            })
+            .unwrap(),
         };
-        let handle_result = handle(&mut deps, env.clone(), handle_msg);
-        // = * it raises an error
+        handle(&mut deps, mock_env(mock_silk().address, &[]), handle_msg).unwrap();
+        // === * it records the fee token in the claim
         assert_eq!(
-            handle_result.unwrap_err(),
-            StdError::GenericErr {
-                msg: "Wrong amount received.".to_string(),
-                backtrace: None
-            }
+            claim(&deps.storage, code_hash.as_slice())
+                .unwrap()
+                .unwrap()
+                .fee_token,
+            mock_silk()
         );
-        // = when user sends in the correct amount
+        // === when the user reclaims it
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            HandleMsg::Reclaim { code_hash },
+        );
+        // === * it refunds the fee in the fee token
+        assert_eq!(
+            handle_result.unwrap().messages,
+            vec![snip20::transfer_msg(
+                mock_user_address(),
+                Uint128(500),
+                None,
+                BLOCK_SIZE,
+                mock_silk().contract_hash,
+                mock_silk().address,
+            )
+            .unwrap()]
+        );
+
+        // == when a user pays the fees for a payment link in a fee token
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(1_000),
+            msg: to_binary(&ReceiveMsg::CreatePaymentLink {
+                code_hash: Binary(hash_code("fee-token")),
+                send_amount: Uint128(1_000),
+                description: None,
+                token: mock_silk(),
+                max_uses: Some(2),
+                referrer: None,
+            })
+            .unwrap(),
+        };
+        handle(&mut deps, mock_env(mock_silk().address, &[]), handle_msg).unwrap();
+        // === when the link is paid
         let handle_msg = HandleMsg::Receive {
             sender: mock_user_address(),
             from: mock_user_address(),
-            amount: send_amount,
-            msg: to_binary(&receive_msg).unwrap(),
+            amount: Uint128(1_000),
+            msg: to_binary(&ReceiveMsg::PayLink {
+                code: "fee-token".to_string(),
+            })
+            .unwrap(),
+        };
+        handle(&mut deps, mock_env(mock_silk().address, &[]), handle_msg).unwrap();
+        // === * it records the fee token in the Tx
+        let tx = tx_at_position(&mut deps.storage, &user_canonical, 1).unwrap();
+        assert_eq!(tx.fee, Uint128(500));
+        assert_eq!(tx.fee_token, mock_silk());
+        // === when the link is closed
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            HandleMsg::ClosePaymentLink {
+                code_hash: Binary(hash_code("fee-token")),
+            },
+        );
+        // === * it refunds the fee for the use that is left in the fee token
+        assert_eq!(
+            handle_result.unwrap().messages,
+            vec![snip20::transfer_msg(
+                mock_contract_initiator_address(),
+                Uint128(500),
+                None,
+                BLOCK_SIZE,
+                mock_silk().contract_hash,
+                mock_silk().address,
+            )
+            .unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_update_referral_share() {
+        let (_init_result, mut deps) = init_helper();
+        let referrer = mock_contract().address;
+        let handle_msg = HandleMsg::UpdateReferralShare {
+            referral_share_bps: 2_000,
         };
-        // == when user sends in the wrong token
+
+        // when called by non-admin
+        // * it raises an unauthorized error
         let handle_result = handle(
             &mut deps,
-            mock_env(mock_sscrt().address, &[]),
+            mock_env(mock_user_address(), &[]),
             handle_msg.clone(),
         );
-        // == * it raises an error
         assert_eq!(
             handle_result.unwrap_err(),
-            StdError::GenericErr {
-                msg: "Wrong token received.".to_string(),
-                backtrace: None
-            }
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // when admin calls this
+        // = when share is more than 100%
+        // = * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            HandleMsg::UpdateReferralShare {
+                referral_share_bps: 10_001,
+            },
         );
-        // == when user sends in the right token
-        let handle_result = handle(&mut deps, mock_env(mock_silk().address, &[]), handle_msg);
-        // ==== when user sends payment for Tx that is pending address confirmation
-        // ==== * it raises an error
         assert_eq!(
             handle_result.unwrap_err(),
             StdError::GenericErr {
-                msg: "Tx status at that position is incorrect.".to_string(),
+                msg: "Referral share can not be more than 10000 basis points.".to_string(),
                 backtrace: None
             }
         );
 
-        // ==== when user sends payment for Tx that is cancelled
-        let mut from_tx = tx_at_position(
-            &mut deps.storage,
-            &deps.api.canonical_address(&mock_user_address()).unwrap(),
-            0,
-        )
-        .unwrap();
-        let mut to_tx = tx_at_position(
-            &mut deps.storage,
-            &from_tx.to,
-            from_tx.other_storage_position,
+        // = when share is valid
+        // = * it updates the referral share
+        handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            handle_msg,
         )
         .unwrap();
-        from_tx.status = 2;
-        to_tx.status = 2;
-        update_tx(&mut deps.storage, &from_tx.from.clone(), from_tx.clone()).unwrap();
-        update_tx(&mut deps.storage, &to_tx.to.clone(), to_tx.clone()).unwrap();
-        // ==== * it raises an error
+        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        assert_eq!(config.referral_share_bps, 2_000);
+
+        // when creator sets themselves as referrer
+        // * it raises an error
+        let receive_msg = ReceiveMsg::CreateSendRequest {
+            address: mock_contract_initiator_address(),
+            send_amount: Uint128(555_555),
+            description: Some("Referred".to_string()),
+            token: mock_silk(),
+            skip_confirmation_if_verified: None,
+            referrer: Some(mock_user_address()),
+            cancellation_policy: None,
+            cancellation_penalty: None,
+            hash_timelock: None,
+            milestones: None,
+            attachment_hash: None,
+        };
         let handle_msg = HandleMsg::Receive {
             sender: mock_user_address(),
             from: mock_user_address(),
-            amount: send_amount,
+            amount: mock_fee(),
             msg: to_binary(&receive_msg).unwrap(),
         };
-        let handle_result = handle(&mut deps, mock_env(mock_silk().address, &[]), handle_msg);
+        let handle_result = handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg);
         assert_eq!(
             handle_result.unwrap_err(),
             StdError::GenericErr {
-                msg: "Tx status at that position is incorrect.".to_string(),
+                msg: "Referrer can not be the creator.".to_string(),
                 backtrace: None
             }
         );
 
-        // ==== when user sends payment for Tx that is already finalized
-        from_tx.status = 3;
-        to_tx.status = 3;
-        update_tx(&mut deps.storage, &from_tx.from.clone(), from_tx.clone()).unwrap();
-        update_tx(&mut deps.storage, &to_tx.to.clone(), to_tx.clone()).unwrap();
-        // ==== * it raises an error
+        // when a referred Tx is paid
+        let receive_msg = ReceiveMsg::CreateSendRequest {
+            address: mock_contract_initiator_address(),
+            send_amount: Uint128(555_555),
+            description: Some("Referred".to_string()),
+            token: mock_silk(),
+            skip_confirmation_if_verified: None,
+            referrer: Some(referrer.clone()),
+            cancellation_policy: None,
+            cancellation_penalty: None,
+            hash_timelock: None,
+            milestones: None,
+            attachment_hash: None,
+        };
         let handle_msg = HandleMsg::Receive {
             sender: mock_user_address(),
             from: mock_user_address(),
-            amount: send_amount,
+            amount: mock_fee(),
             msg: to_binary(&receive_msg).unwrap(),
         };
-        let handle_result = handle(&mut deps, mock_env(mock_silk().address, &[]), handle_msg);
-        assert_eq!(
-            handle_result.unwrap_err(),
-            StdError::GenericErr {
-                msg: "Tx status at that position is incorrect.".to_string(),
-                backtrace: None
-            }
-        );
-
-        // ==== when user sends payment for Tx that is pending payment
-        from_tx.status = 1;
-        to_tx.status = 1;
-        update_tx(&mut deps.storage, &from_tx.from.clone(), from_tx.clone()).unwrap();
-        update_tx(&mut deps.storage, &to_tx.to.clone(), to_tx).unwrap();
-        // ===== when user user is not the from for that Tx
-        // ===== * it raises an unauthorized error
-        let handle_msg = HandleMsg::Receive {
+        handle(&mut deps, mock_env(mock_sscrt().address, &[]), handle_msg).unwrap();
+        let confirm_msg = HandleMsg::Receive {
             sender: mock_contract_initiator_address(),
             from: mock_contract_initiator_address(),
-            amount: send_amount,
-            msg: to_binary(&receive_msg).unwrap(),
+            amount: Uint128(0),
+            msg: to_binary(&ReceiveMsg::ConfirmAddress { position: 0 }).unwrap(),
         };
-        let handle_result = handle(&mut deps, mock_env(mock_silk().address, &[]), handle_msg);
-        assert_eq!(
-            handle_result.unwrap_err(),
-            StdError::Unauthorized { backtrace: None }
-        );
-        // ===== when user is the the from for that Tx
-        // ===== * it sends payment to receiver
-        // ===== * it sends fee to treasury
+        handle(&mut deps, mock_env(mock_sscrt().address, &[]), confirm_msg).unwrap();
         let handle_msg = HandleMsg::Receive {
             sender: mock_user_address(),
             from: mock_user_address(),
-            amount: send_amount,
-            msg: to_binary(&receive_msg).unwrap(),
+            amount: Uint128(555_555),
+            msg: to_binary(&ReceiveMsg::SendPayment { position: 0 }).unwrap(),
         };
         let handle_result_unwrapped =
             handle(&mut deps, mock_env(mock_silk().address, &[]), handle_msg).unwrap();
-        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        // * it sends the referral share of the fee to the referrer
         assert_eq!(
             handle_result_unwrapped.messages,
             vec![
                 snip20::transfer_msg(
-                    config.treasury_address,
-                    from_tx.fee,
+                    referrer.clone(),
+                    Uint128(200_000),
                     None,
                     BLOCK_SIZE,
-                    config.sscrt.contract_hash,
-                    config.sscrt.address,
+                    mock_sscrt().contract_hash,
+                    mock_sscrt().address,
                 )
                 .unwrap(),
                 snip20::transfer_msg(
-                    deps.api.human_address(&from_tx.to).unwrap(),
-                    from_tx.amount,
+                    mock_contract_initiator_address(),
+                    Uint128(555_555),
                     None,
                     BLOCK_SIZE,
-                    from_tx.token.contract_hash,
-                    from_tx.token.address,
+                    mock_silk().contract_hash,
+                    mock_silk().address,
                 )
                 .unwrap()
             ]
         );
-        // ==== * it updates the status to finalized
-        let from_tx = tx_at_position(
-            &mut deps.storage,
-            &deps.api.canonical_address(&mock_user_address()).unwrap(),
-            0,
-        )
-        .unwrap();
-        let to_tx = tx_at_position(
-            &mut deps.storage,
-            &from_tx.to,
-            from_tx.other_storage_position,
-        )
-        .unwrap();
-        assert_eq!(from_tx.status, 3);
-        assert_eq!(to_tx.status, 3);
+        // * it accrues the rest of the fee for the treasury
+        let stats = fee_stats(&deps.storage, &mock_sscrt().address).unwrap();
+        assert_eq!(stats.referrals_paid, Uint128(200_000));
+        assert_eq!(stats.accrued, Uint128(800_000));
+        // * it records the referral earnings
+        assert_eq!(
+            referral_earnings(
+                &deps.storage,
+                &deps.api.canonical_address(&referrer).unwrap(),
+                &mock_sscrt().address
+            )
+            .unwrap(),
+            Uint128(200_000)
+        );
     }
 
     #[test]
-    fn test_update_fee() {
+    fn test_update_treasury_fee() {
         let (_init_result, mut deps) = init_helper();
         let env = mock_env(mock_user_address(), &[]);
-        let new_fee = Uint128(555);
+        let new_treasury_address = mock_contract().address;
 
         // when called by non-admin
         // * it raises an unauthorized error
-        let handle_msg = HandleMsg::UpdateFee { fee: new_fee };
+        let handle_msg = HandleMsg::UpdateTreasuryAddress {
+            address: new_treasury_address.clone(),
+        };
         let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
         assert_eq!(
             handle_result.unwrap_err(),
@@ -1564,31 +10567,240 @@ mod tests {
         let handle_result = handle(&mut deps, env, handle_msg);
         handle_result.unwrap();
         let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
-        assert_eq!(config.fee, new_fee)
+        assert_eq!(config.treasury_address, new_treasury_address)
     }
 
     #[test]
-    fn test_update_treasury_fee() {
+    fn test_update_treasury_recipients() {
         let (_init_result, mut deps) = init_helper();
-        let env = mock_env(mock_user_address(), &[]);
-        let new_treasury_address = mock_contract().address;
+        let treasury_recipients = vec![
+            TreasuryRecipient {
+                address: mock_treasury_address(),
+                weight: 1,
+            },
+            TreasuryRecipient {
+                address: mock_contract().address,
+                weight: 2,
+            },
+        ];
+        let handle_msg = HandleMsg::UpdateTreasuryRecipients {
+            treasury_recipients: treasury_recipients.clone(),
+        };
 
         // when called by non-admin
         // * it raises an unauthorized error
-        let handle_msg = HandleMsg::UpdateTreasuryAddress {
-            address: new_treasury_address.clone(),
-        };
-        let handle_result = handle(&mut deps, env.clone(), handle_msg.clone());
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            handle_msg.clone(),
+        );
         assert_eq!(
             handle_result.unwrap_err(),
             StdError::Unauthorized { backtrace: None }
         );
 
         // when admin calls this
+        // = when a recipient has no weight
+        // = * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            HandleMsg::UpdateTreasuryRecipients {
+                treasury_recipients: vec![TreasuryRecipient {
+                    address: mock_treasury_address(),
+                    weight: 0,
+                }],
+            },
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Treasury recipient weight must be greater than zero.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // = when recipients are valid
+        // = * it updates the treasury recipients
+        handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            handle_msg,
+        )
+        .unwrap();
+        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        assert_eq!(config.treasury_recipients, treasury_recipients);
+
+        // when fees are withdrawn without a recipient
+        // * it splits them between the recipients by weight
+        create_send_requests_helper(&mut deps, vec![Uint128(1)]);
+        let confirm_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(0),
+            msg: to_binary(&ReceiveMsg::ConfirmAddress { position: 0 }).unwrap(),
+        };
+        handle(&mut deps, mock_env(mock_sscrt().address, &[]), confirm_msg).unwrap();
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(1),
+            msg: to_binary(&ReceiveMsg::SendPayment { position: 0 }).unwrap(),
+        };
+        handle(&mut deps, mock_env(mock_silk().address, &[]), handle_msg).unwrap();
+        let handle_msg = HandleMsg::WithdrawFees {
+            token: mock_sscrt().address,
+            amount: mock_fee(),
+            recipient: None,
+        };
+        let handle_result_unwrapped = handle(
+            &mut deps,
+            mock_env(mock_contract_initiator_address(), &[]),
+            handle_msg,
+        )
+        .unwrap();
+        assert_eq!(
+            handle_result_unwrapped.messages,
+            vec![
+                snip20::transfer_msg(
+                    mock_treasury_address(),
+                    Uint128(333_333),
+                    None,
+                    BLOCK_SIZE,
+                    mock_sscrt().contract_hash,
+                    mock_sscrt().address,
+                )
+                .unwrap(),
+                snip20::transfer_msg(
+                    mock_contract().address,
+                    Uint128(666_667),
+                    None,
+                    BLOCK_SIZE,
+                    mock_sscrt().contract_hash,
+                    mock_sscrt().address,
+                )
+                .unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_withdraw_fees() {
+        let (_init_result, mut deps) = init_helper();
         let env = mock_env(mock_contract_initiator_address(), &[]);
+        create_send_requests_helper(&mut deps, vec![Uint128(1), Uint128(2)]);
+        let confirm_msg = HandleMsg::Receive {
+            sender: mock_contract_initiator_address(),
+            from: mock_contract_initiator_address(),
+            amount: Uint128(0),
+            msg: to_binary(&ReceiveMsg::ConfirmAddress { position: 0 }).unwrap(),
+        };
+        handle(&mut deps, mock_env(mock_sscrt().address, &[]), confirm_msg).unwrap();
+        let handle_msg = HandleMsg::Receive {
+            sender: mock_user_address(),
+            from: mock_user_address(),
+            amount: Uint128(1),
+            msg: to_binary(&ReceiveMsg::SendPayment { position: 0 }).unwrap(),
+        };
+        handle(&mut deps, mock_env(mock_silk().address, &[]), handle_msg).unwrap();
+        // * it records the fees collected and accrued
+        let stats = fee_stats(&deps.storage, &mock_sscrt().address).unwrap();
+        assert_eq!(stats.collected, Uint128(mock_fee().u128() * 2));
+        assert_eq!(stats.accrued, mock_fee());
+        let (daily_stats, total) =
+            get_daily_fee_stats(&deps.storage, &mock_sscrt().address, 0, 10).unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(daily_stats[0].day, env.block.time / SECONDS_PER_DAY);
+        assert_eq!(daily_stats[0].stats, stats);
+
+        // when called by non-admin
+        // * it raises an unauthorized error
+        let handle_msg = HandleMsg::WithdrawFees {
+            token: mock_sscrt().address,
+            amount: mock_fee(),
+            recipient: Some(mock_contract().address),
+        };
+        let handle_result = handle(
+            &mut deps,
+            mock_env(mock_user_address(), &[]),
+            handle_msg.clone(),
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::Unauthorized { backtrace: None }
+        );
+
+        // when admin calls this
+        // = when the token is not registered
+        // = * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            env.clone(),
+            HandleMsg::WithdrawFees {
+                token: HumanAddr::from("unknown"),
+                amount: Uint128(0),
+                recipient: None,
+            },
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Token is not registered.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // = when amount is more than the accrued fees
+        // = * it raises an error
+        let handle_result = handle(
+            &mut deps,
+            env.clone(),
+            HandleMsg::WithdrawFees {
+                token: mock_sscrt().address,
+                amount: Uint128(mock_fee().u128() + 1),
+                recipient: None,
+            },
+        );
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Amount is more than the withdrawable fees.".to_string(),
+                backtrace: None
+            }
+        );
+
+        // = when amount is withdrawable
+        let handle_result_unwrapped = handle(&mut deps, env.clone(), handle_msg.clone()).unwrap();
+        // = * it sends the fees to the recipient
+        assert_eq!(
+            handle_result_unwrapped.messages,
+            vec![snip20::transfer_msg(
+                mock_contract().address,
+                mock_fee(),
+                None,
+                BLOCK_SIZE,
+                mock_sscrt().contract_hash,
+                mock_sscrt().address,
+            )
+            .unwrap()]
+        );
+        // = * it records the withdrawal
+        assert_eq!(
+            fee_stats(&deps.storage, &mock_sscrt().address)
+                .unwrap()
+                .withdrawn,
+            mock_fee()
+        );
+
+        // when the accrued fees have already been withdrawn
+        // * it raises an error
         let handle_result = handle(&mut deps, env, handle_msg);
-        handle_result.unwrap();
-        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
-        assert_eq!(config.treasury_address, new_treasury_address)
+        assert_eq!(
+            handle_result.unwrap_err(),
+            StdError::GenericErr {
+                msg: "Amount is more than the withdrawable fees.".to_string(),
+                backtrace: None
+            }
+        );
     }
 }
@@ -0,0 +1,172 @@
+use crate::constants::{
+    PREFIX_DAILY_FEE_STATS, PREFIX_FEE_DISCOUNT_KEYS, PREFIX_FEE_EXEMPTIONS, PREFIX_FEE_STATS,
+    PREFIX_REFERRAL_EARNINGS,
+};
+use cosmwasm_std::{CanonicalAddr, HumanAddr, ReadonlyStorage, StdResult, Storage, Uint128};
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+use schemars::JsonSchema;
+use secret_toolkit::storage::{AppendStore, AppendStoreMut, TypedStore, TypedStoreMut};
+use serde::{Deserialize, Serialize};
+
+pub const SECONDS_PER_DAY: u64 = 86_400;
+
+// Accrued fees are the treasury's share of fees for finalized Txs, which can be withdrawn
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Default, PartialEq)]
+pub struct FeeStats {
+    pub collected: Uint128,
+    pub refunded: Uint128,
+    pub referrals_paid: Uint128,
+    pub accrued: Uint128,
+    pub withdrawn: Uint128,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct DailyFeeStats {
+    pub day: u64,
+    pub stats: FeeStats,
+}
+
+// Storage functions:
+pub fn is_fee_exempt<S: ReadonlyStorage>(storage: &S, address: &CanonicalAddr) -> StdResult<bool> {
+    let store = ReadonlyPrefixedStorage::new(PREFIX_FEE_EXEMPTIONS, storage);
+    let exempt: Option<bool> = TypedStore::attach(&store).may_load(address.as_slice())?;
+
+    Ok(exempt.unwrap_or(false))
+}
+
+pub fn set_fee_exempt<S: Storage>(
+    storage: &mut S,
+    address: &CanonicalAddr,
+    exempt: bool,
+) -> StdResult<()> {
+    let mut store = PrefixedStorage::new(PREFIX_FEE_EXEMPTIONS, storage);
+    if exempt {
+        TypedStoreMut::attach(&mut store).store(address.as_slice(), &exempt)
+    } else {
+        TypedStoreMut::<bool, _>::attach(&mut store).remove(address.as_slice());
+        Ok(())
+    }
+}
+
+// The viewing key the owner registered so their balance of a discount token can be checked
+pub fn fee_discount_key<S: ReadonlyStorage>(
+    storage: &S,
+    owner: &CanonicalAddr,
+    token_address: &HumanAddr,
+) -> StdResult<Option<String>> {
+    let store =
+        ReadonlyPrefixedStorage::multilevel(&[PREFIX_FEE_DISCOUNT_KEYS, owner.as_slice()], storage);
+    TypedStore::attach(&store).may_load(token_address.0.as_bytes())
+}
+
+pub fn set_fee_discount_key<S: Storage>(
+    storage: &mut S,
+    owner: &CanonicalAddr,
+    token_address: &HumanAddr,
+    key: &String,
+) -> StdResult<()> {
+    let mut store =
+        PrefixedStorage::multilevel(&[PREFIX_FEE_DISCOUNT_KEYS, owner.as_slice()], storage);
+    TypedStoreMut::attach(&mut store).store(token_address.0.as_bytes(), key)
+}
+
+// Referral earnings are kept per fee token
+pub fn add_referral_earnings<S: Storage>(
+    storage: &mut S,
+    referrer: &CanonicalAddr,
+    token_address: &HumanAddr,
+    amount: Uint128,
+) -> StdResult<()> {
+    let earnings = referral_earnings(storage, referrer, token_address)?;
+    let mut store =
+        PrefixedStorage::multilevel(&[PREFIX_REFERRAL_EARNINGS, referrer.as_slice()], storage);
+    TypedStoreMut::attach(&mut store).store(token_address.0.as_bytes(), &(earnings + amount))
+}
+
+pub fn referral_earnings<S: ReadonlyStorage>(
+    storage: &S,
+    referrer: &CanonicalAddr,
+    token_address: &HumanAddr,
+) -> StdResult<Uint128> {
+    let store = ReadonlyPrefixedStorage::multilevel(
+        &[PREFIX_REFERRAL_EARNINGS, referrer.as_slice()],
+        storage,
+    );
+    let earnings: Option<Uint128> =
+        TypedStore::attach(&store).may_load(token_address.0.as_bytes())?;
+
+    Ok(earnings.unwrap_or(Uint128(0)))
+}
+
+pub fn fee_stats<S: ReadonlyStorage>(
+    storage: &S,
+    token_address: &HumanAddr,
+) -> StdResult<FeeStats> {
+    let store = ReadonlyPrefixedStorage::new(PREFIX_FEE_STATS, storage);
+    let stats: Option<FeeStats> =
+        TypedStore::attach(&store).may_load(token_address.0.as_bytes())?;
+
+    Ok(stats.unwrap_or_default())
+}
+
+pub fn get_daily_fee_stats<S: ReadonlyStorage>(
+    storage: &S,
+    token_address: &HumanAddr,
+    page: u32,
+    page_size: u32,
+) -> StdResult<(Vec<DailyFeeStats>, u64)> {
+    let store = ReadonlyPrefixedStorage::multilevel(
+        &[PREFIX_DAILY_FEE_STATS, token_address.0.as_bytes()],
+        storage,
+    );
+
+    // Try to access the daily stats for the token.
+    // If they don't exist yet, return an empty list.
+    let store = AppendStore::<DailyFeeStats, _, _>::attach(&store);
+    let store = if let Some(result) = store {
+        result?
+    } else {
+        return Ok((vec![], 0));
+    };
+
+    let daily_stats: StdResult<Vec<DailyFeeStats>> = store
+        .iter()
+        .rev()
+        .skip((page * page_size) as _)
+        .take(page_size as _)
+        .collect();
+    daily_stats.map(|daily_stats| (daily_stats, store.len() as u64))
+}
+
+// Apply the same change to the token's running totals and to the bucket for the day of `time`
+pub fn record_fees<S: Storage, F: Fn(&mut FeeStats) -> StdResult<()>>(
+    storage: &mut S,
+    token_address: &HumanAddr,
+    time: u64,
+    update: F,
+) -> StdResult<()> {
+    let mut stats = fee_stats(storage, token_address)?;
+    update(&mut stats)?;
+    let mut store = PrefixedStorage::new(PREFIX_FEE_STATS, storage);
+    TypedStoreMut::attach(&mut store).store(token_address.0.as_bytes(), &stats)?;
+
+    let day = time / SECONDS_PER_DAY;
+    let mut store = PrefixedStorage::multilevel(
+        &[PREFIX_DAILY_FEE_STATS, token_address.0.as_bytes()],
+        storage,
+    );
+    let mut store = AppendStoreMut::<DailyFeeStats, _, _>::attach_or_create(&mut store)?;
+    if !store.is_empty() {
+        let mut daily_stats = store.get_at(store.len() - 1)?;
+        if daily_stats.day == day {
+            update(&mut daily_stats.stats)?;
+            return store.set_at(store.len() - 1, &daily_stats);
+        }
+    }
+    let mut daily_stats = DailyFeeStats {
+        day,
+        stats: FeeStats::default(),
+    };
+    update(&mut daily_stats.stats)?;
+    store.push(&daily_stats)
+}
@@ -1,10 +1,20 @@
+mod acceptance_policy;
+mod address_book;
 mod asset;
 mod authorize;
+mod claim;
 mod constants;
 pub mod contract;
+mod fees;
 pub mod msg;
+mod organizations;
+mod payment_link;
+mod shape;
+mod spending_limits;
 pub mod state;
+mod stats;
 mod transaction_history;
+mod viewers;
 
 #[cfg(target_arch = "wasm32")]
 mod wasm {
@@ -1,5 +1,16 @@
-use crate::state::SecretContract;
-use crate::transaction_history::HumanizedTx;
+use crate::acceptance_policy::AcceptancePolicy;
+use crate::address_book::AddressBookEntry;
+use crate::claim::Claim;
+use crate::fees::{DailyFeeStats, FeeStats};
+use crate::organizations::{
+    ApprovalPolicy, Organization, OrganizationAuditEntry, OrganizationRole, PaymentApproval,
+};
+use crate::payment_link::PaymentLink;
+use crate::spending_limits::{SpendingLimitSettings, TokenSpendingLimit};
+use crate::state::{FeeDiscount, FeeToken, SecretContract, TreasuryRecipient};
+use crate::stats::{DailyStats, Stats};
+use crate::transaction_history::{CancellationPolicy, HashTimelock, HumanizedTx, Milestone};
+use crate::viewers::ViewerScope;
 use cosmwasm_std::{Binary, HumanAddr, Uint128};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -16,52 +27,361 @@ pub struct InitMsg {
 #[serde(rename_all = "snake_case")]
 pub enum HandleMsg {
     AcceptNewAdminNomination {},
+    AcknowledgeRequest {
+        position: u32,
+    },
+    ApprovePayment {
+        organization: HumanAddr,
+        position: u32,
+    },
+    BlockAddress {
+        address: HumanAddr,
+    },
+    // Cancel, ConfirmAddress, CreateReceiveRequest, CreateSendRequest and SendPayment
+    // pull what is owed with transfer_from against an allowance given to this contract,
+    // instead of being sent with the token's Send
+    Cancel {
+        position: u32,
+        reason: Option<String>,
+    },
+    CancelMilestone {
+        position: u32,
+        index: u32,
+    },
+    Claim {
+        code: String,
+        recipient: HumanAddr,
+    },
+    ClaimHtlc {
+        position: u32,
+        preimage: Binary,
+    },
+    ClosePaymentLink {
+        code_hash: Binary,
+    },
+    ConfirmAddress {
+        position: u32,
+    },
+    CreateOrganization {
+        name: String,
+    },
+    CreateReceiveRequest {
+        address: HumanAddr,
+        send_amount: Uint128,
+        description: Option<String>,
+        token: SecretContract,
+        referrer: Option<HumanAddr>,
+        cancellation_policy: Option<CancellationPolicy>,
+        cancellation_penalty: Option<Uint128>,
+        attachment_hash: Option<Binary>,
+        fee_token: Option<HumanAddr>,
+    },
+    CreateSendRequest {
+        address: HumanAddr,
+        send_amount: Uint128,
+        description: Option<String>,
+        token: SecretContract,
+        skip_confirmation_if_verified: Option<bool>,
+        referrer: Option<HumanAddr>,
+        cancellation_policy: Option<CancellationPolicy>,
+        cancellation_penalty: Option<Uint128>,
+        hash_timelock: Option<HashTimelock>,
+        milestones: Option<Vec<Milestone>>,
+        attachment_hash: Option<Binary>,
+        fee_token: Option<HumanAddr>,
+    },
+    Decline {
+        position: u32,
+        reason: Option<String>,
+    },
+    GrantViewer {
+        address: HumanAddr,
+        scope: ViewerScope,
+        expires_at: Option<u64>,
+    },
     NominateNewAdmin {
         address: HumanAddr,
     },
+    OnBehalfOf {
+        organization: HumanAddr,
+        msg: Box<HandleMsg>,
+    },
+    PruneHistory {
+        before_position: Option<u32>,
+        before_time: Option<u64>,
+    },
     Receive {
         sender: HumanAddr,
         from: HumanAddr,
         amount: Uint128,
         msg: Binary,
     },
+    Reclaim {
+        code_hash: Binary,
+    },
+    RefundHtlc {
+        position: u32,
+    },
+    RejectPayment {
+        organization: HumanAddr,
+        position: u32,
+    },
+    ReleaseMilestone {
+        position: u32,
+        index: u32,
+    },
+    RevokeViewer {
+        address: HumanAddr,
+    },
+    SendPayment {
+        position: u32,
+    },
+    SetAcceptancePolicy {
+        contacts_only: bool,
+        minimum_amount: Option<Uint128>,
+        allowed_tokens: Option<Vec<HumanAddr>>,
+        acknowledge_receive_requests: Option<bool>,
+    },
+    SetAddressBookLabel {
+        address: HumanAddr,
+        label: Option<String>,
+    },
+    SetApprovalPolicies {
+        organization: HumanAddr,
+        approval_policies: Vec<ApprovalPolicy>,
+    },
+    SetFeeDiscountKey {
+        token: HumanAddr,
+        key: String,
+    },
+    SetFeeExemption {
+        address: HumanAddr,
+        exempt: bool,
+    },
+    SetMemo {
+        position: u32,
+        memo: Option<String>,
+    },
+    SetSpendingLimits {
+        token_limits: Vec<TokenSpendingLimit>,
+        cooling_off_period: Option<u64>,
+    },
+    SetOrganizationMember {
+        organization: HumanAddr,
+        address: HumanAddr,
+        role: Option<OrganizationRole>,
+    },
+    UnblockAddress {
+        address: HumanAddr,
+    },
+    UpdateConstantShape {
+        constant_shape: bool,
+    },
     UpdateFee {
         fee: Uint128,
     },
+    UpdateFeeDiscounts {
+        fee_discounts: Vec<FeeDiscount>,
+    },
+    UpdateFeeTokens {
+        fee_tokens: Vec<FeeToken>,
+    },
+    UpdateReferralShare {
+        referral_share_bps: u16,
+    },
     UpdateTreasuryAddress {
         address: HumanAddr,
     },
+    UpdateTreasuryRecipients {
+        treasury_recipients: Vec<TreasuryRecipient>,
+    },
+    WithdrawFees {
+        token: HumanAddr,
+        amount: Uint128,
+        recipient: Option<HumanAddr>,
+    },
 }
 
 #[derive(Serialize, Deserialize, JsonSchema, Debug)]
 #[serde(rename_all = "snake_case")]
+pub enum HandleAnswer {
+    CreateOrganization { address: HumanAddr },
+    CreateSendRequests { positions: Vec<u32> },
+    PruneHistory { pruned: u32 },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
 pub enum ReceiveMsg {
+    BatchCancel {
+        positions: Vec<u32>,
+        reason: Option<String>,
+    },
+    BatchConfirmAddress {
+        positions: Vec<u32>,
+    },
+    BatchSendPayment {
+        positions: Vec<u32>,
+    },
     Cancel {
         position: u32,
+        reason: Option<String>,
     },
     ConfirmAddress {
         position: u32,
     },
+    CreateClaim {
+        code_hash: Binary,
+        send_amount: Uint128,
+        description: Option<String>,
+        token: SecretContract,
+        expires_at: u64,
+    },
+    CreatePaymentLink {
+        code_hash: Binary,
+        send_amount: Uint128,
+        description: Option<String>,
+        token: SecretContract,
+        max_uses: Option<u32>,
+        referrer: Option<HumanAddr>,
+    },
     CreateReceiveRequest {
         address: HumanAddr,
         send_amount: Uint128,
         description: Option<String>,
         token: SecretContract,
+        referrer: Option<HumanAddr>,
+        cancellation_policy: Option<CancellationPolicy>,
+        cancellation_penalty: Option<Uint128>,
+        attachment_hash: Option<Binary>,
     },
     CreateSendRequest {
         address: HumanAddr,
         send_amount: Uint128,
         description: Option<String>,
         token: SecretContract,
+        skip_confirmation_if_verified: Option<bool>,
+        referrer: Option<HumanAddr>,
+        cancellation_policy: Option<CancellationPolicy>,
+        cancellation_penalty: Option<Uint128>,
+        hash_timelock: Option<HashTimelock>,
+        milestones: Option<Vec<Milestone>>,
+        attachment_hash: Option<Binary>,
+    },
+    CreateSendRequests {
+        requests: Vec<SendRequest>,
+    },
+    FundClaim {
+        code_hash: Binary,
+    },
+    OnBehalfOf {
+        organization: HumanAddr,
+        msg: Box<ReceiveMsg>,
+    },
+    PayLink {
+        code: String,
+    },
+    Refund {
+        position: u32,
     },
     SendPayment {
         position: u32,
     },
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ClaimRequest {
+    pub code_hash: Binary,
+    pub send_amount: Uint128,
+    pub description: Option<String>,
+    pub token: SecretContract,
+    pub expires_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PaymentLinkRequest {
+    pub code_hash: Binary,
+    pub send_amount: Uint128,
+    pub description: Option<String>,
+    pub token: SecretContract,
+    pub max_uses: Option<u32>,
+    pub referrer: Option<HumanAddr>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReceiveRequest {
+    pub address: HumanAddr,
+    pub send_amount: Uint128,
+    pub description: Option<String>,
+    pub token: SecretContract,
+    pub referrer: Option<HumanAddr>,
+    pub cancellation_policy: Option<CancellationPolicy>,
+    pub cancellation_penalty: Option<Uint128>,
+    pub attachment_hash: Option<Binary>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SendRequest {
+    pub address: HumanAddr,
+    pub send_amount: Uint128,
+    pub description: Option<String>,
+    pub token: SecretContract,
+    pub skip_confirmation_if_verified: Option<bool>,
+    pub referrer: Option<HumanAddr>,
+    pub cancellation_policy: Option<CancellationPolicy>,
+    pub cancellation_penalty: Option<Uint128>,
+    pub hash_timelock: Option<HashTimelock>,
+    pub milestones: Option<Vec<Milestone>>,
+    pub attachment_hash: Option<Binary>,
+}
+
 #[derive(Serialize, Deserialize, JsonSchema, Debug)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryAnswer {
+    AcceptancePolicy {
+        policy: AcceptancePolicy,
+    },
+    AddressBook {
+        entries: Vec<AddressBookEntry>,
+        total: Option<u64>,
+    },
+    Claim {
+        claim: Claim,
+    },
+    DailyStats {
+        daily_stats: Vec<DailyStats>,
+        total: Option<u64>,
+    },
+    EffectiveFee {
+        fee: Uint128,
+    },
+    FeeStats {
+        stats: FeeStats,
+        daily_stats: Vec<DailyFeeStats>,
+        total: Option<u64>,
+    },
+    Organization {
+        organization: Organization,
+    },
+    OrganizationAuditTrail {
+        entries: Vec<OrganizationAuditEntry>,
+        total: Option<u64>,
+    },
+    PaymentApprovals {
+        approvals: Vec<PaymentApproval>,
+    },
+    PaymentLink {
+        payment_link: PaymentLink,
+    },
+    ReferralEarnings {
+        amount: Uint128,
+    },
+    SpendingLimits {
+        settings: SpendingLimitSettings,
+    },
+    Stats {
+        stats: Stats,
+    },
     Txs {
         txs: Vec<HumanizedTx>,
         total: Option<u64>,
@@ -71,11 +391,75 @@ pub enum QueryAnswer {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
+    AcceptancePolicy {
+        address: HumanAddr,
+        key: String,
+    },
+    AddressBook {
+        address: HumanAddr,
+        key: String,
+        page: u32,
+        page_size: u32,
+    },
+    Claim {
+        code: String,
+    },
     Config {},
+    DailyStats {
+        key: String,
+        page: u32,
+        page_size: u32,
+    },
+    EffectiveFee {
+        address: HumanAddr,
+        key: String,
+        token: Option<HumanAddr>,
+    },
+    FeeStats {
+        key: String,
+        token: HumanAddr,
+        page: u32,
+        page_size: u32,
+    },
+    Organization {
+        organization: HumanAddr,
+        address: HumanAddr,
+        key: String,
+    },
+    OrganizationAuditTrail {
+        organization: HumanAddr,
+        address: HumanAddr,
+        key: String,
+        page: u32,
+        page_size: u32,
+    },
+    PaymentApprovals {
+        organization: HumanAddr,
+        address: HumanAddr,
+        key: String,
+        position: u32,
+    },
+    PaymentLink {
+        code: String,
+    },
+    ReferralEarnings {
+        address: HumanAddr,
+        key: String,
+        token: Option<HumanAddr>,
+    },
+    SpendingLimits {
+        address: HumanAddr,
+        key: String,
+        time: u64,
+    },
+    Stats {},
     Txs {
         address: HumanAddr,
         key: String,
         page: u32,
         page_size: u32,
+        fixed_size: Option<bool>,
+        viewer: Option<HumanAddr>,
+        time: Option<u64>,
     },
 }
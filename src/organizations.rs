@@ -0,0 +1,248 @@
+use crate::constants::{
+    ORGANIZATION_COUNT_KEY, PREFIX_ESCROWED_PAYMENTS, PREFIX_ORGANIZATIONS,
+    PREFIX_ORGANIZATION_AUDIT_TRAILS, PREFIX_PAYMENT_APPROVALS,
+};
+use crate::msg::{HandleMsg, ReceiveMsg};
+use cosmwasm_std::{
+    Binary, CanonicalAddr, HumanAddr, ReadonlyStorage, StdError, StdResult, Storage, Uint128,
+};
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+use schemars::JsonSchema;
+use secret_toolkit::storage::{AppendStore, AppendStoreMut, TypedStore, TypedStoreMut};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+// A shared account whose Txs are created and paid by its members.
+// The admin manages the members and receives any refunds owed to the organization.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct Organization {
+    pub address: HumanAddr,
+    pub name: String,
+    pub admin: HumanAddr,
+    pub members: Vec<OrganizationMember>,
+    pub approval_policies: Vec<ApprovalPolicy>,
+}
+impl Organization {
+    // The number of approvals a payment needs before it is released, if any
+    pub fn required_approvals(&self, token: &HumanAddr, amount: Uint128) -> Option<u32> {
+        self.approval_policies
+            .iter()
+            .find(|policy| &policy.token == token && amount > policy.threshold)
+            .map(|policy| policy.required_approvals)
+    }
+
+    pub fn role(&self, address: &HumanAddr) -> Option<OrganizationRole> {
+        if address == &self.admin {
+            return Some(OrganizationRole::Approver);
+        }
+
+        self.members
+            .iter()
+            .find(|member| &member.address == address)
+            .map(|member| member.role)
+    }
+
+    pub fn verify_role(&self, address: &HumanAddr, role: OrganizationRole) -> StdResult<()> {
+        match self.role(address) {
+            Some(member_role) if member_role >= role => Ok(()),
+            _ => Err(StdError::unauthorized()),
+        }
+    }
+}
+
+// Payments of token above the threshold are escrowed until that many approvers approve them
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct ApprovalPolicy {
+    pub token: HumanAddr,
+    pub threshold: Uint128,
+    pub required_approvals: u32,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct PaymentApproval {
+    pub approver: HumanAddr,
+    pub block_time: u64,
+}
+
+// The member who paid a payment awaiting approval and when, so that a rejection can undo it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EscrowedPayment {
+    pub payer: HumanAddr,
+    pub block_time: u64,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct OrganizationMember {
+    pub address: HumanAddr,
+    pub role: OrganizationRole,
+}
+
+// Each role can do everything the roles before it can
+#[derive(
+    Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum OrganizationRole {
+    Viewer,
+    Requester,
+    Payer,
+    Approver,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct OrganizationAuditEntry {
+    pub member: HumanAddr,
+    pub action: OrganizationAction,
+    pub block_time: u64,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OrganizationAction {
+    Handle { msg: HandleMsg },
+    Receive { amount: Uint128, msg: ReceiveMsg },
+}
+
+// Storage functions:
+pub fn organization<S: ReadonlyStorage>(
+    storage: &S,
+    address: &CanonicalAddr,
+) -> StdResult<Option<Organization>> {
+    let store = ReadonlyPrefixedStorage::new(PREFIX_ORGANIZATIONS, storage);
+    TypedStore::attach(&store).may_load(address.as_slice())
+}
+
+pub fn store_organization<S: Storage>(
+    storage: &mut S,
+    address: &CanonicalAddr,
+    organization: &Organization,
+) -> StdResult<()> {
+    let mut store = PrefixedStorage::new(PREFIX_ORGANIZATIONS, storage);
+    TypedStoreMut::attach(&mut store).store(address.as_slice(), organization)
+}
+
+// Organizations get an address derived from the contract address and a counter,
+// so that nobody holds a key for it
+pub fn next_organization_address<S: Storage>(
+    storage: &mut S,
+    contract: &CanonicalAddr,
+) -> StdResult<CanonicalAddr> {
+    let count: Option<u64> = TypedStore::attach(storage).may_load(ORGANIZATION_COUNT_KEY)?;
+    let count = count.unwrap_or(0);
+    TypedStoreMut::attach(storage).store(ORGANIZATION_COUNT_KEY, &(count + 1))?;
+
+    let seed = [contract.as_slice(), &count.to_be_bytes()].concat();
+    let digest = format!("{:x}", Sha256::digest(&seed));
+    Ok(CanonicalAddr(Binary(
+        digest.as_bytes()[..contract.len()].to_vec(),
+    )))
+}
+
+pub fn payment_approvals<S: ReadonlyStorage>(
+    storage: &S,
+    organization: &CanonicalAddr,
+    position: u32,
+) -> StdResult<Vec<PaymentApproval>> {
+    let store = ReadonlyPrefixedStorage::multilevel(
+        &[PREFIX_PAYMENT_APPROVALS, organization.as_slice()],
+        storage,
+    );
+    let approvals: Option<Vec<PaymentApproval>> =
+        TypedStore::attach(&store).may_load(&position.to_be_bytes())?;
+
+    Ok(approvals.unwrap_or_default())
+}
+
+pub fn set_payment_approvals<S: Storage>(
+    storage: &mut S,
+    organization: &CanonicalAddr,
+    position: u32,
+    approvals: &[PaymentApproval],
+) -> StdResult<()> {
+    let mut store = PrefixedStorage::multilevel(
+        &[PREFIX_PAYMENT_APPROVALS, organization.as_slice()],
+        storage,
+    );
+    if approvals.is_empty() {
+        TypedStoreMut::<Vec<PaymentApproval>, _>::attach(&mut store)
+            .remove(&position.to_be_bytes());
+        Ok(())
+    } else {
+        TypedStoreMut::attach(&mut store).store(&position.to_be_bytes(), &approvals.to_vec())
+    }
+}
+
+pub fn escrowed_payment<S: ReadonlyStorage>(
+    storage: &S,
+    organization: &CanonicalAddr,
+    position: u32,
+) -> StdResult<Option<EscrowedPayment>> {
+    let store = ReadonlyPrefixedStorage::multilevel(
+        &[PREFIX_ESCROWED_PAYMENTS, organization.as_slice()],
+        storage,
+    );
+    TypedStore::attach(&store).may_load(&position.to_be_bytes())
+}
+
+pub fn set_escrowed_payment<S: Storage>(
+    storage: &mut S,
+    organization: &CanonicalAddr,
+    position: u32,
+    escrowed_payment: Option<&EscrowedPayment>,
+) -> StdResult<()> {
+    let mut store = PrefixedStorage::multilevel(
+        &[PREFIX_ESCROWED_PAYMENTS, organization.as_slice()],
+        storage,
+    );
+    match escrowed_payment {
+        Some(escrowed_payment) => {
+            TypedStoreMut::attach(&mut store).store(&position.to_be_bytes(), escrowed_payment)
+        }
+        None => {
+            TypedStoreMut::<EscrowedPayment, _>::attach(&mut store).remove(&position.to_be_bytes());
+            Ok(())
+        }
+    }
+}
+
+pub fn append_audit_entry<S: Storage>(
+    storage: &mut S,
+    organization: &CanonicalAddr,
+    entry: &OrganizationAuditEntry,
+) -> StdResult<()> {
+    let mut store = PrefixedStorage::multilevel(
+        &[PREFIX_ORGANIZATION_AUDIT_TRAILS, organization.as_slice()],
+        storage,
+    );
+    let mut store = AppendStoreMut::attach_or_create(&mut store)?;
+    store.push(entry)
+}
+
+pub fn get_audit_trail<S: ReadonlyStorage>(
+    storage: &S,
+    organization: &CanonicalAddr,
+    page: u32,
+    page_size: u32,
+) -> StdResult<(Vec<OrganizationAuditEntry>, u64)> {
+    let store = ReadonlyPrefixedStorage::multilevel(
+        &[PREFIX_ORGANIZATION_AUDIT_TRAILS, organization.as_slice()],
+        storage,
+    );
+
+    // Try to access the audit trail for the organization.
+    // If it doesn't exist yet, return an empty list.
+    let store = AppendStore::<OrganizationAuditEntry, _, _>::attach(&store);
+    let store = if let Some(result) = store {
+        result?
+    } else {
+        return Ok((vec![], 0));
+    };
+
+    let entries: StdResult<Vec<OrganizationAuditEntry>> = store
+        .iter()
+        .rev()
+        .skip((page * page_size) as _)
+        .take(page_size as _)
+        .collect();
+    entries.map(|entries| (entries, store.len() as u64))
+}
@@ -0,0 +1,53 @@
+use crate::constants::PREFIX_PAYMENT_LINKS;
+use crate::state::{hash_code, SecretContract};
+use cosmwasm_std::{HumanAddr, ReadonlyStorage, StdResult, Storage, Uint128};
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+use schemars::JsonSchema;
+use secret_toolkit::storage::{TypedStore, TypedStoreMut};
+use serde::{Deserialize, Serialize};
+
+// A receive request without a payer, which can be paid by whoever knows the code
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct PaymentLink {
+    pub receiver: HumanAddr,
+    pub send_amount: Uint128,
+    pub token: SecretContract,
+    pub description: Option<String>,
+    pub referrer: Option<HumanAddr>,
+    // Fee paid per use when the link was created
+    pub fee: Uint128,
+    pub fee_token: SecretContract,
+    pub uses: u32,
+    pub max_uses: u32,
+}
+
+// Storage functions:
+// Payment links are stored under the SHA-256 hash of their code
+pub fn payment_link<S: ReadonlyStorage>(
+    storage: &S,
+    code_hash: &[u8],
+) -> StdResult<Option<PaymentLink>> {
+    let store = ReadonlyPrefixedStorage::new(PREFIX_PAYMENT_LINKS, storage);
+    TypedStore::attach(&store).may_load(code_hash)
+}
+
+pub fn payment_link_by_code<S: ReadonlyStorage>(
+    storage: &S,
+    code: &str,
+) -> StdResult<Option<PaymentLink>> {
+    payment_link(storage, &hash_code(code))
+}
+
+pub fn remove_payment_link<S: Storage>(storage: &mut S, code_hash: &[u8]) {
+    let mut store = PrefixedStorage::new(PREFIX_PAYMENT_LINKS, storage);
+    TypedStoreMut::<PaymentLink, _>::attach(&mut store).remove(code_hash);
+}
+
+pub fn store_payment_link<S: Storage>(
+    storage: &mut S,
+    code_hash: &[u8],
+    payment_link: &PaymentLink,
+) -> StdResult<()> {
+    let mut store = PrefixedStorage::new(PREFIX_PAYMENT_LINKS, storage);
+    TypedStoreMut::attach(&mut store).store(code_hash, payment_link)
+}
@@ -0,0 +1,95 @@
+use crate::constants::SHAPE_PADDING_KEY;
+use cosmwasm_std::{Querier, QuerierResult, ReadonlyStorage, StdError, StdResult, Storage};
+use std::cell::Cell;
+
+// Status transitions are padded up to this many storage reads, storage writes, bytes written
+// and messages when the contract runs in constant shape mode. They cover the heaviest transition,
+// a cancellation of Txs with every text, token and milestone field at its maximum size.
+pub const SHAPE_MESSAGES: usize = 2;
+pub const SHAPE_READS: u32 = 32;
+pub const SHAPE_WRITES: u32 = 16;
+pub const SHAPE_WRITTEN_BYTES: usize = 8192;
+
+// Counts the storage accesses of a handle so that they can be padded to a constant shape
+pub struct CountingStorage<'a, S: Storage> {
+    storage: &'a mut S,
+    // Reads go through &self, so their counter needs interior mutability
+    reads: Cell<u32>,
+    writes: u32,
+    // Keys and values both count towards the gas used by a write
+    written_bytes: usize,
+}
+impl<'a, S: Storage> CountingStorage<'a, S> {
+    pub fn new(storage: &'a mut S) -> Self {
+        Self {
+            storage,
+            reads: Cell::new(0),
+            writes: 0,
+            written_bytes: 0,
+        }
+    }
+
+    pub fn reads(&self) -> u32 {
+        self.reads.get()
+    }
+
+    pub fn writes(&self) -> u32 {
+        self.writes
+    }
+
+    pub fn written_bytes(&self) -> usize {
+        self.written_bytes
+    }
+
+    // Read and write a decoy key until the totals reach the constant shape. The bytes missing
+    // are spread over the decoy writes, so that they are sized like the records they stand in for.
+    // A handle beyond the constant shape would stand out, so it fails instead.
+    pub fn pad(&mut self) -> StdResult<()> {
+        if self.reads() > SHAPE_READS
+            || self.writes() >= SHAPE_WRITES
+            || self.written_bytes()
+                + (SHAPE_WRITES - self.writes()) as usize * SHAPE_PADDING_KEY.len()
+                > SHAPE_WRITTEN_BYTES
+        {
+            return Err(StdError::generic_err("Handle exceeds the constant shape."));
+        }
+
+        while self.reads() < SHAPE_READS {
+            self.get(SHAPE_PADDING_KEY);
+        }
+        while self.writes() < SHAPE_WRITES {
+            let decoys = (SHAPE_WRITES - self.writes()) as usize;
+            let size = (SHAPE_WRITTEN_BYTES - self.written_bytes()) / decoys;
+            self.set(SHAPE_PADDING_KEY, &vec![0; size - SHAPE_PADDING_KEY.len()]);
+        }
+
+        Ok(())
+    }
+}
+impl<'a, S: Storage> ReadonlyStorage for CountingStorage<'a, S> {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.reads.set(self.reads.get() + 1);
+        self.storage.get(key)
+    }
+}
+impl<'a, S: Storage> Storage for CountingStorage<'a, S> {
+    fn set(&mut self, key: &[u8], value: &[u8]) {
+        self.writes += 1;
+        self.written_bytes += key.len() + value.len();
+        self.storage.set(key, value)
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        self.writes += 1;
+        self.written_bytes += key.len();
+        self.storage.remove(key)
+    }
+}
+
+// Lets a handle run against a borrowed querier
+pub struct BorrowedQuerier<'a, Q: Querier>(pub &'a Q);
+impl<'a, Q: Querier> Querier for BorrowedQuerier<'a, Q> {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        self.0.raw_query(bin_request)
+    }
+}
@@ -0,0 +1,286 @@
+use crate::constants::{
+    PREFIX_COUNTERPARTY_SPENDING, PREFIX_PAID_COUNTERPARTIES, PREFIX_SPENDING,
+    PREFIX_SPENDING_LIMITS,
+};
+use crate::fees::SECONDS_PER_DAY;
+use cosmwasm_std::{
+    CanonicalAddr, HumanAddr, ReadonlyStorage, StdError, StdResult, Storage, Uint128,
+};
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+use schemars::JsonSchema;
+use secret_toolkit::storage::{TypedStore, TypedStoreMut};
+use serde::{Deserialize, Serialize};
+
+// Changes to spending limits wait this long, so that a compromised wallet can't lift them at once
+pub const SPENDING_LIMITS_DELAY: u64 = SECONDS_PER_DAY;
+pub const SECONDS_PER_WEEK: u64 = 7 * SECONDS_PER_DAY;
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Default, PartialEq)]
+pub struct SpendingLimits {
+    pub token_limits: Vec<TokenSpendingLimit>,
+    // Counterparties the user hasn't paid before can only be paid this long after the Tx was created
+    pub cooling_off_period: Option<u64>,
+}
+
+// Days and weeks are counted in whole periods of block time, like daily stats
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct TokenSpendingLimit {
+    pub token: HumanAddr,
+    pub daily_maximum: Option<Uint128>,
+    pub weekly_maximum: Option<Uint128>,
+    pub counterparty_daily_maximum: Option<Uint128>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Default, PartialEq)]
+pub struct SpendingLimitSettings {
+    pub limits: SpendingLimits,
+    pub pending: Option<PendingSpendingLimits>,
+}
+impl SpendingLimitSettings {
+    // Apply the pending limits if their delay has passed
+    pub fn at(mut self, time: u64) -> Self {
+        if let Some(pending) = &self.pending {
+            if pending.effective_at <= time {
+                self.limits = pending.limits.clone();
+                self.pending = None;
+            }
+        }
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct PendingSpendingLimits {
+    pub limits: SpendingLimits,
+    pub effective_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+struct TokenSpending {
+    day: u64,
+    daily: Uint128,
+    week: u64,
+    weekly: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+struct CounterpartySpending {
+    day: u64,
+    amount: Uint128,
+}
+
+// Storage functions:
+pub fn spending_limit_settings<S: ReadonlyStorage>(
+    storage: &S,
+    owner: &CanonicalAddr,
+) -> StdResult<SpendingLimitSettings> {
+    let store = ReadonlyPrefixedStorage::new(PREFIX_SPENDING_LIMITS, storage);
+    let settings: Option<SpendingLimitSettings> =
+        TypedStore::attach(&store).may_load(owner.as_slice())?;
+
+    Ok(settings.unwrap_or_default())
+}
+
+pub fn set_spending_limit_settings<S: Storage>(
+    storage: &mut S,
+    owner: &CanonicalAddr,
+    settings: &SpendingLimitSettings,
+) -> StdResult<()> {
+    let mut store = PrefixedStorage::new(PREFIX_SPENDING_LIMITS, storage);
+    TypedStoreMut::attach(&mut store).store(owner.as_slice(), settings)
+}
+
+// Verify that a payment is within the owner's limits and add it to their spending.
+// Nothing is tracked for owners without limits. Without a counterparty, as for a claim that
+// anyone with the code can redeem, the payment counts as one to a new counterparty.
+pub fn record_spending<S: Storage>(
+    storage: &mut S,
+    owner: &CanonicalAddr,
+    counterparty: Option<&CanonicalAddr>,
+    token: &HumanAddr,
+    amount: Uint128,
+    created_at: u64,
+    time: u64,
+) -> StdResult<()> {
+    let limits = spending_limit_settings(storage, owner)?.at(time).limits;
+    if limits == SpendingLimits::default() {
+        return Ok(());
+    }
+
+    let paid_before = match counterparty {
+        Some(counterparty) => {
+            let store = ReadonlyPrefixedStorage::multilevel(
+                &[PREFIX_PAID_COUNTERPARTIES, owner.as_slice()],
+                storage,
+            );
+            let paid: Option<bool> =
+                TypedStore::attach(&store).may_load(counterparty.as_slice())?;
+            paid.is_some()
+        }
+        None => false,
+    };
+    if !paid_before {
+        if let Some(cooling_off_period) = limits.cooling_off_period {
+            if time < created_at + cooling_off_period {
+                return Err(StdError::generic_err(
+                    "Counterparty is new and still in its cooling-off period.",
+                ));
+            }
+        }
+    }
+
+    // Add the payment to the spending for the token and for the counterparty
+    let limit = limits
+        .token_limits
+        .into_iter()
+        .find(|limit| &limit.token == token);
+    let day = time / SECONDS_PER_DAY;
+    let week = time / SECONDS_PER_WEEK;
+    let mut token_spending: TokenSpending = {
+        let store =
+            ReadonlyPrefixedStorage::multilevel(&[PREFIX_SPENDING, owner.as_slice()], storage);
+        TypedStore::attach(&store)
+            .may_load(token.0.as_bytes())?
+            .unwrap_or_default()
+    };
+    if token_spending.day != day {
+        token_spending.day = day;
+        token_spending.daily = Uint128(0);
+    }
+    if token_spending.week != week {
+        token_spending.week = week;
+        token_spending.weekly = Uint128(0);
+    }
+    token_spending.daily = token_spending.daily + amount;
+    token_spending.weekly = token_spending.weekly + amount;
+    let counterparty_spending: Option<CounterpartySpending> = match counterparty {
+        Some(counterparty) => {
+            let store = ReadonlyPrefixedStorage::multilevel(
+                &[
+                    PREFIX_COUNTERPARTY_SPENDING,
+                    owner.as_slice(),
+                    token.0.as_bytes(),
+                ],
+                storage,
+            );
+            let mut counterparty_spending: CounterpartySpending = TypedStore::attach(&store)
+                .may_load(counterparty.as_slice())?
+                .unwrap_or_default();
+            if counterparty_spending.day != day {
+                counterparty_spending.day = day;
+                counterparty_spending.amount = Uint128(0);
+            }
+            counterparty_spending.amount = counterparty_spending.amount + amount;
+            Some(counterparty_spending)
+        }
+        None => None,
+    };
+    if let Some(limit) = limit {
+        if exceeds(token_spending.daily, limit.daily_maximum) {
+            return Err(StdError::generic_err(
+                "Payment exceeds the daily spending limit for this token.",
+            ));
+        }
+        if exceeds(token_spending.weekly, limit.weekly_maximum) {
+            return Err(StdError::generic_err(
+                "Payment exceeds the weekly spending limit for this token.",
+            ));
+        }
+        if counterparty_spending
+            .as_ref()
+            .is_some_and(|counterparty_spending| {
+                exceeds(
+                    counterparty_spending.amount,
+                    limit.counterparty_daily_maximum,
+                )
+            })
+        {
+            return Err(StdError::generic_err(
+                "Payment exceeds the daily spending limit for this counterparty.",
+            ));
+        }
+    }
+
+    let mut store = PrefixedStorage::multilevel(&[PREFIX_SPENDING, owner.as_slice()], storage);
+    TypedStoreMut::attach(&mut store).store(token.0.as_bytes(), &token_spending)?;
+    if let (Some(counterparty), Some(counterparty_spending)) = (counterparty, counterparty_spending)
+    {
+        if !paid_before {
+            let mut store = PrefixedStorage::multilevel(
+                &[PREFIX_PAID_COUNTERPARTIES, owner.as_slice()],
+                storage,
+            );
+            TypedStoreMut::attach(&mut store).store(counterparty.as_slice(), &true)?;
+        }
+        let mut store = PrefixedStorage::multilevel(
+            &[
+                PREFIX_COUNTERPARTY_SPENDING,
+                owner.as_slice(),
+                token.0.as_bytes(),
+            ],
+            storage,
+        );
+        TypedStoreMut::attach(&mut store).store(counterparty.as_slice(), &counterparty_spending)?;
+    }
+
+    Ok(())
+}
+
+// Take a payment back out of the owner's spending for the periods it was paid in, after it was
+// returned to the owner. Only the current settings are kept, so whether it was tracked is judged
+// by the limits they give at the time of payment, including pending limits in effect by then.
+// Spending never goes below zero.
+pub fn release_spending<S: Storage>(
+    storage: &mut S,
+    owner: &CanonicalAddr,
+    counterparty: &CanonicalAddr,
+    token: &HumanAddr,
+    amount: Uint128,
+    paid_at: u64,
+) -> StdResult<()> {
+    let limits = spending_limit_settings(storage, owner)?.at(paid_at).limits;
+    if limits == SpendingLimits::default() {
+        return Ok(());
+    }
+
+    let day = paid_at / SECONDS_PER_DAY;
+    let week = paid_at / SECONDS_PER_WEEK;
+    let mut store = PrefixedStorage::multilevel(&[PREFIX_SPENDING, owner.as_slice()], storage);
+    let token_spending: Option<TokenSpending> =
+        TypedStore::attach(&store).may_load(token.0.as_bytes())?;
+    if let Some(mut token_spending) = token_spending {
+        if token_spending.day == day {
+            token_spending.daily = subtract(token_spending.daily, amount);
+        }
+        if token_spending.week == week {
+            token_spending.weekly = subtract(token_spending.weekly, amount);
+        }
+        TypedStoreMut::attach(&mut store).store(token.0.as_bytes(), &token_spending)?;
+    }
+    let mut store = PrefixedStorage::multilevel(
+        &[
+            PREFIX_COUNTERPARTY_SPENDING,
+            owner.as_slice(),
+            token.0.as_bytes(),
+        ],
+        storage,
+    );
+    let counterparty_spending: Option<CounterpartySpending> =
+        TypedStore::attach(&store).may_load(counterparty.as_slice())?;
+    if let Some(mut counterparty_spending) = counterparty_spending {
+        if counterparty_spending.day == day {
+            counterparty_spending.amount = subtract(counterparty_spending.amount, amount);
+        }
+        TypedStoreMut::attach(&mut store).store(counterparty.as_slice(), &counterparty_spending)?;
+    }
+
+    Ok(())
+}
+
+fn subtract(amount: Uint128, payment: Uint128) -> Uint128 {
+    Uint128(amount.u128().saturating_sub(payment.u128()))
+}
+
+fn exceeds(amount: Uint128, maximum: Option<Uint128>) -> bool {
+    maximum.is_some_and(|maximum| amount > maximum)
+}
@@ -5,15 +5,63 @@ use schemars::JsonSchema;
 use secret_toolkit::serialization::{Bincode2, Serde};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct Config {
     pub admin: HumanAddr,
+    // Pad status transitions to the same storage accesses and message count
+    pub constant_shape: bool,
     pub fee: Uint128,
+    pub fee_discounts: Vec<FeeDiscount>,
+    // Accepted for fees besides SSCRT, which is charged the fee above
+    pub fee_tokens: Vec<FeeToken>,
     pub new_admin_nomination: Option<HumanAddr>,
+    pub referral_share_bps: u16,
     pub shade: SecretContract,
     pub sscrt: SecretContract,
     pub treasury_address: HumanAddr,
+    pub treasury_recipients: Vec<TreasuryRecipient>,
+}
+impl Config {
+    // The fee token sent as token_address. Any other token falls back to SSCRT,
+    // so that it fails the usual check of the amount and token received.
+    pub fn fee_token(&self, token_address: &HumanAddr) -> FeeToken {
+        self.fee_tokens
+            .iter()
+            .find(|fee_token| &fee_token.token.address == token_address)
+            .cloned()
+            .unwrap_or_else(|| FeeToken {
+                token: self.sscrt.clone(),
+                fee: self.fee,
+            })
+    }
+
+    // Volumes are only kept for SSCRT, Shade and the fee tokens, so that paying in throwaway
+    // tokens can't grow the stats
+    pub fn volume_tokens(&self) -> Vec<HumanAddr> {
+        let mut tokens = vec![self.sscrt.address.clone(), self.shade.address.clone()];
+        for fee_token in &self.fee_tokens {
+            if !tokens.contains(&fee_token.token.address) {
+                tokens.push(fee_token.token.address.clone());
+            }
+        }
+        tokens
+    }
+}
+
+// Holders of at least minimum_balance of token get discount_bps off the fee
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, JsonSchema)]
+pub struct FeeDiscount {
+    pub token: SecretContract,
+    pub minimum_balance: Uint128,
+    pub discount_bps: u16,
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, JsonSchema)]
+pub struct FeeToken {
+    pub token: SecretContract,
+    pub fee: Uint128,
 }
 
 #[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, JsonSchema)]
@@ -22,6 +70,13 @@ pub struct SecretContract {
     pub contract_hash: String,
 }
 
+// Fees are split between treasury recipients in proportion to their weight
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, JsonSchema)]
+pub struct TreasuryRecipient {
+    pub address: HumanAddr,
+    pub weight: u16,
+}
+
 // === RegisteredTokens Storage ===
 pub struct RegisteredTokensReadonlyStorage<'a, S: Storage> {
     storage: ReadonlyPrefixedStorage<'a, S>,
@@ -81,6 +136,11 @@ impl<'a, S: ReadonlyStorage> ReadonlyRegisteredTokensStorageImpl<'a, S> {
 }
 
 // === FUNCTIONS ===
+// Claims and payment links are stored under the SHA-256 hash of their code
+pub fn hash_code(code: &str) -> Vec<u8> {
+    Sha256::digest(code.as_bytes()).to_vec()
+}
+
 fn may_load<T: DeserializeOwned, S: ReadonlyStorage>(
     storage: &S,
     key: &[u8],
@@ -0,0 +1,216 @@
+use crate::constants::{
+    CONFIG_KEY, PREFIX_DAILY_STATS, PREFIX_DAILY_VOLUMES, PREFIX_PARTICIPANTS, PREFIX_VOLUMES,
+    STATS_KEY,
+};
+use crate::fees::SECONDS_PER_DAY;
+use crate::state::Config;
+use cosmwasm_std::{CanonicalAddr, HumanAddr, ReadonlyStorage, StdResult, Storage, Uint128};
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+use schemars::JsonSchema;
+use secret_toolkit::storage::{AppendStore, AppendStoreMut, TypedStore, TypedStoreMut};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    pub send_requests_created: u64,
+    pub receive_requests_created: u64,
+    pub confirmed: u64,
+    pub cancelled: u64,
+    pub finalized: u64,
+    pub unique_participants: u64,
+    // Kept per token in their own records and filled in when queried
+    pub volumes: Vec<TokenVolume>,
+}
+impl Stats {
+    // Round everything down to two significant digits so that the public numbers
+    // can't be used to work out individual requests
+    pub fn into_coarse(self) -> Stats {
+        Stats {
+            send_requests_created: coarsen(self.send_requests_created as u128) as u64,
+            receive_requests_created: coarsen(self.receive_requests_created as u128) as u64,
+            confirmed: coarsen(self.confirmed as u128) as u64,
+            cancelled: coarsen(self.cancelled as u128) as u64,
+            finalized: coarsen(self.finalized as u128) as u64,
+            unique_participants: coarsen(self.unique_participants as u128) as u64,
+            volumes: self
+                .volumes
+                .into_iter()
+                .map(|volume| TokenVolume {
+                    token: volume.token,
+                    volume: Uint128(coarsen(volume.volume.u128())),
+                })
+                .collect(),
+        }
+    }
+}
+
+// Volume of finalized payments in a token
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct TokenVolume {
+    pub token: HumanAddr,
+    pub volume: Uint128,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct DailyStats {
+    pub day: u64,
+    pub stats: Stats,
+}
+
+// Storage functions:
+pub fn stats<S: ReadonlyStorage>(storage: &S, tokens: &[HumanAddr]) -> StdResult<Stats> {
+    let stats: Option<Stats> = TypedStore::attach(storage).may_load(STATS_KEY)?;
+    let mut stats = stats.unwrap_or_default();
+    stats.volumes = volumes(storage, tokens, None)?;
+
+    Ok(stats)
+}
+
+pub fn get_daily_stats<S: ReadonlyStorage>(
+    storage: &S,
+    tokens: &[HumanAddr],
+    page: u32,
+    page_size: u32,
+) -> StdResult<(Vec<DailyStats>, u64)> {
+    let store = ReadonlyPrefixedStorage::new(PREFIX_DAILY_STATS, storage);
+
+    // Try to access the daily stats.
+    // If they don't exist yet, return an empty list.
+    let store = AppendStore::<DailyStats, _, _>::attach(&store);
+    let store = if let Some(result) = store {
+        result?
+    } else {
+        return Ok((vec![], 0));
+    };
+
+    let mut daily_stats: Vec<DailyStats> = store
+        .iter()
+        .rev()
+        .skip((page * page_size) as _)
+        .take(page_size as _)
+        .collect::<StdResult<Vec<DailyStats>>>()?;
+    for daily_stats in daily_stats.iter_mut() {
+        daily_stats.stats.volumes = volumes(storage, tokens, Some(daily_stats.day))?;
+    }
+    Ok((daily_stats, store.len() as u64))
+}
+
+// The volumes of the tokens in total or on a day, leaving out tokens without any
+fn volumes<S: ReadonlyStorage>(
+    storage: &S,
+    tokens: &[HumanAddr],
+    day: Option<u64>,
+) -> StdResult<Vec<TokenVolume>> {
+    let mut volumes: Vec<TokenVolume> = vec![];
+    for token in tokens {
+        let volume = token_volume(storage, token, day)?;
+        if volume.u128() > 0 {
+            volumes.push(TokenVolume {
+                token: token.clone(),
+                volume,
+            });
+        }
+    }
+
+    Ok(volumes)
+}
+
+fn token_volume<S: ReadonlyStorage>(
+    storage: &S,
+    token_address: &HumanAddr,
+    day: Option<u64>,
+) -> StdResult<Uint128> {
+    let volume: Option<Uint128> = match day {
+        Some(day) => {
+            let store = ReadonlyPrefixedStorage::multilevel(
+                &[PREFIX_DAILY_VOLUMES, token_address.0.as_bytes()],
+                storage,
+            );
+            TypedStore::attach(&store).may_load(&day.to_be_bytes())?
+        }
+        None => {
+            let store = ReadonlyPrefixedStorage::new(PREFIX_VOLUMES, storage);
+            TypedStore::attach(&store).may_load(token_address.0.as_bytes())?
+        }
+    };
+
+    Ok(volume.unwrap_or(Uint128(0)))
+}
+
+// Apply the same change to the running totals and to the bucket for the day of `time`
+pub fn record_stats<S: Storage, F: Fn(&mut Stats)>(
+    storage: &mut S,
+    time: u64,
+    update: F,
+) -> StdResult<()> {
+    let totals: Option<Stats> = TypedStore::attach(storage).may_load(STATS_KEY)?;
+    let mut totals = totals.unwrap_or_default();
+    update(&mut totals);
+    TypedStoreMut::attach(storage).store(STATS_KEY, &totals)?;
+
+    let day = time / SECONDS_PER_DAY;
+    let mut store = PrefixedStorage::new(PREFIX_DAILY_STATS, storage);
+    let mut store = AppendStoreMut::<DailyStats, _, _>::attach_or_create(&mut store)?;
+    if !store.is_empty() {
+        let mut daily_stats = store.get_at(store.len() - 1)?;
+        if daily_stats.day == day {
+            update(&mut daily_stats.stats);
+            return store.set_at(store.len() - 1, &daily_stats);
+        }
+    }
+    let mut daily_stats = DailyStats {
+        day,
+        stats: Stats::default(),
+    };
+    update(&mut daily_stats.stats);
+    store.push(&daily_stats)
+}
+
+// Add a finalized payment to the running volume of its token and to the volume for the day of `time`
+pub fn record_volume<S: Storage>(
+    storage: &mut S,
+    token_address: &HumanAddr,
+    time: u64,
+    amount: Uint128,
+) -> StdResult<()> {
+    let config: Config = TypedStore::attach(storage).load(CONFIG_KEY)?;
+    if !config.volume_tokens().contains(token_address) {
+        return Ok(());
+    }
+
+    let volume = token_volume(storage, token_address, None)?;
+    let mut store = PrefixedStorage::new(PREFIX_VOLUMES, storage);
+    TypedStoreMut::attach(&mut store).store(token_address.0.as_bytes(), &(volume + amount))?;
+
+    let day = time / SECONDS_PER_DAY;
+    let volume = token_volume(storage, token_address, Some(day))?;
+    let mut store =
+        PrefixedStorage::multilevel(&[PREFIX_DAILY_VOLUMES, token_address.0.as_bytes()], storage);
+    TypedStoreMut::attach(&mut store).store(&day.to_be_bytes(), &(volume + amount))
+}
+
+// Remember the addresses and return how many of them haven't been seen before
+pub fn record_participants<S: Storage>(
+    storage: &mut S,
+    addresses: &[&CanonicalAddr],
+) -> StdResult<u64> {
+    let mut store = PrefixedStorage::new(PREFIX_PARTICIPANTS, storage);
+    let mut store = TypedStoreMut::<bool, _>::attach(&mut store);
+    let mut new_participants: u64 = 0;
+    for address in addresses {
+        if store.may_load(address.as_slice())?.is_none() {
+            store.store(address.as_slice(), &true)?;
+            new_participants += 1;
+        }
+    }
+
+    Ok(new_participants)
+}
+
+fn coarsen(value: u128) -> u128 {
+    let mut scale: u128 = 1;
+    while value / scale >= 100 {
+        scale *= 10;
+    }
+    value / scale * scale
+}
@@ -1,9 +1,12 @@
 use crate::authorize::authorize;
-use crate::constants::PREFIX_TXS;
+use crate::constants::{
+    DESCRIPTION_BLOCK_SIZE, DESCRIPTION_LENGTH_WIDTH, MAX_DESCRIPTION_LENGTH,
+    MAX_TOKEN_FIELD_LENGTH, PREFIX_TXS,
+};
 use crate::contract::correct_amount_of_token;
 use crate::state::SecretContract;
 use cosmwasm_std::{
-    Api, CanonicalAddr, HumanAddr, ReadonlyStorage, StdError, StdResult, Storage, Uint128,
+    Api, Binary, CanonicalAddr, HumanAddr, ReadonlyStorage, StdError, StdResult, Storage, Uint128,
 };
 use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
 use schemars::JsonSchema;
@@ -22,6 +25,90 @@ pub struct HumanizedTx {
     pub status: u8,
     pub block_time: u64,
     pub block_height: u64,
+    pub cancellation_policy: CancellationPolicy,
+    pub cancellation_penalty: Uint128,
+    pub cancellation_reason: Option<String>,
+    pub refunded: Uint128,
+    pub refund_of: Option<u32>,
+    pub hash_timelock: Option<HashTimelock>,
+    pub preimage: Option<Binary>,
+    pub milestones: Option<Vec<Milestone>>,
+    pub milestone_of: Option<u32>,
+    pub memo: Option<String>,
+    pub attachment_hash: Option<Binary>,
+}
+
+impl HumanizedTx {
+    // Fills up fixed size pages of Txs
+    pub fn placeholder() -> Self {
+        HumanizedTx {
+            position: 0,
+            from: HumanAddr::default(),
+            to: HumanAddr::default(),
+            amount: Uint128(0),
+            token: SecretContract {
+                address: HumanAddr::default(),
+                contract_hash: String::new(),
+            },
+            description: None,
+            status: 0,
+            block_time: 0,
+            block_height: 0,
+            cancellation_policy: CancellationPolicy::default(),
+            cancellation_penalty: Uint128(0),
+            cancellation_reason: None,
+            refunded: Uint128(0),
+            refund_of: None,
+            hash_timelock: None,
+            preimage: None,
+            milestones: None,
+            milestone_of: None,
+            memo: None,
+            attachment_hash: None,
+        }
+    }
+}
+
+// Who can cancel a Tx and until when
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CancellationPolicy {
+    // Either party, until the Tx is finalized
+    #[default]
+    EitherParty,
+    // Only the creator, until the Tx is finalized
+    CreatorOnly,
+    // Either party, until the receiver confirms their address
+    BeforeConfirmation,
+}
+
+// Payment is escrowed until the receiver reveals the preimage of the hashlock before the timelock
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct HashTimelock {
+    // SHA-256 hash of the preimage
+    pub hashlock: Binary,
+    // Block time after which the sender can take back the payment
+    pub timelock: u64,
+}
+
+// Part of the payment of a milestone Tx, which the sender releases to the receiver on its own
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct Milestone {
+    pub label: String,
+    pub amount: Uint128,
+    // Block time after which the sender can cancel the milestone whatever the cancellation policy
+    pub deadline: Option<u64>,
+    #[serde(default)]
+    pub status: MilestoneStatus,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MilestoneStatus {
+    #[default]
+    Pending,
+    Released,
+    Cancelled,
 }
 
 #[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
@@ -29,9 +116,12 @@ pub struct Tx {
     pub position: u32,
     pub other_storage_position: u32,
     pub fee: Uint128,
+    // Token the fee was paid in, which refunds of the fee and the cancellation penalty use
+    pub fee_token: SecretContract,
     pub from: CanonicalAddr,
     pub to: CanonicalAddr,
     pub creator: HumanAddr,
+    pub referrer: Option<HumanAddr>,
     pub amount: Uint128,
     pub token: SecretContract,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -39,8 +129,65 @@ pub struct Tx {
     pub status: u8,
     pub block_time: u64,
     pub block_height: u64,
+    pub cancellation_policy: CancellationPolicy,
+    // Amount of the fee token the counterparty pays the creator to cancel
+    pub cancellation_penalty: Uint128,
+    // Given when the Tx is cancelled or declined
+    pub cancellation_reason: Option<String>,
+    // Amount the receiver has sent back after the Tx was finalized
+    pub refunded: Uint128,
+    // For a refund, the position of the original Tx in the same address's Txs
+    pub refund_of: Option<u32>,
+    pub hash_timelock: Option<HashTimelock>,
+    // Revealed by the receiver to claim the payment of a hash timelocked Tx
+    pub preimage: Option<Binary>,
+    // Payment is escrowed and released to the receiver milestone by milestone
+    pub milestones: Option<Vec<Milestone>>,
+    // For a milestone release, the position of the milestone Tx in the same address's Txs
+    pub milestone_of: Option<u32>,
+    // Private note only stored on the copy of the address that wrote it
+    pub memo: Option<String>,
+    // SHA-256 hash of an off-chain document
+    pub attachment_hash: Option<Binary>,
 }
 impl Tx {
+    // Keep only what links the counterparty's copy to this position
+    fn into_pruned(self, address: &CanonicalAddr) -> Tx {
+        Tx {
+            position: self.position,
+            other_storage_position: self.other_storage_position,
+            fee: Uint128(0),
+            fee_token: SecretContract {
+                address: HumanAddr::default(),
+                contract_hash: String::new(),
+            },
+            from: address.clone(),
+            to: address.clone(),
+            creator: HumanAddr::default(),
+            referrer: None,
+            amount: Uint128(0),
+            token: SecretContract {
+                address: HumanAddr::default(),
+                contract_hash: String::new(),
+            },
+            description: None,
+            status: 9,
+            block_time: 0,
+            block_height: 0,
+            cancellation_policy: CancellationPolicy::default(),
+            cancellation_penalty: Uint128(0),
+            cancellation_reason: None,
+            refunded: Uint128(0),
+            refund_of: None,
+            hash_timelock: None,
+            preimage: None,
+            milestones: None,
+            milestone_of: None,
+            memo: None,
+            attachment_hash: None,
+        }
+    }
+
     fn into_humanized<A: Api>(self, api: &A) -> StdResult<HumanizedTx> {
         Ok(HumanizedTx {
             position: self.position,
@@ -52,6 +199,17 @@ impl Tx {
             status: self.status,
             block_time: self.block_time,
             block_height: self.block_height,
+            cancellation_policy: self.cancellation_policy,
+            cancellation_penalty: self.cancellation_penalty,
+            cancellation_reason: self.cancellation_reason,
+            refunded: self.refunded,
+            refund_of: self.refund_of,
+            hash_timelock: self.hash_timelock,
+            preimage: self.preimage,
+            milestones: self.milestones,
+            milestone_of: self.milestone_of,
+            memo: self.memo,
+            attachment_hash: self.attachment_hash,
         })
     }
 }
@@ -85,44 +243,123 @@ pub fn get_txs<A: Api, S: ReadonlyStorage>(
 
     // The `and_then` here flattens the `StdResult<StdResult<RichTx>>` to an `StdResult<RichTx>`
     let txs: StdResult<Vec<HumanizedTx>> = tx_iter
-        .map(|tx| tx.map(|tx| tx.into_humanized(api)).and_then(|x| x))
+        .map(|tx| {
+            tx.map(|tx| unpad_tx(tx).into_humanized(api))
+                .and_then(|x| x)
+        })
         .collect();
     txs.map(|txs| (txs, store.len() as u64))
 }
 
+// Like get_txs, but only pages through the Txs that pass the filter
+pub fn get_filtered_txs<A: Api, S: ReadonlyStorage, F: Fn(&Tx) -> bool>(
+    api: &A,
+    storage: &S,
+    for_address: &CanonicalAddr,
+    page: u32,
+    page_size: u32,
+    filter: F,
+) -> StdResult<(Vec<HumanizedTx>, u64)> {
+    let store = ReadonlyPrefixedStorage::multilevel(&[PREFIX_TXS, for_address.as_slice()], storage);
+    let store = AppendStore::<Tx, _, _>::attach(&store);
+    let store = if let Some(result) = store {
+        result?
+    } else {
+        return Ok((vec![], 0));
+    };
+
+    let skip = (page * page_size) as u64;
+    let mut txs: Vec<HumanizedTx> = vec![];
+    let mut total: u64 = 0;
+    for tx in store.iter().rev() {
+        let tx = unpad_tx(tx?);
+        if !filter(&tx) {
+            continue;
+        }
+        if total >= skip && txs.len() < page_size as usize {
+            txs.push(tx.into_humanized(api)?);
+        }
+        total += 1;
+    }
+
+    Ok((txs, total))
+}
+
+// The optional terms the creator of a Tx can set
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TxTerms {
+    pub attachment_hash: Option<Binary>,
+    pub referrer: Option<HumanAddr>,
+    pub cancellation_policy: CancellationPolicy,
+    pub cancellation_penalty: Uint128,
+    pub hash_timelock: Option<HashTimelock>,
+    pub milestones: Option<Vec<Milestone>>,
+}
+
+// The payment and fee of a new Tx
+#[derive(Clone, Debug, PartialEq)]
+pub struct NewTx {
+    pub creator: HumanAddr,
+    pub amount: Uint128,
+    pub token: SecretContract,
+    pub description: Option<String>,
+    pub fee: Uint128,
+    pub fee_token: SecretContract,
+}
+
+// Returns the position of the Tx in the from address's Txs
 pub fn store_txs<S: Storage>(
     store: &mut S,
-    fee: Uint128,
     from: &CanonicalAddr,
     to: &CanonicalAddr,
-    creator: HumanAddr,
-    amount: Uint128,
-    token: SecretContract,
-    description: Option<String>,
+    tx: NewTx,
+    terms: TxTerms,
     status: u8,
     block: &cosmwasm_std::BlockInfo,
-) -> StdResult<()> {
+) -> StdResult<u32> {
     if from == to {
         return Err(StdError::generic_err(
             "From and to addresses must be different.",
         ));
     }
+    verify_text_length("Description", &tx.description)?;
+    verify_token(&tx.token)?;
+    if let Some(attachment_hash) = &terms.attachment_hash {
+        if attachment_hash.len() != 32 {
+            return Err(StdError::generic_err(
+                "Attachment hash must be a SHA-256 hash.",
+            ));
+        }
+    }
 
     let from_position = get_next_position(store, from)?;
     let to_position = get_next_position(store, to)?;
     let from_tx = Tx {
         position: from_position,
         other_storage_position: to_position,
-        fee: fee,
+        fee: tx.fee,
+        fee_token: tx.fee_token,
         from: from.clone(),
         to: to.clone(),
-        creator: creator,
-        amount: amount,
-        token: token,
-        description: description,
-        status: status,
+        creator: tx.creator,
+        referrer: terms.referrer,
+        amount: tx.amount,
+        token: tx.token,
+        description: tx.description,
+        status,
         block_time: block.time,
         block_height: block.height,
+        cancellation_policy: terms.cancellation_policy,
+        cancellation_penalty: terms.cancellation_penalty,
+        cancellation_reason: None,
+        refunded: Uint128(0),
+        refund_of: None,
+        hash_timelock: terms.hash_timelock,
+        preimage: None,
+        milestones: terms.milestones,
+        milestone_of: None,
+        memo: None,
+        attachment_hash: terms.attachment_hash,
     };
     append_tx(store, &from_tx, from)?;
     let mut to_tx = from_tx;
@@ -130,7 +367,115 @@ pub fn store_txs<S: Storage>(
     to_tx.other_storage_position = from_position;
     append_tx(store, &to_tx, to)?;
 
-    Ok(())
+    Ok(from_position)
+}
+
+// Store a finalized refund from the receiver of the original Tx back to its sender.
+// Each copy of the refund points at the copy of the original in the same address's Txs.
+pub fn store_refund_txs<S: Storage>(
+    store: &mut S,
+    original_from_tx: &Tx,
+    original_to_tx: &Tx,
+    creator: HumanAddr,
+    amount: Uint128,
+    block: &cosmwasm_std::BlockInfo,
+) -> StdResult<u32> {
+    let position = store_txs(
+        store,
+        &original_to_tx.to,
+        &original_from_tx.from,
+        NewTx {
+            creator,
+            amount,
+            token: original_to_tx.token.clone(),
+            description: original_to_tx.description.clone(),
+            fee: Uint128(0),
+            fee_token: original_to_tx.fee_token.clone(),
+        },
+        TxTerms::default(),
+        3,
+        block,
+    )?;
+    let mut from_tx = tx_at_position(store, &original_to_tx.to, position)?;
+    let mut to_tx = tx_at_position(
+        store,
+        &original_from_tx.from,
+        from_tx.other_storage_position,
+    )?;
+    from_tx.refund_of = Some(original_to_tx.position);
+    to_tx.refund_of = Some(original_from_tx.position);
+    update_tx(store, &original_to_tx.to, from_tx)?;
+    update_tx(store, &original_from_tx.from, to_tx)?;
+
+    Ok(position)
+}
+
+// Store a finalized release of a milestone from the sender of the milestone Tx to its receiver.
+// Each copy of the release points at the copy of the milestone Tx in the same address's Txs.
+pub fn store_milestone_release_txs<S: Storage>(
+    store: &mut S,
+    milestone_from_tx: &Tx,
+    milestone_to_tx: &Tx,
+    label: String,
+    amount: Uint128,
+    block: &cosmwasm_std::BlockInfo,
+) -> StdResult<u32> {
+    let position = store_txs(
+        store,
+        &milestone_from_tx.from,
+        &milestone_to_tx.to,
+        NewTx {
+            creator: milestone_from_tx.creator.clone(),
+            amount,
+            token: milestone_from_tx.token.clone(),
+            description: Some(label),
+            fee: Uint128(0),
+            fee_token: milestone_from_tx.fee_token.clone(),
+        },
+        TxTerms::default(),
+        3,
+        block,
+    )?;
+    let mut from_tx = tx_at_position(store, &milestone_from_tx.from, position)?;
+    let mut to_tx = tx_at_position(store, &milestone_to_tx.to, from_tx.other_storage_position)?;
+    from_tx.milestone_of = Some(milestone_from_tx.position);
+    to_tx.milestone_of = Some(milestone_to_tx.position);
+    update_tx(store, &milestone_from_tx.from, from_tx)?;
+    update_tx(store, &milestone_to_tx.to, to_tx)?;
+
+    Ok(position)
+}
+
+// Replace the address's finalized, cancelled and declined Txs before the position or block time
+// with pruned records. Returns how many Txs were pruned.
+pub fn prune_txs<S: Storage>(
+    store: &mut S,
+    address: &CanonicalAddr,
+    before_position: Option<u32>,
+    before_time: Option<u64>,
+) -> StdResult<u32> {
+    let mut store = PrefixedStorage::multilevel(&[PREFIX_TXS, address.as_slice()], store);
+    let mut store = AppendStoreMut::<Tx, _, _>::attach_or_create(&mut store)?;
+    let end = match before_position {
+        Some(before_position) => std::cmp::min(before_position, store.len()),
+        None => store.len(),
+    };
+    let mut pruned: u32 = 0;
+    for position in 0..end {
+        let tx: Tx = store.get_at(position)?;
+        // Txs are stored in the order they were created
+        if let Some(before_time) = before_time {
+            if tx.block_time >= before_time {
+                break;
+            }
+        }
+        if [2, 3, 4, 5, 8].contains(&tx.status) {
+            store.set_at(position, &pad_tx(tx.into_pruned(address)))?;
+            pruned += 1;
+        }
+    }
+
+    Ok(pruned)
 }
 
 pub fn tx_at_position<S: Storage>(
@@ -143,7 +488,7 @@ pub fn tx_at_position<S: Storage>(
     // If it doesn't exist yet, return an empty list of transfers.
     let store = AppendStoreMut::<Tx, _, _>::attach_or_create(&mut store)?;
 
-    Ok(store.get_at(position)?)
+    Ok(unpad_tx(store.get_at(position)?))
 }
 
 pub fn update_tx<S: Storage>(store: &mut S, address: &CanonicalAddr, tx: Tx) -> StdResult<()> {
@@ -151,7 +496,7 @@ pub fn update_tx<S: Storage>(store: &mut S, address: &CanonicalAddr, tx: Tx) ->
     // Try to access the storage of txs for the account.
     // If it doesn't exist yet, return an empty list of transfers.
     let mut store = AppendStoreMut::<Tx, _, _>::attach_or_create(&mut store)?;
-    store.set_at(tx.position, &tx)?;
+    store.set_at(tx.position, &pad_tx(tx))?;
 
     Ok(())
 }
@@ -184,19 +529,85 @@ pub fn verify_txs<A: Api, S: Storage>(
     Ok((from_tx, to_tx))
 }
 
-pub fn verify_txs_for_cancel<S: Storage>(
+// Verify that the address can cancel the Tx at its position, which can be in either party's Txs
+pub fn verify_txs_for_cancel<A: Api, S: Storage>(
+    api: &A,
     store: &mut S,
     address: &CanonicalAddr,
     position: u32,
 ) -> StdResult<(Tx, Tx)> {
-    let from_tx = tx_at_position(store, address, position)?;
-    let to_tx = tx_at_position(store, &from_tx.to, from_tx.other_storage_position)?;
+    let tx = tx_at_position(store, address, position)?;
+    let (from_tx, to_tx) = if &tx.from == address {
+        let to_tx = tx_at_position(store, &tx.to, tx.other_storage_position)?;
+        (tx, to_tx)
+    } else {
+        let from_tx = tx_at_position(store, &tx.from, tx.other_storage_position)?;
+        (from_tx, tx)
+    };
     if to_tx.status == 2 {
         return Err(StdError::generic_err("Tx already cancelled."));
     }
-    if to_tx.status == 3 {
+    if to_tx.status == 8 {
+        return Err(StdError::generic_err("Tx already declined."));
+    }
+    if to_tx.status == 3 || to_tx.status == 4 || to_tx.status == 5 {
         return Err(StdError::generic_err("Tx already finalized."));
     }
+    if to_tx.status == 6 {
+        return Err(StdError::generic_err("Escrowed Txs can not be cancelled."));
+    }
+    match to_tx.cancellation_policy {
+        CancellationPolicy::EitherParty => {}
+        CancellationPolicy::CreatorOnly => {
+            if api.human_address(address)? != to_tx.creator {
+                return Err(StdError::generic_err(
+                    "Only the creator can cancel this Tx.",
+                ));
+            }
+        }
+        CancellationPolicy::BeforeConfirmation => {
+            if to_tx.status != 0 {
+                return Err(StdError::generic_err(
+                    "Tx can not be cancelled after address confirmation.",
+                ));
+            }
+        }
+    }
+
+    Ok((from_tx, to_tx))
+}
+
+// Verify the receiver's Tx can be refunded by the amount of the token received
+pub fn verify_txs_for_refund<A: Api, S: Storage>(
+    api: &A,
+    store: &mut S,
+    address: &CanonicalAddr,
+    amount: Uint128,
+    position: u32,
+    token_address: HumanAddr,
+) -> StdResult<(Tx, Tx)> {
+    let to_tx = tx_at_position(store, address, position)?;
+    let from_tx = tx_at_position(store, &to_tx.from, to_tx.other_storage_position)?;
+    authorize(api.human_address(&to_tx.to)?, api.human_address(address)?)?;
+    if to_tx.status != 3 && to_tx.status != 5 {
+        return Err(StdError::generic_err("Only finalized Txs can be refunded."));
+    }
+    if to_tx.refund_of.is_some() {
+        return Err(StdError::generic_err("Refunds can not be refunded."));
+    }
+    if to_tx.milestones.is_some() {
+        return Err(StdError::generic_err(
+            "Milestone Txs are refunded by refunding their releases.",
+        ));
+    }
+    if token_address != to_tx.token.address {
+        return Err(StdError::generic_err("Wrong token received."));
+    }
+    if amount.is_zero() || amount > (to_tx.amount - to_tx.refunded)? {
+        return Err(StdError::generic_err(
+            "Refund must be greater than zero and at most the amount not yet refunded.",
+        ));
+    }
 
     Ok((from_tx, to_tx))
 }
@@ -219,10 +630,103 @@ pub fn verify_txs_for_confirm_address<A: Api, S: Storage>(
     Ok((from_tx, to_tx))
 }
 
+// Verify that the address is the payer of a receive request that can still be acknowledged or
+// declined
+pub fn verify_txs_for_acknowledgement<A: Api, S: Storage>(
+    api: &A,
+    store: &mut S,
+    address: &CanonicalAddr,
+    position: u32,
+    statuses: &[u8],
+) -> StdResult<(Tx, Tx)> {
+    let from_tx = tx_at_position(store, address, position)?;
+    let to_tx = tx_at_position(store, &from_tx.to, from_tx.other_storage_position)?;
+    authorize(
+        api.human_address(&from_tx.from)?,
+        api.human_address(address)?,
+    )?;
+    if from_tx.creator == api.human_address(address)? {
+        return Err(StdError::generic_err("Tx is not a receive request."));
+    }
+    if !statuses.contains(&from_tx.status) {
+        return Err(StdError::generic_err(
+            "Tx status at that position is incorrect.",
+        ));
+    }
+
+    Ok((from_tx, to_tx))
+}
+
 fn append_tx<S: Storage>(store: &mut S, tx: &Tx, for_address: &CanonicalAddr) -> StdResult<()> {
     let mut store = PrefixedStorage::multilevel(&[PREFIX_TXS, for_address.as_slice()], store);
     let mut store = AppendStoreMut::attach_or_create(&mut store)?;
-    store.push(tx)
+    store.push(&pad_tx(tx.clone()))
+}
+
+// Descriptions and memos are stored space padded to a multiple of DESCRIPTION_BLOCK_SIZE bytes,
+// so that neither their length nor whether they are set shows in the size of a Tx.
+// They are prefixed with their zero padded byte length, so trailing spaces of the text survive.
+fn pad_tx(mut tx: Tx) -> Tx {
+    tx.description = Some(pad_text(tx.description));
+    tx.memo = Some(pad_text(tx.memo));
+    tx
+}
+
+fn unpad_tx(mut tx: Tx) -> Tx {
+    tx.description = unpad_text(tx.description);
+    tx.memo = unpad_text(tx.memo);
+    tx
+}
+
+fn pad_text(text: Option<String>) -> String {
+    let text = text.unwrap_or_default();
+    let length = DESCRIPTION_LENGTH_WIDTH + text.len();
+    let missing = length.div_ceil(DESCRIPTION_BLOCK_SIZE) * DESCRIPTION_BLOCK_SIZE - length;
+    let mut padded = format!(
+        "{:0width$}{}",
+        text.len(),
+        text,
+        width = DESCRIPTION_LENGTH_WIDTH
+    );
+    padded.extend(std::iter::repeat_n(' ', missing));
+    padded
+}
+
+fn unpad_text(text: Option<String>) -> Option<String> {
+    text.and_then(|text| {
+        let length: usize = text.get(..DESCRIPTION_LENGTH_WIDTH)?.parse().ok()?;
+        text.get(DESCRIPTION_LENGTH_WIDTH..DESCRIPTION_LENGTH_WIDTH + length)
+            .map(|text| text.to_string())
+    })
+    .filter(|text| !text.is_empty())
+}
+
+pub fn verify_text_length(name: &str, text: &Option<String>) -> StdResult<()> {
+    if let Some(text) = text {
+        if text.len() > MAX_DESCRIPTION_LENGTH {
+            return Err(StdError::generic_err(format!(
+                "{} must be at most {} bytes.",
+                name, MAX_DESCRIPTION_LENGTH
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+// The token is given by the creator and copied to both Txs, so it can't be any longer than
+// an address and a code hash
+pub fn verify_token(token: &SecretContract) -> StdResult<()> {
+    if token.address.0.len() > MAX_TOKEN_FIELD_LENGTH
+        || token.contract_hash.len() > MAX_TOKEN_FIELD_LENGTH
+    {
+        return Err(StdError::generic_err(format!(
+            "Token address and code hash must be at most {} bytes.",
+            MAX_TOKEN_FIELD_LENGTH
+        )));
+    }
+
+    Ok(())
 }
 
 fn get_next_position<S: Storage>(store: &mut S, for_address: &CanonicalAddr) -> StdResult<u32> {
@@ -0,0 +1,51 @@
+use crate::constants::PREFIX_VIEWER_GRANTS;
+use cosmwasm_std::{CanonicalAddr, HumanAddr, ReadonlyStorage, StdResult, Storage};
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+use schemars::JsonSchema;
+use secret_toolkit::storage::{TypedStore, TypedStoreMut};
+use serde::{Deserialize, Serialize};
+
+// Read-only access an owner gives another address to their Txs
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct ViewerGrant {
+    pub scope: ViewerScope,
+    // Block time from which the viewer can no longer see the Txs. Queries can't read the
+    // block time, so this is checked against the time the viewer sends with the Txs query.
+    pub expires_at: Option<u64>,
+}
+
+// Which of the owner's Txs the viewer can see. Unset restrictions allow everything.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Default, PartialEq)]
+pub struct ViewerScope {
+    pub tokens: Option<Vec<HumanAddr>>,
+    pub from_time: Option<u64>,
+    pub to_time: Option<u64>,
+    pub counterparties: Option<Vec<HumanAddr>>,
+}
+
+// Storage functions:
+pub fn viewer_grant<S: ReadonlyStorage>(
+    storage: &S,
+    owner: &CanonicalAddr,
+    viewer: &CanonicalAddr,
+) -> StdResult<Option<ViewerGrant>> {
+    let store =
+        ReadonlyPrefixedStorage::multilevel(&[PREFIX_VIEWER_GRANTS, owner.as_slice()], storage);
+    TypedStore::attach(&store).may_load(viewer.as_slice())
+}
+
+pub fn set_viewer_grant<S: Storage>(
+    storage: &mut S,
+    owner: &CanonicalAddr,
+    viewer: &CanonicalAddr,
+    grant: Option<&ViewerGrant>,
+) -> StdResult<()> {
+    let mut store = PrefixedStorage::multilevel(&[PREFIX_VIEWER_GRANTS, owner.as_slice()], storage);
+    match grant {
+        Some(grant) => TypedStoreMut::attach(&mut store).store(viewer.as_slice(), grant),
+        None => {
+            TypedStoreMut::<ViewerGrant, _>::attach(&mut store).remove(viewer.as_slice());
+            Ok(())
+        }
+    }
+}